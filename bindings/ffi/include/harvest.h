@@ -0,0 +1,54 @@
+/*
+ * The C API of harvestlib, in the `libharvest` library of `bindings/ffi`.
+ *
+ * A run streams the decoded events of a range of checkpoints as JSON objects, with the
+ * fields of the `--output ndjson --decode` lines of `harvest`. Its configuration is a JSON
+ * object whose fields are all optional:
+ *
+ *   start            first checkpoint, 0 by default
+ *   limit            number of checkpoints, following the chain if not set
+ *   packages         array of package IDs of the events to keep
+ *   event_types      array of event type patterns, e.g. "0xdee9::clob_v2::*"
+ *   filter           filter expression, as with `--filter`
+ *   full_node_url    full node decoding the events, mainnet by default
+ *   checkpoints_url  checkpoint store, mainnet by default
+ *   cache_folder     folder caching the downloaded checkpoints
+ *   concurrency      number of checkpoints downloaded concurrently, 5 by default
+ */
+#ifndef HARVEST_H
+#define HARVEST_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct HarvestHandle HarvestHandle;
+
+/* Start a run configured by the JSON object `config`, or the default one if null. Returns
+ * null if the configuration is invalid, e.g. has an unknown field, setting `error`, if not
+ * null, to the reason, to free with `harvest_free_string`. */
+HarvestHandle *harvest_start(const char *config, char **error);
+
+/* Wait for the next event of the run and set `event` to it, to free with
+ * `harvest_free_string`. Returns 1 for an event, 0 at the end of the run and -1 if the run
+ * failed, see `harvest_error`, or if `handle` or `event` is null. A handle is used by one
+ * thread at a time. */
+int harvest_next_event(HarvestHandle *handle, char **event);
+
+/* Stop the run, even if it is waiting for events, and free its handle. Does nothing for a
+ * null handle. */
+void harvest_stop(HarvestHandle *handle);
+
+/* Free an event returned by `harvest_next_event` or an error set by `harvest_start`. Does
+ * nothing for null. */
+void harvest_free_string(char *event);
+
+/* The error of the last failed `harvest_next_event` of the handle, or null, also for a
+ * null handle. Owned by the handle and valid until its next call of `harvest_next_event`. */
+const char *harvest_error(const HarvestHandle *handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* HARVEST_H */
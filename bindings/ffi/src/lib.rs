@@ -0,0 +1,142 @@
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+};
+
+use anyhow::{Context, Result};
+
+use harvestlib::embed::{start_stream, EventReceiver, StreamConfig};
+
+/// An error as a C string. The errors are returned to the caller rather than kept per
+/// thread, as the threads of callers such as goroutines change between calls.
+fn error_string(error: impl std::fmt::Display) -> CString {
+    // Errors holding a nul byte are truncated rather than lost
+    let message = error.to_string();
+    let message = message.split('\0').next().unwrap_or_default();
+    CString::new(message).expect("The nul bytes are removed")
+}
+
+/// A run started by `harvest_start`, see `include/harvest.h`.
+pub struct HarvestHandle {
+    receiver: EventReceiver,
+    /// The error of the last failed `harvest_next_event`, returned by `harvest_error`
+    error: Option<CString>,
+}
+
+/// Start a run configured by the JSON object `config`, with the fields of `StreamConfig`,
+/// returning its handle, or null if the configuration is invalid, setting `error` to the
+/// reason.
+///
+/// # Safety
+///
+/// `config` must be a valid nul-terminated string, or null for the default configuration,
+/// and `error` must be valid for writes, or null.
+#[no_mangle]
+pub unsafe extern "C" fn harvest_start(
+    config: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut HarvestHandle {
+    let start = || -> Result<HarvestHandle> {
+        let config: StreamConfig = match config.is_null() {
+            true => StreamConfig::default(),
+            false => {
+                let config = CStr::from_ptr(config)
+                    .to_str()
+                    .context("The configuration is not valid UTF-8")?;
+                serde_json::from_str(config).context("Invalid configuration")?
+            }
+        };
+        Ok(HarvestHandle {
+            receiver: start_stream(config)?,
+            error: None,
+        })
+    };
+    if !error.is_null() {
+        *error = ptr::null_mut();
+    }
+    match start() {
+        Ok(handle) => Box::into_raw(Box::new(handle)),
+        Err(e) => {
+            if !error.is_null() {
+                *error = error_string(format!("{e:#}")).into_raw();
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Wait for the next decoded event of the run, setting `event` to it as a JSON object to
+/// free with `harvest_free_string`. Returns 1 for an event, 0 at the end of the run and -1
+/// if the run failed, see `harvest_error`, or if `handle` or `event` is null.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by `harvest_start` and not yet stopped, used by one
+/// thread at a time, or null, and `event` must be valid for writes, or null.
+#[no_mangle]
+pub unsafe extern "C" fn harvest_next_event(
+    handle: *mut HarvestHandle,
+    event: *mut *mut c_char,
+) -> c_int {
+    let Some(handle) = handle.as_mut() else {
+        return -1;
+    };
+    if event.is_null() {
+        handle.error = Some(error_string("The event pointer is null"));
+        return -1;
+    }
+    *event = ptr::null_mut();
+    let error = match handle.receiver.blocking_recv() {
+        Some(Ok(json)) => match CString::new(json) {
+            Ok(json) => {
+                *event = json.into_raw();
+                return 1;
+            }
+            Err(e) => error_string(e),
+        },
+        Some(Err(e)) => error_string(e),
+        None => return 0,
+    };
+    handle.error = Some(error);
+    -1
+}
+
+/// Stop the run, even if its worker is waiting for events matching its configuration, and
+/// free its handle.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by `harvest_start` and not yet stopped, or null.
+#[no_mangle]
+pub unsafe extern "C" fn harvest_stop(handle: *mut HarvestHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Free an event returned by `harvest_next_event`, or an error returned by `harvest_start`.
+///
+/// # Safety
+///
+/// `event` must be such a string not yet freed, or null.
+#[no_mangle]
+pub unsafe extern "C" fn harvest_free_string(event: *mut c_char) {
+    if !event.is_null() {
+        drop(CString::from_raw(event));
+    }
+}
+
+/// The error of the last failed `harvest_next_event` of the handle, or null, also for a
+/// null handle. The string is owned by the handle and valid until its next call of
+/// `harvest_next_event`.
+///
+/// # Safety
+///
+/// `handle` must be a handle returned by `harvest_start` and not yet stopped, or null.
+#[no_mangle]
+pub unsafe extern "C" fn harvest_error(handle: *const HarvestHandle) -> *const c_char {
+    handle
+        .as_ref()
+        .and_then(|handle| handle.error.as_ref())
+        .map_or(ptr::null(), |error| error.as_ptr())
+}
@@ -0,0 +1,100 @@
+use std::{path::PathBuf, sync::Mutex};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyStopAsyncIteration, PyValueError},
+    prelude::*,
+};
+
+use harvestlib::embed::{start_stream, EventReceiver, StreamConfig};
+
+/// An async iterator over the decoded events of a run, as the dicts of `DecodedEvent`, or of
+/// `JsonEvent` with the BCS contents for the events that cannot be decoded. Dropping it stops
+/// the run.
+#[pyclass(module = "harvestlib")]
+struct EventStream {
+    receiver: Mutex<EventReceiver>,
+}
+
+#[pymethods]
+impl EventStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Wait for the next event in the default executor of the event loop, whose threads
+    /// `asyncio.run` joins before the interpreter exits.
+    fn __anext__<'py>(slf: &Bound<'py, Self>) -> PyResult<Bound<'py, PyAny>> {
+        let py = slf.py();
+        let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+        event_loop.call_method1("run_in_executor", (py.None(), slf.getattr("_next_event")?))
+    }
+
+    fn _next_event(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let next = py.allow_threads(|| {
+            self.receiver
+                .lock()
+                .map(|mut receiver| receiver.blocking_recv())
+                .map_err(|_| PyRuntimeError::new_err("A previous read of the stream panicked"))
+        })?;
+        let json = match next {
+            Some(Ok(json)) => json,
+            Some(Err(e)) => return Err(PyRuntimeError::new_err(e)),
+            None => return Err(PyStopAsyncIteration::new_err(())),
+        };
+        Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+    }
+}
+
+/// Stream the decoded events of `limit` checkpoints from `start`, or following the chain
+/// if `limit` is not set, keeping those matching `packages`, `event_types` and `filter` like
+/// the `--package`, `--event-type` and `--filter` options of `harvest`.
+#[pyfunction]
+#[pyo3(signature = (
+    start,
+    limit = None,
+    packages = Vec::new(),
+    event_types = Vec::new(),
+    filter = String::new(),
+    full_node_url = None,
+    checkpoints_url = None,
+    cache_folder = None,
+    concurrency = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn events(
+    start: u64,
+    limit: Option<u64>,
+    packages: Vec<String>,
+    event_types: Vec<String>,
+    filter: String,
+    full_node_url: Option<String>,
+    checkpoints_url: Option<String>,
+    cache_folder: Option<PathBuf>,
+    concurrency: Option<usize>,
+) -> PyResult<EventStream> {
+    let defaults = StreamConfig::default();
+    let config = StreamConfig {
+        start,
+        limit,
+        packages,
+        event_types,
+        filter,
+        full_node_url: full_node_url.unwrap_or(defaults.full_node_url),
+        checkpoints_url: checkpoints_url.unwrap_or(defaults.checkpoints_url),
+        cache_folder,
+        concurrency: concurrency.unwrap_or(defaults.concurrency),
+    };
+    let receiver = start_stream(config).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(EventStream {
+        receiver: Mutex::new(receiver),
+    })
+}
+
+/// The `harvestlib` Python module, built with `maturin develop --release`.
+#[pymodule]
+#[pyo3(name = "harvestlib")]
+fn harvest_python(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<EventStream>()?;
+    module.add_function(wrap_pyfunction!(events, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,4 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/harvest.proto").expect("Cannot compile the gRPC protocol");
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    html,
+    labels::Labeled,
+    presets::{preset, PRESETS},
+    report::{EventHistogram, ReportOptions},
+    transactions::TransactionRecord,
+    EventRecord,
+};
+
+/// The outcome of an analyzer, printed once the run is over.
+pub struct AnalyzerReport {
+    /// The name of the analyzer that produced the report
+    pub name: &'static str,
+    /// The report as JSON
+    pub json: Value,
+    /// The report as human readable text, formatted when printed so that it is only
+    /// coloured on a terminal
+    pub text: Box<dyn fmt::Display + Send>,
+    /// The report as a section of an HTML page, if the analyzer supports it
+    pub html: Option<String>,
+}
+
+/// Consumes the extracted events of every checkpoint, in order, and summarizes them at the
+/// end of the run.
+pub trait Analyzer: Send {
+    fn name(&self) -> &'static str;
+
+    /// Called with every processed checkpoint, before its events.
+    fn on_checkpoint(&mut self, _summary: &CertifiedCheckpointSummary) {}
+
+    /// Consume an extracted event.
+    fn on_event(&mut self, record: &EventRecord);
+
+    /// Whether `on_transaction` should be called with the transactions of every checkpoint,
+    /// which requires extracting them alongside the events.
+    fn wants_transactions(&self) -> bool {
+        false
+    }
+
+    /// Consume a transaction of a processed checkpoint, see `wants_transactions`.
+    fn on_transaction(&mut self, _transaction: &TransactionRecord) {}
+
+    fn finish(self: Box<Self>) -> AnalyzerReport;
+}
+
+/// The analyzer called `name`, the histogram or a preset; `options` only apply to the
+/// histogram.
+pub fn analyzer(name: &str, options: &ReportOptions) -> Result<Box<dyn Analyzer>> {
+    match name {
+        "histogram" => Ok(Box::new(HistogramAnalyzer::new(options.clone()))),
+        _ if PRESETS.contains(&name) => preset(name),
+        _ => bail!(
+            "Unknown analyzer `{name}`, expected `histogram` or one of: {}",
+            PRESETS.join(", ")
+        ),
+    }
+}
+
+/// Counts the events by address, package and type, see `EventHistogram`.
+pub struct HistogramAnalyzer {
+    histogram: EventHistogram,
+    options: ReportOptions,
+}
+
+impl HistogramAnalyzer {
+    pub fn new(options: ReportOptions) -> Self {
+        let mut histogram = match options.time_buckets {
+            Some(width) => EventHistogram::with_time_buckets(width),
+            None => EventHistogram::default(),
+        };
+        if options.by_transaction {
+            histogram = histogram.with_transactions(options.top);
+        }
+        Self { histogram, options }
+    }
+}
+
+impl Analyzer for HistogramAnalyzer {
+    fn name(&self) -> &'static str {
+        "histogram"
+    }
+
+    fn on_checkpoint(&mut self, summary: &CertifiedCheckpointSummary) {
+        self.histogram.add_checkpoint(summary);
+    }
+
+    fn on_event(&mut self, record: &EventRecord) {
+        self.histogram.add(record);
+    }
+
+    fn wants_transactions(&self) -> bool {
+        self.options.by_call
+    }
+
+    fn on_transaction(&mut self, transaction: &TransactionRecord) {
+        self.histogram.add_calls(transaction);
+    }
+
+    fn finish(self: Box<Self>) -> AnalyzerReport {
+        let mut report = self.histogram.report(&self.options);
+        report.label(&self.options.labels);
+        AnalyzerReport {
+            name: "histogram",
+            json: serde_json::to_value(&report).unwrap_or(Value::Null),
+            html: Some(html::histogram_section(&report)),
+            text: Box::new(report),
+        }
+    }
+}
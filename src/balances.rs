@@ -0,0 +1,226 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use sui_types::{
+    base_types::SuiAddress,
+    digests::TransactionDigest,
+    full_checkpoint_content::{CheckpointData, CheckpointTransaction},
+    messages_checkpoint::{CertifiedCheckpointSummary, CheckpointSequenceNumber},
+    object::Owner,
+    TypeTag,
+};
+use url::Url;
+
+use crate::{
+    channel::{ChannelConfig, Receiver, Sender},
+    default_remote_store_urls,
+    error::HarvestResult,
+    fetcher::{FetchOptions, ReaderOptions},
+    metrics::HarvestMetrics,
+    run_ordered, Replay, Worker,
+};
+
+/// The net change of the balance of one coin type for one owner, caused by a transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub checkpoint_sequence_number: u64,
+    pub timestamp: u64,
+    pub digest: TransactionDigest,
+    pub owner: SuiAddress,
+    pub coin_type: TypeTag,
+    pub amount: i128,
+}
+
+/// Compute the balance changes of a transaction from the coins it reads and writes.
+pub fn balance_changes(tx: &CheckpointTransaction) -> HashMap<(SuiAddress, TypeTag), i128> {
+    let mut changes = HashMap::new();
+
+    let inputs = tx.input_objects.iter().map(|o| (o, -1));
+    let outputs = tx.output_objects.iter().map(|o| (o, 1));
+    for (object, sign) in inputs.chain(outputs) {
+        let (Some(coin_type), Some(coin)) = (object.coin_type_maybe(), object.as_coin_maybe())
+        else {
+            continue;
+        };
+        let owner = match object.owner {
+            Owner::AddressOwner(address) => address,
+            Owner::ObjectOwner(address) => address,
+            _ => continue,
+        };
+
+        *changes.entry((owner, coin_type)).or_insert(0) += sign * coin.value() as i128;
+    }
+
+    changes.retain(|_, amount| *amount != 0);
+    changes
+}
+
+pub struct BalanceChangeExtractWorker<F>
+where
+    F: Fn(&BalanceChange) -> bool,
+{
+    filter: F,
+    sender: Sender<(CertifiedCheckpointSummary, Vec<BalanceChange>)>,
+}
+
+impl<F> BalanceChangeExtractWorker<F>
+where
+    F: Fn(&BalanceChange) -> bool + Send + Sync + 'static,
+{
+    /// Configure a worker keeping the balance changes accepted by `filter`.
+    pub fn builder(filter: F) -> BalanceChangeExtractWorkerBuilder<F> {
+        BalanceChangeExtractWorkerBuilder {
+            filter,
+            start: 0,
+            limit: None,
+            remote_store_urls: default_remote_store_urls(),
+            concurrency: 5,
+            reader_options: None,
+            fetch_options: None,
+            cache_folder: None,
+            channel: None,
+            metrics: None,
+        }
+    }
+}
+
+/// Configures and starts a `BalanceChangeExtractWorker`, with the defaults of
+/// `EventExtractWorkerBuilder`.
+pub struct BalanceChangeExtractWorkerBuilder<F> {
+    filter: F,
+    start: u64,
+    limit: Option<u64>,
+    remote_store_urls: Vec<Url>,
+    concurrency: usize,
+    reader_options: Option<ReaderOptions>,
+    fetch_options: Option<FetchOptions>,
+    cache_folder: Option<PathBuf>,
+    channel: Option<ChannelConfig>,
+    metrics: Option<HarvestMetrics>,
+}
+
+impl<F> BalanceChangeExtractWorkerBuilder<F>
+where
+    F: Fn(&BalanceChange) -> bool + Send + Sync + 'static,
+{
+    /// First checkpoint to process.
+    pub fn start(mut self, start: u64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Number of checkpoints to process, unlimited by default.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// See `fetcher::CheckpointFetcher::new` for the supported URLs.
+    pub fn remote_store_url(mut self, url: Url) -> Self {
+        self.remote_store_urls = vec![url];
+        self
+    }
+
+    /// Spread the downloads over these stores, failing over between them.
+    pub fn remote_store_urls(mut self, urls: impl IntoIterator<Item = Url>) -> Self {
+        self.remote_store_urls = urls.into_iter().collect();
+        self
+    }
+
+    /// Number of checkpoints downloaded concurrently.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn reader_options(mut self, options: ReaderOptions) -> Self {
+        self.reader_options = Some(options);
+        self
+    }
+
+    pub fn fetch_options(mut self, options: FetchOptions) -> Self {
+        self.fetch_options = Some(options);
+        self
+    }
+
+    /// Cache the downloaded checkpoints in this folder.
+    pub fn cache_folder(mut self, folder: impl Into<PathBuf>) -> Self {
+        self.cache_folder = Some(folder.into());
+        self
+    }
+
+    pub fn channel(mut self, config: ChannelConfig) -> Self {
+        self.channel = Some(config);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: HarvestMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Start the worker, returning the future driving it and the receiver of the balance changes
+    /// of every checkpoint, in checkpoint order.
+    pub async fn build(
+        self,
+    ) -> HarvestResult<(
+        impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+        Receiver<(CertifiedCheckpointSummary, Vec<BalanceChange>)>,
+    )> {
+        let filter = self.filter;
+        let length = self.limit.unwrap_or(u64::MAX - self.start);
+        run_ordered(
+            |sender| BalanceChangeExtractWorker { filter, sender },
+            vec![self.start..self.start.saturating_add(length)],
+            self.remote_store_urls.into(),
+            self.concurrency,
+            self.reader_options,
+            self.fetch_options,
+            self.cache_folder,
+            self.channel,
+            self.metrics,
+        )
+        .await
+    }
+}
+
+impl<F> Replay for BalanceChangeExtractWorker<F> {}
+
+#[async_trait]
+impl<F> Worker for BalanceChangeExtractWorker<F>
+where
+    F: Fn(&BalanceChange) -> bool + Send + Sync,
+{
+    async fn process_checkpoint(&self, checkpoint: CheckpointData) -> Result<()> {
+        let CheckpointData {
+            checkpoint_summary,
+            checkpoint_contents: _, // We don't need this
+            transactions,
+        } = checkpoint;
+
+        let mut records = vec![];
+        for tx in &transactions {
+            for ((owner, coin_type), amount) in balance_changes(tx) {
+                let record = BalanceChange {
+                    checkpoint_sequence_number: checkpoint_summary.sequence_number,
+                    timestamp: checkpoint_summary.timestamp_ms,
+                    digest: *tx.transaction.digest(),
+                    owner,
+                    coin_type,
+                    amount,
+                };
+                if (self.filter)(&record) {
+                    records.push(record);
+                }
+            }
+        }
+
+        // Send them to the aggregator
+        self.sender.send((checkpoint_summary, records)).await?;
+
+        Ok(())
+    }
+}
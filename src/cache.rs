@@ -0,0 +1,215 @@
+#[cfg(feature = "cache-index")]
+mod index;
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Result};
+use tokio::io::AsyncWriteExt;
+
+#[cfg(feature = "cache-index")]
+pub use index::{clean, evict, CacheIndex, CacheStats, Eviction};
+
+/// Extension of the checkpoint files stored in the cache folder.
+pub const CHECKPOINT_EXTENSION: &str = "chk";
+
+/// Extension of the files cache entries are written to before being renamed into place.
+const TEMP_EXTENSION: &str = "tmp";
+
+/// File of the cache folder recording the chain of its checkpoints, for the runs reading
+/// the cache without network access.
+const CHAIN_FILE: &str = "chain";
+
+/// Header of the cache entries, followed by the version of their format. Entries of version
+/// 1 are compressed, those of version 2 then give their compression and the name of their
+/// network, and those of version 3 their compression and the identifier of their chain.
+const ENTRY_MAGIC: &[u8; 4] = b"HVST";
+const COMPRESSED_VERSION: u8 = 1;
+const NETWORK_VERSION: u8 = 2;
+const ENTRY_VERSION: u8 = 3;
+
+/// Writes a checkpoint file to the cache as it is downloaded, zstd-compressing it unless
+/// `level` is 0, without holding the whole file in memory. Its header records the `chain` of
+/// the checkpoint, if known. The entry is written to a file of its own and renamed once
+/// complete, so that processes sharing the cache folder never read a partial entry nor write
+/// to the same file.
+pub struct EntryWriter {
+    path: PathBuf,
+    temp: PathBuf,
+    file: tokio::fs::File,
+    encoder: Option<zstd::stream::write::Encoder<'static, Vec<u8>>>,
+    /// Number of bytes written to the file
+    size: u64,
+}
+
+impl EntryWriter {
+    pub async fn create(path: &Path, level: i32, chain: Option<&str>) -> Result<Self> {
+        let chain = chain.unwrap_or_default();
+        if chain.len() > u8::MAX as usize {
+            bail!("The chain identifier `{chain}` is too long");
+        }
+        static WRITERS: AtomicU64 = AtomicU64::new(0);
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(format!(
+            ".{}-{}.{TEMP_EXTENSION}",
+            std::process::id(),
+            WRITERS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp = PathBuf::from(temp);
+
+        let mut header = ENTRY_MAGIC.to_vec();
+        header.extend([ENTRY_VERSION, (level != 0) as u8, chain.len() as u8]);
+        header.extend(chain.as_bytes());
+        let mut file = tokio::fs::File::create(&temp).await?;
+        file.write_all(&header).await?;
+        let size = header.len() as u64;
+        let encoder = match level {
+            0 => None,
+            level => Some(zstd::stream::write::Encoder::new(Vec::new(), level)?),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp,
+            file,
+            encoder,
+            size,
+        })
+    }
+
+    /// Append the next chunk of the checkpoint file.
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        match &mut self.encoder {
+            None => {
+                self.file.write_all(chunk).await?;
+                self.size += chunk.len() as u64;
+            }
+            Some(encoder) => {
+                encoder.write_all(chunk)?;
+                let compressed = encoder.get_mut();
+                self.file.write_all(compressed).await?;
+                self.size += compressed.len() as u64;
+                compressed.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Complete the entry, returning its size on disk and the entry opened for reading,
+    /// which evictions cannot take away.
+    pub async fn finish(mut self) -> Result<(u64, fs::File)> {
+        if let Some(encoder) = self.encoder.take() {
+            let compressed = encoder.finish()?;
+            self.file.write_all(&compressed).await?;
+            self.size += compressed.len() as u64;
+        }
+        // The entry must be on disk before it can be found under its name
+        self.file.sync_all().await?;
+        let reader = tokio::fs::File::open(&self.temp).await?.into_std().await;
+        tokio::fs::rename(&self.temp, &self.path).await?;
+        Ok((self.size, reader))
+    }
+
+    /// Give up on the entry, e.g. when its download failed.
+    pub async fn abort(self) {
+        drop(self.file);
+        let _ = tokio::fs::remove_file(&self.temp).await;
+    }
+}
+
+/// Open a cache entry for reading the checkpoint file back. Entries without the header are
+/// checkpoint files stored as they are, by earlier versions or in local stores, and entries
+/// of version 1 compressed ones of earlier versions.
+pub fn open_entry(path: &Path) -> Result<Box<dyn Read + Send>> {
+    read_entry(fs::File::open(path)?)
+}
+
+/// Like `open_entry`, for an already opened entry.
+pub fn read_entry(file: fs::File) -> Result<Box<dyn Read + Send>> {
+    let mut reader = BufReader::new(file);
+    let header = reader.fill_buf()?;
+    let version = match header.strip_prefix(ENTRY_MAGIC.as_slice()) {
+        Some([version, ..]) => *version,
+        _ => return Ok(Box::new(reader)),
+    };
+    reader.consume(ENTRY_MAGIC.len() + 1);
+    let compressed = match version {
+        COMPRESSED_VERSION => true,
+        NETWORK_VERSION | ENTRY_VERSION => {
+            let mut fields = [0; 2];
+            reader.read_exact(&mut fields)?;
+            let mut chain = vec![0; fields[1] as usize];
+            reader.read_exact(&mut chain)?;
+            fields[0] != 0
+        }
+        version => bail!(
+            "Unsupported cache entry version {version}, written by a later version of the \
+            harvester"
+        ),
+    };
+    Ok(match compressed {
+        true => Box::new(zstd::Decoder::with_buffer(reader)?),
+        false => Box::new(reader),
+    })
+}
+
+/// The chain identifier recorded in the header of the cache entry `file`, if any, leaving
+/// the file at its start for `read_entry`. Entries of earlier versions have none.
+pub fn entry_chain(mut file: &fs::File) -> Result<Option<String>> {
+    let mut header = Vec::new();
+    file.by_ref()
+        .take(ENTRY_MAGIC.len() as u64 + 3)
+        .read_to_end(&mut header)?;
+    let chain = match header.strip_prefix(ENTRY_MAGIC.as_slice()) {
+        Some([ENTRY_VERSION, _, len]) => {
+            let mut chain = vec![0; *len as usize];
+            file.read_exact(&mut chain)?;
+            Some(String::from_utf8(chain)?).filter(|chain| !chain.is_empty())
+        }
+        _ => None,
+    };
+    file.seek(SeekFrom::Start(0))?;
+    Ok(chain)
+}
+
+/// The chain of the checkpoints of `folder`, as recorded by the last run writing to it.
+pub fn read_chain(folder: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(folder.join(CHAIN_FILE)) {
+        Ok(chain) => Ok(Some(chain.trim().to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Record `chain` as that of the checkpoints of `folder`.
+pub fn write_chain(folder: &Path, chain: &str) -> Result<()> {
+    fs::create_dir_all(folder)?;
+    fs::write(folder.join(CHAIN_FILE), chain)?;
+    Ok(())
+}
+
+/// Limits on the checkpoints kept in the cache folder. Files are evicted in least recently
+/// used order, their last use being recorded in the index every time they are read.
+#[derive(Debug, Clone, Default)]
+pub struct CachePolicy {
+    /// Maximum total size of the cached checkpoints, in bytes
+    pub max_bytes: Option<u64>,
+    /// Cached checkpoints unused for longer than this are removed
+    pub ttl: Option<Duration>,
+}
+
+impl CachePolicy {
+    pub fn is_unbounded(&self) -> bool {
+        self.max_bytes.is_none() && self.ttl.is_none()
+    }
+
+    /// Whether an entry last used at `used`, as recorded by the index, outlived the TTL.
+    pub fn is_expired(&self, used: SystemTime) -> bool {
+        self.ttl
+            .is_some_and(|ttl| used.elapsed().unwrap_or_default() > ttl)
+    }
+}
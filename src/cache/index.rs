@@ -0,0 +1,399 @@
+use std::{
+    collections::HashSet,
+    fs::{self, TryLockError},
+    io,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+
+use super::{CachePolicy, CHECKPOINT_EXTENSION, TEMP_EXTENSION};
+
+/// Temporary files older than this were left by a process that did not finish writing them.
+const STALE_TEMP_AGE: Duration = Duration::from_secs(3600);
+
+/// File locked by the process evicting checkpoints from the cache folder.
+const LOCK_FILE: &str = ".lock";
+
+/// Index of the cache folder, shared by the processes using it.
+const INDEX_FILE: &str = "index.sqlite";
+
+const INDEX_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        sequence_number INTEGER PRIMARY KEY,
+        size INTEGER NOT NULL,
+        stored_at INTEGER NOT NULL,
+        used_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS entries_used_at ON entries (used_at);
+    CREATE TABLE IF NOT EXISTS counters (
+        name TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+";
+
+/// What a cache eviction removed.
+#[derive(Debug, Default)]
+pub struct Eviction {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Remove the cached checkpoints outliving the policy's TTL, then the least recently used
+/// ones until the cache fits in its maximum size, as recorded by the index of the folder.
+/// The checkpoints `in_use`, fetched but not read yet, are kept. Nothing is evicted while
+/// another process sharing the folder is evicting.
+pub fn evict(
+    folder: &Path,
+    index: &CacheIndex,
+    policy: &CachePolicy,
+    in_use: &HashSet<u64>,
+) -> Result<Eviction> {
+    let mut eviction = Eviction::default();
+    let Some(_lock) = lock_folder(folder, false)? else {
+        return Ok(eviction);
+    };
+    remove_stale_temps(folder)?;
+
+    // Oldest first
+    let entries = index.entries()?;
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    let mut removed = Vec::new();
+    for (sequence_number, size, used) in entries {
+        let over_size = policy.max_bytes.is_some_and(|max| total > max);
+        if (!over_size && !policy.is_expired(used)) || in_use.contains(&sequence_number) {
+            continue;
+        }
+        total -= size;
+        if remove(&entry_path(folder, sequence_number))? {
+            eviction.files += 1;
+            eviction.bytes += size;
+        }
+        removed.push(sequence_number);
+    }
+    index.remove(&removed)?;
+
+    Ok(eviction)
+}
+
+/// Remove all cached checkpoints, leaving the other files of the folder untouched.
+/// Waits for the evictions of other processes sharing the folder.
+pub fn clean(folder: &Path, index: &CacheIndex) -> Result<Eviction> {
+    let mut eviction = Eviction::default();
+    let _lock = lock_folder(folder, true)?;
+    remove_stale_temps(folder)?;
+    for (path, size, _) in cached_checkpoints(folder)? {
+        if remove(&path)? {
+            eviction.files += 1;
+            eviction.bytes += size;
+        }
+    }
+    index.rebuild(folder)?;
+    Ok(eviction)
+}
+
+/// What the index knows of the cache folder.
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    /// Number of cached checkpoints
+    pub checkpoints: u64,
+    /// Total size of the cached checkpoints, in bytes
+    pub bytes: u64,
+    /// Checkpoints read from the cache, over all runs
+    pub hits: u64,
+    /// Checkpoints downloaded as they were not in the cache, over all runs
+    pub misses: u64,
+    /// Ranges of consecutive cached checkpoints, first and last included
+    pub ranges: Vec<(u64, u64)>,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64)
+    }
+}
+
+/// The checkpoints of the cache folder with their sizes and times of storage and last use,
+/// and the cache hits and misses of the runs using it, so that neither evictions nor
+/// statistics scan the folder. It is rebuilt from the files of the folder when missing.
+pub struct CacheIndex {
+    connection: Mutex<Connection>,
+}
+
+impl CacheIndex {
+    pub fn open(folder: &Path) -> Result<Self> {
+        fs::create_dir_all(folder)?;
+        let path = folder.join(INDEX_FILE);
+        let exists = path.exists();
+        let connection = Connection::open(path)?;
+        // Other processes sharing the folder may be writing
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        connection.execute_batch(INDEX_SCHEMA)?;
+
+        let index = Self {
+            connection: Mutex::new(connection),
+        };
+        if !exists {
+            index.rebuild(folder)?;
+        }
+        Ok(index)
+    }
+
+    /// Open the index of `folder` without writing to it, if it exists.
+    pub fn open_read_only(folder: &Path) -> Result<Option<Self>> {
+        let path = folder.join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        connection.busy_timeout(Duration::from_secs(5))?;
+        Ok(Some(Self {
+            connection: Mutex::new(connection),
+        }))
+    }
+
+    /// Replace the entries of the index by the checkpoint files of the folder, returning
+    /// their number.
+    pub fn rebuild(&self, folder: &Path) -> Result<usize> {
+        let files = cached_checkpoints(folder)?;
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM entries", [])?;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT INTO entries (sequence_number, size, stored_at, used_at)
+                    VALUES (?1, ?2, ?3, ?3)
+                    ON CONFLICT (sequence_number) DO NOTHING",
+            )?;
+            for (path, size, modified) in &files {
+                let Some(sequence_number) = sequence_number(path) else {
+                    continue;
+                };
+                statement.execute(params![
+                    sequence_number as i64,
+                    *size as i64,
+                    unix_secs(*modified)
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(files.len())
+    }
+
+    /// Record a checkpoint written to the cache, after a miss.
+    pub fn insert(&self, sequence_number: u64, size: u64) -> Result<()> {
+        let now = unix_secs(SystemTime::now());
+        self.connection().execute(
+            "INSERT INTO entries (sequence_number, size, stored_at, used_at)
+                VALUES (?1, ?2, ?3, ?3)
+                ON CONFLICT (sequence_number) DO UPDATE
+                SET size = excluded.size, stored_at = excluded.stored_at, used_at = excluded.used_at",
+            params![sequence_number as i64, size as i64, now],
+        )?;
+        Ok(())
+    }
+
+    /// Record a checkpoint read from the cache.
+    pub fn hit(&self, sequence_number: u64) -> Result<()> {
+        let now = unix_secs(SystemTime::now());
+        let connection = self.connection();
+        connection.execute(
+            "UPDATE entries SET used_at = ?2 WHERE sequence_number = ?1",
+            params![sequence_number as i64, now],
+        )?;
+        increment(&connection, "hits")
+    }
+
+    /// Record a checkpoint missing from the cache.
+    pub fn miss(&self) -> Result<()> {
+        increment(&self.connection(), "misses")
+    }
+
+    pub fn stats(&self) -> Result<CacheStats> {
+        let connection = self.connection();
+        let (checkpoints, bytes): (i64, i64) = connection.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM entries",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let counter = |name: &str| -> Result<u64> {
+            let value: i64 = connection.query_row(
+                "SELECT COALESCE((SELECT value FROM counters WHERE name = ?1), 0)",
+                params![name],
+                |row| row.get(0),
+            )?;
+            Ok(value as u64)
+        };
+        let (hits, misses) = (counter("hits")?, counter("misses")?);
+
+        // Consecutive checkpoints have the same difference with their rank
+        let mut statement = connection.prepare(
+            "SELECT MIN(sequence_number), MAX(sequence_number) FROM (
+                SELECT sequence_number,
+                    sequence_number - ROW_NUMBER() OVER (ORDER BY sequence_number) AS run
+                FROM entries
+            ) GROUP BY run ORDER BY 1",
+        )?;
+        let ranges = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(CacheStats {
+            checkpoints: checkpoints as u64,
+            bytes: bytes as u64,
+            hits,
+            misses,
+            ranges,
+        })
+    }
+
+    /// Number and total size of the cached checkpoints in `start..=end`.
+    pub fn range_stats(&self, start: u64, end: u64) -> Result<(u64, u64)> {
+        let (checkpoints, bytes): (i64, i64) = self.connection().query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM entries
+            WHERE sequence_number BETWEEN ?1 AND ?2",
+            params![start as i64, end as i64],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((checkpoints as u64, bytes as u64))
+    }
+
+    /// The sequence number, size and last use of every entry, least recently used first.
+    fn entries(&self) -> Result<Vec<(u64, u64, SystemTime)>> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare("SELECT sequence_number, size, used_at FROM entries ORDER BY used_at")?;
+        let entries = statement
+            .query_map([], |row| {
+                let used_at = UNIX_EPOCH + Duration::from_secs(row.get::<_, i64>(2)? as u64);
+                Ok((
+                    row.get::<_, i64>(0)? as u64,
+                    row.get::<_, i64>(1)? as u64,
+                    used_at,
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(entries)
+    }
+
+    fn remove(&self, sequence_numbers: &[u64]) -> Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        {
+            let mut statement =
+                transaction.prepare("DELETE FROM entries WHERE sequence_number = ?1")?;
+            for sequence_number in sequence_numbers {
+                statement.execute(params![*sequence_number as i64])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn increment(connection: &Connection, counter: &str) -> Result<()> {
+    connection.execute(
+        "INSERT INTO counters (name, value) VALUES (?1, 1)
+            ON CONFLICT (name) DO UPDATE SET value = value + 1",
+        params![counter],
+    )?;
+    Ok(())
+}
+
+/// The path of the cache entry of a checkpoint.
+fn entry_path(folder: &Path, sequence_number: u64) -> PathBuf {
+    folder.join(format!("{sequence_number}.{CHECKPOINT_EXTENSION}"))
+}
+
+/// The sequence number of the checkpoint of a cache entry.
+fn sequence_number(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Lock the folder for eviction, waiting for other processes if `wait` is set, or returning
+/// `None` if one holds the lock. The lock is released when the file is dropped.
+fn lock_folder(folder: &Path, wait: bool) -> Result<Option<fs::File>> {
+    if !folder.exists() {
+        return Ok(None);
+    }
+    let file = fs::File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(folder.join(LOCK_FILE))?;
+    if wait {
+        file.lock()?;
+        return Ok(Some(file));
+    }
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Remove the temporary files left by processes that stopped while writing an entry.
+fn remove_stale_temps(folder: &Path) -> Result<()> {
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|e| e != TEMP_EXTENSION) {
+            continue;
+        }
+        let stale = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > STALE_TEMP_AGE);
+        if stale {
+            remove(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove a file, returning whether it was still there, as another process sharing the
+/// folder may have removed it first.
+fn remove(path: &Path) -> Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The path, size and modification time of every checkpoint file in the folder.
+fn cached_checkpoints(folder: &Path) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+    if !folder.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|e| e != CHECKPOINT_EXTENSION) {
+            continue;
+        }
+        // The file may have been removed concurrently
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        files.push((path, metadata.len(), metadata.modified()?));
+    }
+    Ok(files)
+}
@@ -0,0 +1,198 @@
+use std::{
+    collections::VecDeque,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{bail, Result};
+
+use crate::error::{HarvestError, HarvestResult};
+use tokio::sync::Notify;
+
+/// What the worker does when the channel to a slow consumer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait for the consumer, slowing down the download of checkpoints
+    Block,
+    /// Discard the oldest queued item to make room for the new one
+    DropOldest,
+    /// Discard the new item
+    DropNewest,
+}
+
+impl OverflowPolicy {
+    /// Whether items can be discarded, so that not every processed checkpoint reaches the
+    /// consumer.
+    pub fn is_lossy(self) -> bool {
+        self != Self::Block
+    }
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            "drop-newest" => Ok(Self::DropNewest),
+            _ => bail!(
+                "Unknown overflow policy `{s}`, expected `block`, `drop-oldest` or `drop-newest`"
+            ),
+        }
+    }
+}
+
+/// Capacity of the channel between the worker and its consumer by default, in number of
+/// checkpoints.
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Capacity of the channel between the worker and its consumer, in number of checkpoints.
+#[derive(Debug, Clone)]
+pub struct ChannelConfig {
+    /// `None` for an unbounded channel
+    pub capacity: Option<usize>,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for ChannelConfig {
+    /// Block the worker once `DEFAULT_CAPACITY` checkpoints are waiting for the consumer.
+    fn default() -> Self {
+        Self::bounded(DEFAULT_CAPACITY, OverflowPolicy::Block)
+    }
+}
+
+impl ChannelConfig {
+    pub fn bounded(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            capacity: Some(capacity.max(1)),
+            overflow,
+        }
+    }
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    sender_closed: bool,
+    receiver_closed: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    config: ChannelConfig,
+    not_empty: Notify,
+    not_full: Notify,
+    dropped: AtomicU64,
+}
+
+/// Create a single producer, single consumer channel following the configuration.
+pub fn channel<T>(config: ChannelConfig) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            sender_closed: false,
+            receiver_closed: false,
+        }),
+        config,
+        not_empty: Notify::new(),
+        not_full: Notify::new(),
+        dropped: AtomicU64::new(0),
+    });
+    (Sender(shared.clone()), Receiver(shared))
+}
+
+/// The sending half of the channel, held by the worker.
+pub struct Sender<T>(Arc<Shared<T>>);
+
+impl<T> Sender<T> {
+    /// Whether the receiver was dropped, so that nothing sent is received anymore.
+    pub fn is_closed(&self) -> bool {
+        self.0.state.lock().unwrap().receiver_closed
+    }
+
+    /// Queue an item, applying the overflow policy if the channel is full. Fails if the
+    /// receiver was dropped.
+    pub async fn send(&self, item: T) -> HarvestResult<()> {
+        let mut item = Some(item);
+        loop {
+            let notified = {
+                let mut state = self.0.state.lock().unwrap();
+                if state.receiver_closed {
+                    return Err(HarvestError::ReceiverDropped);
+                }
+
+                let full = self
+                    .0
+                    .config
+                    .capacity
+                    .is_some_and(|capacity| state.queue.len() >= capacity);
+                if full {
+                    match self.0.config.overflow {
+                        OverflowPolicy::Block => {}
+                        OverflowPolicy::DropOldest => {
+                            state.queue.pop_front();
+                            self.0.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        OverflowPolicy::DropNewest => {
+                            self.0.dropped.fetch_add(1, Ordering::Relaxed);
+                            return Ok(());
+                        }
+                    }
+                }
+
+                if !full || self.0.config.overflow != OverflowPolicy::Block {
+                    state.queue.push_back(item.take().unwrap());
+                    self.0.not_empty.notify_one();
+                    return Ok(());
+                }
+                self.0.not_full.notified()
+            };
+            notified.await;
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().sender_closed = true;
+        self.0.not_empty.notify_one();
+    }
+}
+
+/// The receiving half of the channel, held by the consumer.
+pub struct Receiver<T>(Arc<Shared<T>>);
+
+impl<T> Receiver<T> {
+    /// The next item, or `None` once the worker is done and the channel is drained.
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let notified = {
+                let mut state = self.0.state.lock().unwrap();
+                if let Some(item) = state.queue.pop_front() {
+                    self.0.not_full.notify_one();
+                    return Some(item);
+                }
+                if state.sender_closed {
+                    return None;
+                }
+                self.0.not_empty.notified()
+            };
+            notified.await;
+        }
+    }
+
+    /// Number of items discarded so far by the overflow policy.
+    pub fn dropped(&self) -> u64 {
+        self.0.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().receiver_closed = true;
+        self.0.not_full.notify_one();
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+
+/// Name of the task whose watermark is kept in the `ProgressStore` of the commits.
+pub const COMMIT_TASK: &str = "workflow";
+
+/// Persists the watermark of a task, the first checkpoint it has not processed, e.g. in the
+/// database the processed checkpoints are written to.
+#[async_trait]
+pub trait ProgressStore: Send {
+    /// The watermark of `task_name`, 0 if it never ran.
+    async fn load(&mut self, task_name: String) -> Result<CheckpointSequenceNumber>;
+
+    async fn save(
+        &mut self,
+        task_name: String,
+        checkpoint_number: CheckpointSequenceNumber,
+    ) -> Result<()>;
+}
+
+/// Acknowledges that the consumer processed a delivered checkpoint, e.g. wrote the data
+/// derived from it to a database. The persisted watermark only advances past acknowledged
+/// checkpoints, so a checkpoint whose commit is dropped is delivered again by the next run.
+#[must_use = "The watermark only advances past acknowledged checkpoints"]
+pub struct Commit {
+    sequence_number: u64,
+    log: Arc<CommitLog>,
+}
+
+impl Commit {
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    /// Acknowledge the checkpoint, persisting the watermark if it advanced. Checkpoints can
+    /// be acknowledged in any order, the watermark being the first unacknowledged one.
+    pub async fn ack(self) -> Result<()> {
+        self.log.acknowledge([self.sequence_number]).await
+    }
+}
+
+/// The acknowledged checkpoints past the watermark, and the store persisting it.
+pub(crate) struct CommitLog {
+    /// The first unacknowledged checkpoint, and the acknowledged ones after it
+    state: Mutex<(u64, BTreeSet<u64>)>,
+    /// The store, and the last watermark saved to it
+    store: tokio::sync::Mutex<(Box<dyn ProgressStore + Send>, u64)>,
+}
+
+impl CommitLog {
+    pub(crate) fn new(watermark: u64, store: Box<dyn ProgressStore + Send>) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new((watermark, BTreeSet::new())),
+            store: tokio::sync::Mutex::new((store, watermark)),
+        })
+    }
+
+    /// The commit handle of a delivered checkpoint.
+    pub(crate) fn commit(self: &Arc<Self>, sequence_number: u64) -> Commit {
+        Commit {
+            sequence_number,
+            log: self.clone(),
+        }
+    }
+
+    /// Mark checkpoints as processed, e.g. those skipped by the worker, which are never
+    /// delivered, and persist the watermark if it advanced.
+    pub(crate) async fn acknowledge(
+        &self,
+        sequence_numbers: impl IntoIterator<Item = u64>,
+    ) -> Result<()> {
+        let advanced = {
+            let mut state = self.state.lock().unwrap();
+            let (watermark, acknowledged) = &mut *state;
+            let before = *watermark;
+            acknowledged.extend(sequence_numbers.into_iter().filter(|s| *s >= before));
+            while acknowledged.remove(watermark) {
+                *watermark += 1;
+            }
+            *watermark > before
+        };
+        if !advanced {
+            return Ok(());
+        }
+
+        // Concurrent acknowledgements save the latest watermark, never an older one
+        let mut store = self.store.lock().await;
+        let watermark = self.state.lock().unwrap().0;
+        if watermark > store.1 {
+            store.0.save(COMMIT_TASK.to_string(), watermark).await?;
+            store.1 = watermark;
+        }
+        Ok(())
+    }
+}
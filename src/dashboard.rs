@@ -0,0 +1,164 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Sparkline, Table},
+    Frame,
+};
+
+use crate::{metrics::HarvestMetrics, report::type_to_short_string, EventRecord};
+
+/// How often the dashboard is redrawn
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+/// Number of seconds of history shown by the event rate sparkline
+const RATE_HISTORY: usize = 120;
+/// Number of event types shown in the table
+const TOP_TYPES: usize = 30;
+
+#[derive(Default)]
+struct State {
+    by_type: HashMap<String, u64>,
+    total: u64,
+    /// Number of events in each of the last seconds, the most recent last
+    rates: VecDeque<u64>,
+    current: u64,
+}
+
+/// Feeds the dashboard with the events processed by the consumer.
+#[derive(Clone)]
+pub struct DashboardHandle(Arc<Mutex<State>>);
+
+impl DashboardHandle {
+    pub fn add(&self, records: &[EventRecord]) {
+        let mut state = self.0.lock().unwrap();
+        for (_index, _id, event) in records {
+            *state
+                .by_type
+                .entry(type_to_short_string(&event.type_))
+                .or_default() += 1;
+        }
+        state.total += records.len() as u64;
+        state.current += records.len() as u64;
+    }
+}
+
+/// A live terminal view of the top event types, the event rate, the checkpoint lag and
+/// the cache usage.
+pub struct Dashboard {
+    state: Arc<Mutex<State>>,
+    metrics: HarvestMetrics,
+}
+
+impl Dashboard {
+    pub fn new(metrics: HarvestMetrics) -> Self {
+        Self {
+            state: Arc::default(),
+            metrics,
+        }
+    }
+
+    pub fn handle(&self) -> DashboardHandle {
+        DashboardHandle(self.state.clone())
+    }
+
+    /// Take over the terminal and redraw the dashboard until `q` or `Ctrl-C` is pressed.
+    /// This blocks the calling thread.
+    pub fn run(self) -> Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    fn event_loop(&self, terminal: &mut ratatui::DefaultTerminal) -> Result<()> {
+        let mut last_second = Instant::now();
+        loop {
+            if last_second.elapsed() >= Duration::from_secs(1) {
+                last_second = Instant::now();
+                let mut state = self.state.lock().unwrap();
+                let current = std::mem::take(&mut state.current);
+                state.rates.push_back(current);
+                if state.rates.len() > RATE_HISTORY {
+                    state.rates.pop_front();
+                }
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(REFRESH_INTERVAL)? {
+                if let Event::Key(key) = event::read()? {
+                    let ctrl_c = key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || ctrl_c)
+                    {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let state = self.state.lock().unwrap();
+        let [status, rates, types] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Length(8),
+            Constraint::Min(0),
+        ])
+        .areas(frame.area());
+
+        let (hits, misses) = (
+            self.metrics.cache_hits.get(),
+            self.metrics.cache_misses.get(),
+        );
+        let hit_rate = match hits + misses {
+            0 => 0.0,
+            total => 100.0 * hits as f64 / total as f64,
+        };
+        let lines = vec![
+            Line::from(format!(
+                "Checkpoint {}  |  lag {}  |  {} checkpoints and {} events processed",
+                self.metrics.latest_checkpoint.get(),
+                self.metrics.checkpoint_lag.get(),
+                self.metrics.checkpoints_processed.get(),
+                state.total
+            )),
+            Line::from(format!(
+                "Cache: {hits} hits, {misses} misses ({hit_rate:.1}% hit rate)  |  press q to quit"
+            )),
+        ];
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("sui-harvest")),
+            status,
+        );
+
+        let rate = state.rates.back().copied().unwrap_or_default();
+        let history: Vec<u64> = state.rates.iter().copied().collect();
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(format!("Events/s ({rate})")))
+                .data(&history),
+            rates,
+        );
+
+        let mut by_type: Vec<_> = state.by_type.iter().collect();
+        by_type.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let rows = by_type
+            .into_iter()
+            .take(TOP_TYPES)
+            .map(|(type_, count)| Row::new(vec![count.to_string(), type_.clone()]));
+        frame.render_widget(
+            Table::new(rows, [Constraint::Length(10), Constraint::Min(0)])
+                .header(Row::new(vec!["Count", "Type"]))
+                .block(Block::bordered().title("Top event types")),
+            types,
+        );
+    }
+}
@@ -4,20 +4,23 @@ use std::{
     sync::Arc,
 };
 
-use rocksdb::{DB, ColumnFamilyDescriptor, Options, WriteBatch};
+use rocksdb::{ColumnFamilyDescriptor, Options, WriteBatch, DB};
 
 use anyhow::Result;
-use futures::{stream::{FuturesOrdered, FuturesUnordered}, StreamExt};
+use futures::{
+    stream::{FuturesOrdered, FuturesUnordered},
+    StreamExt,
+};
 use sui_sdk::SuiClientBuilder;
 
 use async_trait::async_trait;
 
-use sui_data_ingestion_core::Worker;
+use harvestlib::Worker;
 
 use sui_types::{base_types::SuiAddress, full_checkpoint_content::CheckpointData, object::Owner};
 
-use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 use tokio::signal::ctrl_c;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
 
 use flate2::write::GzEncoder;
 use flate2::Compression;
@@ -55,7 +58,6 @@ pub struct IdentifierIndexWorker {
 
 impl IdentifierIndexWorker {
     pub async fn run() {
-
         let args = Args::parse();
 
         // define the events folder
@@ -99,11 +101,11 @@ impl IdentifierIndexWorker {
         // let url = Url::parse(&remote_store_url).expect("Cannot parse url");
         // let (store, _path) = object_store::parse_url(&url).expect("Failed to open store from url");
         let store = object_store::http::HttpBuilder::new()
-        .with_url(remote_store_url)
-        // .with_client_options(client_options)
-        // .with_retry(5)
-        .build()
-        .expect("Failed to build http store");
+            .with_url(remote_store_url)
+            // .with_client_options(client_options)
+            // .with_retry(5)
+            .build()
+            .expect("Failed to build http store");
 
         // A tokio that downloads checkpoint data and sends it to the worker
         let join = tokio::spawn(async move {
@@ -114,7 +116,6 @@ impl IdentifierIndexWorker {
             loop {
                 while fut.len() < concurrency {
                     let future = async {
-
                         let xxx = checkpoint_number.fetch_add(1, Ordering::SeqCst);
 
                         let path = Path::from(format!("{}.chk", xxx));
@@ -156,7 +157,7 @@ impl IdentifierIndexWorker {
                     // all_data.extend(index_terms);
                     initial += 1;
 
-                    println!("Checkpoint: {} transactions: {}", checkpoint_seq, all_txs);
+                    tracing::info!("Checkpoint: {} transactions: {}", checkpoint_seq, all_txs);
 
                     let id_handle = DB::cf_handle(&db, "id_table").unwrap();
                     let mut batch = WriteBatch::default();
@@ -175,7 +176,6 @@ impl IdentifierIndexWorker {
                     // Update the next checkpoint in the _next file
                     let first = checkpoint_seq + 1;
                     std::fs::write(&next_checkpoint_file, (first).to_string()).unwrap();
-
                 }
             }
         });
@@ -184,14 +184,13 @@ impl IdentifierIndexWorker {
 
         match ctrl_c().await {
             Ok(_) => {
-                println!("Ctrl-C received, shutting down");
+                tracing::info!("Ctrl-C received, shutting down");
                 db_close.flush().unwrap();
             }
             Err(e) => {
-                eprintln!("Error: {}", e);
+                tracing::error!("Error: {}", e);
             }
         }
-
     }
 }
 
@@ -212,7 +211,6 @@ impl Worker for IdentifierIndexWorker {
         let mut identifiers: HashSet<SuiAddress> = HashSet::new();
 
         for (seq, transaction) in checkpoint.transactions.iter().enumerate() {
-
             // Extract events
             if transaction.events.is_some() {
                 for e in transaction.events.as_ref().unwrap().data.iter() {
@@ -256,7 +254,6 @@ impl Worker for IdentifierIndexWorker {
                     }
                     Some((_, id)) => {
                         identifiers.insert(id.into());
-
                     }
                     None => {}
                 }
@@ -286,12 +283,18 @@ impl Worker for IdentifierIndexWorker {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn,db_index=info")),
+        )
+        .with_writer(std::io::stderr)
+        .init();
 
     let full_node_url = "https://fullnode.mainnet.sui.io:443";
 
     let sui_mainnet = SuiClientBuilder::default().build(full_node_url).await?;
-    println!("Sui mainnet version: {}", sui_mainnet.api_version());
+    tracing::info!("Sui mainnet version: {}", sui_mainnet.api_version());
 
     // Get and print the latest checkpoint
     let _latest_checkpoint = sui_mainnet
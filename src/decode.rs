@@ -0,0 +1,395 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use futures::future::BoxFuture;
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{StructTag, TypeTag},
+    u256::U256,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sui_sdk::{
+    rpc_types::{SuiMoveNormalizedModule, SuiMoveNormalizedType, SuiObjectDataOptions},
+    SuiClient,
+};
+use sui_types::{base_types::ObjectID, MOVE_STDLIB_ADDRESS, SUI_FRAMEWORK_ADDRESS};
+
+use crate::{sinks::JsonEvent, EventRecord};
+
+/// Maximum number of objects the full node returns per `multi_get_object` request.
+const MULTI_GET_LIMIT: usize = 50;
+
+/// How long the objects that cannot be found, e.g. deleted or wrapped ones, are not looked
+/// up again, as they may be unwrapped later.
+const UNRESOLVED_TTL: Duration = Duration::from_secs(300);
+
+/// An event whose BCS contents have been decoded into JSON fields.
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedEvent {
+    #[serde(flatten)]
+    pub event: JsonEvent,
+    pub fields: Value,
+    /// Types of the objects whose IDs appear in the fields, when resolved
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub objects: BTreeMap<ObjectID, String>,
+}
+
+/// The fully resolved layout of a Move value.
+#[derive(Debug, Clone)]
+enum Layout {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    U256,
+    Address,
+    Vector(Box<Layout>),
+    Struct {
+        type_: StructTag,
+        fields: Vec<(String, Layout)>,
+    },
+}
+
+/// Decodes event contents using the Move struct layouts published on chain. Modules
+/// and layouts are fetched from the full node once and cached.
+pub struct EventDecoder {
+    client: SuiClient,
+    modules: HashMap<ObjectID, BTreeMap<String, SuiMoveNormalizedModule>>,
+    layouts: HashMap<StructTag, Layout>,
+    /// Types of the objects seen so far, if resolved
+    object_types: Option<ObjectTypes>,
+}
+
+#[derive(Default)]
+struct ObjectTypes {
+    found: HashMap<ObjectID, String>,
+    /// When the objects that could not be found were last looked up
+    unresolved: HashMap<ObjectID, Instant>,
+}
+
+impl EventDecoder {
+    pub fn new(client: SuiClient) -> Self {
+        Self {
+            client,
+            modules: HashMap::new(),
+            layouts: HashMap::new(),
+            object_types: None,
+        }
+    }
+
+    /// Also look up the type of the objects whose `ID` appears in the events, e.g. to tell
+    /// what a `Kiosk` or `Table` event is about. Types are fetched once per object.
+    pub fn with_object_types(mut self) -> Self {
+        self.object_types = Some(ObjectTypes::default());
+        self
+    }
+
+    pub async fn decode(&mut self, record: &EventRecord) -> Result<DecodedEvent> {
+        let event = &record.2;
+        let layout = self.struct_layout(&event.type_).await?;
+
+        let mut reader = Reader(&event.contents);
+        let mut ids = Vec::new();
+        let fields = decode_value(&layout, &mut reader, &mut ids)?;
+        if !reader.0.is_empty() {
+            bail!(
+                "{} trailing bytes after decoding {}",
+                reader.0.len(),
+                event.type_
+            );
+        }
+
+        Ok(DecodedEvent {
+            event: JsonEvent::from(record),
+            fields,
+            objects: self.resolve_object_types(ids).await,
+        })
+    }
+
+    /// The types of the objects `ids`, leaving out those that cannot be resolved, including
+    /// when the full node fails.
+    async fn resolve_object_types(&mut self, ids: Vec<ObjectID>) -> BTreeMap<ObjectID, String> {
+        let Some(object_types) = self.object_types.as_mut() else {
+            return BTreeMap::new();
+        };
+
+        let now = Instant::now();
+        let mut missing: Vec<_> = ids
+            .iter()
+            .filter(|id| {
+                !object_types.found.contains_key(*id)
+                    && object_types
+                        .unresolved
+                        .get(*id)
+                        .is_none_or(|since| now.duration_since(*since) >= UNRESOLVED_TTL)
+            })
+            .copied()
+            .collect();
+        missing.sort();
+        missing.dedup();
+        for chunk in missing.chunks(MULTI_GET_LIMIT) {
+            let responses = match self
+                .client
+                .read_api()
+                .multi_get_object_with_options(
+                    chunk.to_vec(),
+                    SuiObjectDataOptions::new().with_type(),
+                )
+                .await
+            {
+                Ok(responses) => responses,
+                Err(e) => {
+                    tracing::warn!("Cannot resolve the types of {} objects: {e}", chunk.len());
+                    continue;
+                }
+            };
+            // Deleted and wrapped objects are not found
+            for (id, response) in chunk.iter().zip(responses) {
+                match response.data.and_then(|data| data.type_) {
+                    Some(type_) => {
+                        object_types.unresolved.remove(id);
+                        object_types.found.insert(*id, type_.to_string());
+                    }
+                    None => {
+                        object_types.unresolved.insert(*id, now);
+                    }
+                }
+            }
+        }
+
+        ids.into_iter()
+            .filter_map(|id| Some((id, object_types.found.get(&id)?.clone())))
+            .collect()
+    }
+
+    async fn module(
+        &mut self,
+        package: ObjectID,
+        module: &str,
+    ) -> Result<&SuiMoveNormalizedModule> {
+        if !self.modules.contains_key(&package) {
+            let modules = self
+                .client
+                .read_api()
+                .get_normalized_move_modules_by_package(package)
+                .await?;
+            self.modules.insert(package, modules);
+        }
+        self.modules[&package]
+            .get(module)
+            .ok_or_else(|| anyhow!("Module {module} not found in package {package}"))
+    }
+
+    fn layout<'a>(&'a mut self, tag: &'a TypeTag) -> BoxFuture<'a, Result<Layout>> {
+        Box::pin(async move {
+            Ok(match tag {
+                TypeTag::Bool => Layout::Bool,
+                TypeTag::U8 => Layout::U8,
+                TypeTag::U16 => Layout::U16,
+                TypeTag::U32 => Layout::U32,
+                TypeTag::U64 => Layout::U64,
+                TypeTag::U128 => Layout::U128,
+                TypeTag::U256 => Layout::U256,
+                TypeTag::Address => Layout::Address,
+                TypeTag::Signer => bail!("Signer values cannot appear in events"),
+                TypeTag::Vector(inner) => Layout::Vector(Box::new(self.layout(inner).await?)),
+                TypeTag::Struct(struct_tag) => self.struct_layout(struct_tag).await?,
+            })
+        })
+    }
+
+    async fn struct_layout(&mut self, tag: &StructTag) -> Result<Layout> {
+        if let Some(layout) = self.layouts.get(tag) {
+            return Ok(layout.clone());
+        }
+
+        let field_types = {
+            let module = self.module(tag.address.into(), tag.module.as_str()).await?;
+            let definition = module
+                .structs
+                .get(tag.name.as_str())
+                .ok_or_else(|| anyhow!("Struct {} not found", tag))?;
+            definition
+                .fields
+                .iter()
+                .map(|field| {
+                    Ok((
+                        field.name.clone(),
+                        to_type_tag(&field.type_, &tag.type_params)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let mut fields = Vec::with_capacity(field_types.len());
+        for (name, type_) in field_types {
+            fields.push((name, self.layout(&type_).await?));
+        }
+
+        let layout = Layout::Struct {
+            type_: tag.clone(),
+            fields,
+        };
+        self.layouts.insert(tag.clone(), layout.clone());
+        Ok(layout)
+    }
+}
+
+/// Instantiate a normalized field type with the type arguments of its struct.
+fn to_type_tag(type_: &SuiMoveNormalizedType, type_args: &[TypeTag]) -> Result<TypeTag> {
+    Ok(match type_ {
+        SuiMoveNormalizedType::Bool => TypeTag::Bool,
+        SuiMoveNormalizedType::U8 => TypeTag::U8,
+        SuiMoveNormalizedType::U16 => TypeTag::U16,
+        SuiMoveNormalizedType::U32 => TypeTag::U32,
+        SuiMoveNormalizedType::U64 => TypeTag::U64,
+        SuiMoveNormalizedType::U128 => TypeTag::U128,
+        SuiMoveNormalizedType::U256 => TypeTag::U256,
+        SuiMoveNormalizedType::Address => TypeTag::Address,
+        SuiMoveNormalizedType::Signer => TypeTag::Signer,
+        SuiMoveNormalizedType::Struct {
+            address,
+            module,
+            name,
+            type_arguments,
+        } => TypeTag::Struct(Box::new(StructTag {
+            address: AccountAddress::from_hex_literal(address)?,
+            module: Identifier::new(module.as_str())?,
+            name: Identifier::new(name.as_str())?,
+            type_params: type_arguments
+                .iter()
+                .map(|arg| to_type_tag(arg, type_args))
+                .collect::<Result<_>>()?,
+        })),
+        SuiMoveNormalizedType::Vector(inner) => {
+            TypeTag::Vector(Box::new(to_type_tag(inner, type_args)?))
+        }
+        SuiMoveNormalizedType::TypeParameter(index) => type_args
+            .get(*index as usize)
+            .cloned()
+            .ok_or_else(|| anyhow!("Missing type argument {index}"))?,
+        SuiMoveNormalizedType::Reference(_) | SuiMoveNormalizedType::MutableReference(_) => {
+            bail!("References cannot appear in struct fields")
+        }
+    })
+}
+
+/// A cursor over BCS encoded bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.0.len() < n {
+            bail!("Unexpected end of input");
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self
+            .take(N)?
+            .try_into()
+            .expect("Slice has the requested length"))
+    }
+
+    fn uleb128(&mut self) -> Result<usize> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value as usize);
+            }
+        }
+        bail!("Invalid ULEB128 length")
+    }
+}
+
+/// Decode a value, collecting the object IDs it contains into `ids`.
+fn decode_value(layout: &Layout, reader: &mut Reader, ids: &mut Vec<ObjectID>) -> Result<Value> {
+    Ok(match layout {
+        Layout::Bool => match reader.take(1)?[0] {
+            0 => Value::Bool(false),
+            1 => Value::Bool(true),
+            byte => bail!("Invalid boolean {byte}"),
+        },
+        Layout::U8 => reader.take(1)?[0].into(),
+        Layout::U16 => u16::from_le_bytes(reader.take_array()?).into(),
+        Layout::U32 => u32::from_le_bytes(reader.take_array()?).into(),
+        // Large integers are strings, as they do not fit in a JSON number
+        Layout::U64 => u64::from_le_bytes(reader.take_array()?).to_string().into(),
+        Layout::U128 => u128::from_le_bytes(reader.take_array()?).to_string().into(),
+        Layout::U256 => U256::from_le_bytes(&reader.take_array()?)
+            .to_string()
+            .into(),
+        Layout::Address => AccountAddress::new(reader.take_array()?)
+            .to_canonical_string(true)
+            .into(),
+        Layout::Vector(inner) => {
+            let length = reader.uleb128()?;
+            (0..length)
+                .map(|_| decode_value(inner, reader, ids))
+                .collect::<Result<Vec<_>>>()?
+                .into()
+        }
+        Layout::Struct { type_, fields } => {
+            let mut map = Map::new();
+            for (name, layout) in fields {
+                map.insert(name.clone(), decode_value(layout, reader, ids)?);
+            }
+            let value = simplify_struct(type_, map);
+            if is_object_id(type_) {
+                if let Some(id) = value.as_str().and_then(|id| id.parse().ok()) {
+                    ids.push(id);
+                }
+            }
+            value
+        }
+    })
+}
+
+fn is_object_id(type_: &StructTag) -> bool {
+    type_.address == SUI_FRAMEWORK_ADDRESS
+        && type_.module.as_str() == "object"
+        && type_.name.as_str() == "ID"
+}
+
+/// Present well-known framework types the way the Sui JSON-RPC does.
+fn simplify_struct(type_: &StructTag, mut fields: Map<String, Value>) -> Value {
+    let address = type_.address;
+    match (type_.module.as_str(), type_.name.as_str()) {
+        ("string", "String") | ("ascii", "String") if address == MOVE_STDLIB_ADDRESS => {
+            let bytes = fields
+                .get("bytes")
+                .and_then(Value::as_array)
+                .map(|bytes| {
+                    bytes
+                        .iter()
+                        .filter_map(|b| b.as_u64().map(|b| b as u8))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            String::from_utf8_lossy(&bytes).into_owned().into()
+        }
+        ("option", "Option") if address == MOVE_STDLIB_ADDRESS => match fields.remove("vec") {
+            Some(Value::Array(mut values)) => values.pop().unwrap_or(Value::Null),
+            _ => Value::Null,
+        },
+        ("object", "UID") if address == SUI_FRAMEWORK_ADDRESS => {
+            fields.remove("id").unwrap_or(Value::Null)
+        }
+        ("object", "ID") if address == SUI_FRAMEWORK_ADDRESS => {
+            fields.remove("bytes").unwrap_or(Value::Null)
+        }
+        _ => Value::Object(fields),
+    }
+}
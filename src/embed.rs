@@ -0,0 +1,154 @@
+use std::{path::PathBuf, sync::OnceLock};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sui_sdk::SuiClientBuilder;
+use tokio::{
+    runtime::Runtime,
+    sync::{mpsc, oneshot},
+};
+use url::Url;
+
+use crate::{
+    decode::EventDecoder, error::HarvestError, filter::EventFilter, sinks::JsonEvent,
+    EventExtractWorker, EventExtractWorkerBuilder, EventRecord, MAINNET_CHECKPOINTS_URL,
+};
+
+/// Number of decoded events waiting for the consumer before the worker blocks.
+const STREAM_CAPACITY: usize = 1024;
+
+/// A run streaming its decoded events as JSON to the bindings of other languages. Dropping
+/// it stops the run, even if its worker has no event to send.
+pub struct EventReceiver {
+    receiver: mpsc::Receiver<Result<String, String>>,
+    /// Dropped with the receiver, which cancels the run
+    _stop: oneshot::Sender<()>,
+}
+
+impl EventReceiver {
+    /// Wait for the next decoded event, or the error that stopped the run. Returns `None`
+    /// at the end of the run.
+    pub fn blocking_recv(&mut self) -> Option<Result<String, String>> {
+        self.receiver.blocking_recv()
+    }
+}
+
+/// The run streamed to the bindings, with the same defaults and criteria as `harvest`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamConfig {
+    pub start: u64,
+    /// Number of checkpoints to process, following the chain if not set
+    pub limit: Option<u64>,
+    pub packages: Vec<String>,
+    pub event_types: Vec<String>,
+    /// An expression like that of `--filter`, matching every event if empty
+    pub filter: String,
+    pub full_node_url: String,
+    pub checkpoints_url: String,
+    pub cache_folder: Option<PathBuf>,
+    pub concurrency: usize,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            start: 0,
+            limit: None,
+            packages: Vec::new(),
+            event_types: Vec::new(),
+            filter: String::new(),
+            full_node_url: "https://fullnode.mainnet.sui.io:443".to_string(),
+            checkpoints_url: MAINNET_CHECKPOINTS_URL.to_string(),
+            cache_folder: None,
+            concurrency: 5,
+        }
+    }
+}
+
+/// The runtime of the workers of every stream. Its threads never call into the embedding
+/// language, so that they cannot race its runtime when it exits.
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("Cannot start the Tokio runtime"))
+}
+
+/// Start the run of `config`, returning the receiver of its decoded events.
+pub fn start_stream(config: StreamConfig) -> Result<EventReceiver> {
+    let filter = EventFilter::parse(&config.packages, &config.event_types, &config.filter)?;
+    let checkpoints_url = Url::parse(&config.checkpoints_url)
+        .with_context(|| format!("Invalid checkpoints URL `{}`", config.checkpoints_url))?;
+    let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+    let (stop, stopped) = oneshot::channel();
+
+    // The worker may not be `Send`, so it is driven by a thread of its own
+    std::thread::spawn(move || {
+        let mut builder = EventExtractWorker::builder(move |record| filter.matches(record))
+            .start(config.start)
+            .remote_store_url(checkpoints_url)
+            .concurrency(config.concurrency);
+        if let Some(limit) = config.limit {
+            builder = builder.limit(limit);
+        }
+        if let Some(folder) = config.cache_folder {
+            builder = builder.cache_folder(folder);
+        }
+        runtime().block_on(async move {
+            if let Err(e) = stream_events(builder, &config.full_node_url, &sender, stopped).await {
+                let _ = sender.send(Err(format!("{e:#}"))).await;
+            }
+        })
+    });
+    Ok(EventReceiver {
+        receiver,
+        _stop: stop,
+    })
+}
+
+/// Run the worker of `builder`, sending its decoded events as JSON until the receiver is
+/// dropped, which also ends `stopped`.
+async fn stream_events<F>(
+    builder: EventExtractWorkerBuilder<F>,
+    full_node_url: &str,
+    sender: &mpsc::Sender<Result<String, String>>,
+    stopped: oneshot::Receiver<()>,
+) -> Result<()>
+where
+    F: Fn(&EventRecord) -> bool + Send + Sync + 'static,
+{
+    let run = async move {
+        let client = SuiClientBuilder::default().build(full_node_url).await?;
+        let mut decoder = EventDecoder::new(client).with_object_types();
+        let (executor, mut receiver) = builder.build().await?;
+
+        let consumer = async move {
+            while let Some((_, records)) = receiver.recv().await {
+                for record in &records {
+                    let json = match decoder.decode(record).await {
+                        Ok(event) => serde_json::to_string(&event)?,
+                        Err(e) => {
+                            tracing::warn!("Cannot decode {}: {}", record.2.type_, e);
+                            serde_json::to_string(&JsonEvent::from(record))?
+                        }
+                    };
+                    if sender.send(Ok(json)).await.is_err() {
+                        // The receiver was dropped, closing the channel of the worker stops it
+                        return Ok(true);
+                    }
+                }
+            }
+            Ok::<_, anyhow::Error>(false)
+        };
+        let (result, dropped) = tokio::join!(executor, consumer);
+        if !dropped? {
+            result?;
+        }
+        Ok(())
+    };
+    // A worker whose filter matches no event never sends, so it would not notice the
+    // receiver is dropped: the run is dropped instead, which stops its worker
+    tokio::select! {
+        result = run => result,
+        _ = stopped => Err(HarvestError::Cancelled.into()),
+    }
+}
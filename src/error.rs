@@ -0,0 +1,68 @@
+use thiserror::Error;
+
+/// A boxed error kept as the source of a `HarvestError`.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors returned by the workers and the checkpoint fetcher, for applications to react to
+/// them, e.g. by restarting a run that failed on a transient download error.
+#[derive(Debug, Error)]
+pub enum HarvestError {
+    /// A checkpoint could not be downloaded, even after retries
+    #[error("Failed to fetch checkpoint {sequence_number} after {retries} retries")]
+    FetchFailed {
+        sequence_number: u64,
+        retries: u32,
+        #[source]
+        source: BoxError,
+    },
+    /// A downloaded or cached checkpoint could not be decoded, or failed verification
+    #[error("Invalid checkpoint {sequence_number}")]
+    Deserialization {
+        sequence_number: u64,
+        #[source]
+        source: BoxError,
+    },
+    /// Reading, writing or evicting cached checkpoints failed
+    #[error("Checkpoint cache error")]
+    CacheIo(#[source] BoxError),
+    /// The checkpoint store cannot be used, e.g. because its URL is invalid
+    #[error("Checkpoint store unavailable")]
+    StoreUnavailable(#[source] BoxError),
+    /// A request to the full node failed
+    #[error("Full node request failed")]
+    Rpc(#[source] BoxError),
+    /// The run was stopped by the application, e.g. by dropping the receiver of the events
+    /// streamed to the bindings
+    #[error("The run was cancelled")]
+    Cancelled,
+    /// The receiver of the extracted items was dropped while the worker was running
+    #[error("The receiver of the extracted items was dropped")]
+    ReceiverDropped,
+    /// The worker failed to process a checkpoint
+    #[error(transparent)]
+    Worker(anyhow::Error),
+}
+
+impl HarvestError {
+    /// Whether running again may succeed, as opposed to errors in the data or the setup.
+    /// A failed fetch is only transient if its store error is, e.g. a timeout or a rate
+    /// limit rather than a checkpoint missing from the store.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::FetchFailed { source, .. } => source
+                .downcast_ref::<object_store::Error>()
+                .is_some_and(crate::fetcher::is_transient),
+            Self::Rpc(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<anyhow::Error> for HarvestError {
+    /// Recover the errors of the library raised through `anyhow`, e.g. by the channel.
+    fn from(error: anyhow::Error) -> Self {
+        error.downcast().unwrap_or_else(Self::Worker)
+    }
+}
+
+pub type HarvestResult<T> = Result<T, HarvestError>;
@@ -0,0 +1,97 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::EventRecord;
+
+/// Folder of the event caches, inside the checkpoint cache folder.
+pub const EVENTS_FOLDER: &str = "events";
+
+/// File of an event cache holding the description of its filter.
+const FILTER_FILE: &str = "filter.txt";
+
+/// Version of the format of the cached events, part of the name of the cache folders.
+const FORMAT_VERSION: u8 = 2;
+
+/// The filtered events of every processed checkpoint, with its summary, so that later runs
+/// with the same filter skip downloading and deserializing the checkpoint. Each filter has a
+/// folder of its own, named after the hash of its description, e.g.
+/// `EventFilter::canonical`.
+#[derive(Debug, Clone)]
+pub struct EventCache {
+    folder: PathBuf,
+}
+
+impl EventCache {
+    /// Open the cache of the events matching the filter described by `filter`, in the
+    /// `EVENTS_FOLDER` of `cache_folder`.
+    pub fn open(cache_folder: &Path, filter: &str) -> Result<Self> {
+        let name = format!("v{FORMAT_VERSION}-{:016x}", fnv1a(filter.as_bytes()));
+        let folder = cache_folder.join(EVENTS_FOLDER).join(name);
+        fs::create_dir_all(&folder)?;
+
+        let filter_file = folder.join(FILTER_FILE);
+        match fs::read_to_string(&filter_file) {
+            Ok(existing) if existing != filter => bail!(
+                "The event cache {} belongs to another filter: {existing}",
+                folder.display()
+            ),
+            Ok(_) => (),
+            Err(_) => fs::write(&filter_file, filter)?,
+        }
+        Ok(Self { folder })
+    }
+
+    pub fn contains(&self, sequence_number: u64) -> bool {
+        self.path(sequence_number).exists()
+    }
+
+    /// The summary and filtered events of a checkpoint, if cached.
+    pub fn get(
+        &self,
+        sequence_number: u64,
+    ) -> Result<Option<(CertifiedCheckpointSummary, Vec<EventRecord>)>> {
+        match fs::read(self.path(sequence_number)) {
+            Ok(bytes) => Ok(Some(bcs::from_bytes(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store the filtered events of a checkpoint, replacing them atomically for the other
+    /// processes sharing the folder.
+    pub async fn put(
+        &self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let path = self.path(summary.sequence_number);
+        let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+        tokio::fs::write(&temp, bcs::to_bytes(&(summary, events))?).await?;
+        tokio::fs::rename(&temp, &path).await?;
+        Ok(())
+    }
+
+    fn path(&self, sequence_number: u64) -> PathBuf {
+        self.folder.join(format!("{sequence_number}.bcs"))
+    }
+}
+
+/// Remove the event caches of all filters.
+pub fn clear(cache_folder: &Path) -> Result<()> {
+    match fs::remove_dir_all(cache_folder.join(EVENTS_FOLDER)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The 64-bit FNV-1a hash, stable across runs and compiler versions unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,341 @@
+//! A small expression language over event attributes, e.g.
+//! `package == 0x2 && module == "coin" && type contains "Deposit"`.
+//!
+//! Comparisons have the form `<field> <operator> <value>`, where the field is one of
+//! `package`, `address` (that defines the type), `module`, `name`, `type`, `sender` or
+//! `transaction_module`, and the operator is `==`, `!=` or `contains`. Values are
+//! either bare words (such as addresses) or double-quoted strings. Comparisons can be
+//! combined with `&&`, `||`, `!` and parentheses.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use move_core_types::account_address::AccountAddress;
+use sui_types::event::Event;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Package,
+    Address,
+    Module,
+    Name,
+    Type,
+    Sender,
+    TransactionModule,
+}
+
+impl Field {
+    fn address(&self, event: &Event) -> Option<AccountAddress> {
+        match self {
+            Self::Package => Some(event.package_id.into()),
+            Self::Address => Some(event.type_.address),
+            Self::Sender => Some(event.sender.into()),
+            _ => None,
+        }
+    }
+
+    fn text(&self, event: &Event) -> String {
+        match self {
+            Self::Package => event.package_id.to_string(),
+            Self::Address => event.type_.address.to_canonical_string(true),
+            Self::Module => event.type_.module.to_string(),
+            Self::Name => event.type_.name.to_string(),
+            Self::Type => event.type_.to_canonical_string(true),
+            Self::Sender => event.sender.to_string(),
+            Self::TransactionModule => event.transaction_module.to_string(),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "package" => Self::Package,
+            "address" => Self::Address,
+            "module" => Self::Module,
+            "name" => Self::Name,
+            "type" => Self::Type,
+            "sender" => Self::Sender,
+            "transaction_module" => Self::TransactionModule,
+            _ => bail!("Unknown field `{s}`"),
+        })
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Package => "package",
+            Self::Address => "address",
+            Self::Module => "module",
+            Self::Name => "name",
+            Self::Type => "type",
+            Self::Sender => "sender",
+            Self::TransactionModule => "transaction_module",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Contains,
+}
+
+/// The right hand side of a comparison. Equality on address fields compares addresses,
+/// so that `0x2` matches the full 32 bytes address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Address(AccountAddress),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterExpr {
+    Compare {
+        field: Field,
+        operator: Operator,
+        operand: Operand,
+    },
+    Not(Box<FilterExpr>),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    pub fn evaluate(&self, event: &Event) -> bool {
+        match self {
+            Self::Compare {
+                field,
+                operator,
+                operand,
+            } => {
+                let equal = || match operand {
+                    Operand::Address(address) => field.address(event) == Some(*address),
+                    Operand::Text(text) => field.text(event) == *text,
+                };
+                match operator {
+                    Operator::Eq => equal(),
+                    Operator::Ne => !equal(),
+                    Operator::Contains => match operand {
+                        Operand::Text(text) => field.text(event).contains(text.as_str()),
+                        Operand::Address(_) => equal(),
+                    },
+                }
+            }
+            Self::Not(inner) => !inner.evaluate(event),
+            Self::And(left, right) => left.evaluate(event) && right.evaluate(event),
+            Self::Or(left, right) => left.evaluate(event) || right.evaluate(event),
+        }
+    }
+}
+
+/// The expression in the syntax it is parsed from, with every combination parenthesized,
+/// e.g. to describe the filter of an event cache.
+impl fmt::Display for FilterExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compare {
+                field,
+                operator,
+                operand,
+            } => {
+                let operator = match operator {
+                    Operator::Eq => "==",
+                    Operator::Ne => "!=",
+                    Operator::Contains => "contains",
+                };
+                match operand {
+                    Operand::Address(address) => write!(
+                        f,
+                        "{field} {operator} {}",
+                        address.to_canonical_string(true)
+                    ),
+                    Operand::Text(text) => write!(f, "{field} {operator} {text:?}"),
+                }
+            }
+            Self::Not(inner) => write!(f, "!({inner})"),
+            Self::And(left, right) => write!(f, "({left} && {right})"),
+            Self::Or(left, right) => write!(f, "({left} || {right})"),
+        }
+    }
+}
+
+impl FromStr for FilterExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let expression = parser.or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {:?} in filter expression", token);
+        }
+        Ok(expression)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Eq,
+    Ne,
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '=' | '!' | '&' | '|' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let token = match (c, next) {
+                    ('=', Some('=')) => Token::Eq,
+                    ('!', Some('=')) => Token::Ne,
+                    ('&', Some('&')) => Token::And,
+                    ('|', Some('|')) => Token::Or,
+                    ('!', _) => {
+                        tokens.push(Token::Not);
+                        continue;
+                    }
+                    _ => bail!("Unexpected `{c}` in filter expression"),
+                };
+                chars.next();
+                tokens.push(token);
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            text.push(chars.next().ok_or_else(|| anyhow!("Unterminated string"))?)
+                        }
+                        Some(c) => text.push(c),
+                        None => bail!("Unterminated string in filter expression"),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == ':' => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == ':') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            _ => bail!("Unexpected `{c}` in filter expression"),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser, where `||` binds looser than `&&`, which binds looser
+/// than `!`.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of filter expression"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<FilterExpr> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            left = FilterExpr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<FilterExpr> {
+        let mut left = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.position += 1;
+            left = FilterExpr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<FilterExpr> {
+        match self.next()? {
+            Token::Not => Ok(FilterExpr::Not(Box::new(self.unary()?))),
+            Token::Open => {
+                let inner = self.or()?;
+                match self.next()? {
+                    Token::Close => Ok(inner),
+                    token => bail!("Expected `)`, got {:?}", token),
+                }
+            }
+            Token::Word(word) => self.comparison(word.parse()?),
+            token => bail!("Expected a field, got {:?}", token),
+        }
+    }
+
+    fn comparison(&mut self, field: Field) -> Result<FilterExpr> {
+        let operator = match self.next()? {
+            Token::Eq => Operator::Eq,
+            Token::Ne => Operator::Ne,
+            Token::Word(word) if word == "contains" => Operator::Contains,
+            token => bail!("Expected `==`, `!=` or `contains`, got {:?}", token),
+        };
+
+        let value = match self.next()? {
+            Token::Word(value) | Token::Quoted(value) => value,
+            token => bail!("Expected a value, got {:?}", token),
+        };
+        let is_address_field = matches!(field, Field::Package | Field::Address | Field::Sender);
+        let operand = if is_address_field && operator != Operator::Contains {
+            Operand::Address(
+                AccountAddress::from_hex_literal(&value)
+                    .map_err(|e| anyhow!("Invalid address `{value}`: {e}"))?,
+            )
+        } else {
+            Operand::Text(value)
+        };
+
+        Ok(FilterExpr::Compare {
+            field,
+            operator,
+            operand,
+        })
+    }
+}
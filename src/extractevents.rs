@@ -2,8 +2,8 @@ use std::io::Write;
 use std::path::PathBuf;
 
 use anyhow::Result;
-use sui_data_ingestion_core::ReaderOptions;
 use sui_sdk::SuiClientBuilder;
+use url::Url;
 
 use harvestlib::EventExtractWorker;
 
@@ -28,7 +28,7 @@ struct Args {
 
     /// URL of Sui checkpoint nodes
     #[arg(long, default_value = "https://checkpoints.mainnet.sui.io")]
-    checkpoints_node_url: String,
+    checkpoints_node_url: Url,
 }
 
 #[tokio::main]
@@ -62,21 +62,15 @@ async fn main() -> Result<()> {
 
     let limit = latest_checkpoint - next_checkpoint;
 
-    // Custom reader options
-    let mut options = ReaderOptions::default();
-    options.batch_size = args.concurrent as usize;
-
     // Get a new Custom Worker
-    let (executor, mut receiver) = EventExtractWorker::new(
-        next_checkpoint,
-        limit,
-        |_e| true,
-        args.checkpoints_node_url.clone(),
-        args.concurrent as usize,
-        Some(options),
-        Some(PathBuf::from("cache")),
-    )
-    .await?;
+    let (executor, mut receiver) = EventExtractWorker::builder(|_e| true)
+        .start(next_checkpoint)
+        .limit(limit)
+        .remote_store_url(args.checkpoints_node_url.clone())
+        .concurrency(args.concurrent as usize)
+        .cache_folder("cache")
+        .build()
+        .await?;
 
     // spawn a task to process the received data
     tokio::spawn(async move {
@@ -0,0 +1,1272 @@
+use std::{
+    collections::HashSet,
+    io::{BufReader, Read},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use futures::{Future, Stream, StreamExt};
+use http::HeaderMap;
+#[cfg(feature = "s3")]
+use object_store::aws::AmazonS3Builder;
+#[cfg(feature = "azure")]
+use object_store::azure::MicrosoftAzureBuilder;
+#[cfg(feature = "gcs")]
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::{
+    http::HttpBuilder, local::LocalFileSystem, path::Path, prefix::PrefixStore, Certificate,
+    ClientOptions, GetResultPayload, ObjectStore, RetryConfig,
+};
+use serde::{de::DeserializeSeed, Deserialize, Deserializer};
+#[cfg(feature = "full-node")]
+use sui_sdk::SuiClient;
+use sui_types::{
+    effects::TransactionEffectsAPI, full_checkpoint_content::CheckpointData,
+    message_envelope::Message, messages_checkpoint::CertifiedCheckpointSummary,
+};
+use tokio::{
+    io::{AsyncSeekExt, AsyncWriteExt},
+    sync::Mutex,
+};
+use url::Url;
+
+#[cfg(feature = "cache-index")]
+use crate::cache::CacheIndex;
+use crate::{
+    cache::{self, CachePolicy, CHECKPOINT_EXTENSION},
+    error::{BoxError, HarvestError, HarvestResult},
+    metrics::{FetchTimer, HarvestMetrics},
+    sampling::Sampling,
+    store::CheckpointStore,
+};
+
+/// Number of checkpoints written to the cache between two evictions
+#[cfg(feature = "cache-index")]
+const EVICTION_INTERVAL: u64 = 100;
+
+/// How failed requests, e.g. checkpoint downloads, are retried. The delay before retry `n` is
+/// `initial_backoff * 2^n`, capped at `max_backoff`, and randomized when `jitter` is set.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// No retries, fail on the first error.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// The delay to wait before the given retry (starting at 0).
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        if self.jitter {
+            // Full jitter, spreads the retries of concurrent downloads
+            delay.mul_f64(rand::random::<f64>())
+        } else {
+            delay
+        }
+    }
+
+    /// Send the request returned by `request` until it succeeds, retrying its failures with
+    /// the backoff of the policy, and return the last failure once the retries are exhausted.
+    /// `what` names the request in the logs.
+    pub async fn retry_with_backoff<T, E, R>(
+        &self,
+        what: &str,
+        mut request: impl FnMut() -> R,
+    ) -> Result<T, E>
+    where
+        R: Future<Output = Result<T, E>>,
+        E: std::fmt::Display,
+    {
+        let mut retry = 0;
+        loop {
+            match request().await {
+                Ok(value) => return Ok(value),
+                Err(e) if retry < self.max_retries => {
+                    let delay = self.backoff(retry);
+                    tracing::warn!("Failed to {what} ({e}), retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Whether a failed request to a store may succeed if sent again, e.g. after a timeout or a
+/// rate limit, as opposed to a request the store refuses, e.g. with a 4xx status.
+pub(crate) fn is_transient(error: &object_store::Error) -> bool {
+    use object_store::Error;
+    match error {
+        Error::Generic { source, .. } => is_transient_source(source.as_ref()),
+        Error::JoinError { .. } => true,
+        _ => false,
+    }
+}
+
+/// Whether the source of a generic store error is transient, from the HTTP or I/O error it
+/// wraps.
+fn is_transient_source(source: &(dyn std::error::Error + 'static)) -> bool {
+    use std::io::ErrorKind;
+    let mut error = Some(source);
+    while let Some(e) = error {
+        if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+            return match e.status() {
+                Some(status) => is_transient_status(status.as_u16()),
+                // Timeouts and failed connections, unlike invalid requests
+                None => !e.is_builder(),
+            };
+        }
+        if let Some(e) = e.downcast_ref::<std::io::Error>() {
+            return !matches!(
+                e.kind(),
+                ErrorKind::NotFound
+                    | ErrorKind::PermissionDenied
+                    | ErrorKind::InvalidInput
+                    | ErrorKind::InvalidData
+            );
+        }
+        error = e.source();
+    }
+    // Last resort: the responses object_store rejects without retrying are kept in its own
+    // error type, which is private, so their status is only exposed by the message of the
+    // error. Errors without a status are assumed to be transient
+    source
+        .to_string()
+        .strip_prefix("Client error with status ")
+        .and_then(|status| status.get(..3))
+        .and_then(|status| status.parse::<u16>().ok())
+        .map_or(true, is_transient_status)
+}
+
+/// Whether a request answered with `status` may succeed if sent again: all but the client
+/// errors, except timeouts and rate limits.
+fn is_transient_status(status: u16) -> bool {
+    !(400..500).contains(&status) || status == 408 || status == 429
+}
+
+/// Options of the requests of the checkpoint fetcher, named like those of the executor of
+/// `sui-data-ingestion-core` it replaced.
+#[derive(Debug, Clone)]
+pub struct ReaderOptions {
+    /// Delay between two polls for a checkpoint that is not available yet, in milliseconds
+    pub tick_interval_ms: u64,
+    /// Timeout of the requests to the remote stores, in seconds
+    pub timeout_secs: u64,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        Self {
+            tick_interval_ms: 100,
+            timeout_secs: 5,
+        }
+    }
+}
+
+/// Options of the checkpoint fetcher that are not part of the `ReaderOptions`.
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    pub retry_policy: RetryPolicy,
+    /// Maximum number of requests per second sent to the remote store
+    pub max_rps: Option<f64>,
+    /// Headers sent with every request to the remote stores, e.g. the API key of a private
+    /// provider
+    pub headers: HeaderMap,
+    /// HTTP(S) proxy of the requests to the remote stores, by default that of the
+    /// `HTTPS_PROXY` environment variable
+    pub proxy_url: Option<String>,
+    /// Certificate authorities trusted on top of the built-in ones, by the remote stores
+    /// and the proxy, e.g. that of a corporate network
+    pub root_certificates: Vec<Certificate>,
+    /// Reject checkpoints whose contents do not match their summary
+    pub verify: bool,
+    /// Only enforced with the `cache-index` feature
+    pub cache_policy: CachePolicy,
+    /// Zstd level of the cached checkpoints, 0 to store them uncompressed
+    pub cache_compression: i32,
+    /// Identifier of the chain of the checkpoints, e.g. `35834a8a` for mainnet, as returned
+    /// by `sui_getChainIdentifier`, recorded in the cache entries. Cached entries of another
+    /// chain or without one, e.g. of a devnet since reset, are downloaded again and replaced
+    pub chain: Option<String>,
+    /// Fail the run on the first checkpoint that cannot be fetched or decoded, instead of
+    /// skipping it
+    pub strict: bool,
+    /// Number of checkpoints deserialized in parallel, by default the number of CPUs
+    pub decode_concurrency: Option<usize>,
+    /// Hand the checkpoints to the worker in sequence number order, through a reorder
+    /// buffer of the concurrent downloads, rather than as soon as they are ready
+    pub ordered: bool,
+    /// Delay between two polls for a checkpoint that is not available yet, by default the
+    /// `tick_interval_ms` of the `ReaderOptions`
+    pub poll_interval: Option<Duration>,
+    /// Wait for the checkpoints at or past the tip of the chain to be produced, instead of
+    /// failing on them like on any missing checkpoint. Set by the workers for unbounded
+    /// ranges, which follow the chain
+    pub follow: bool,
+    /// Only fetch a checkpoint once this number of later checkpoints is available, to stay
+    /// behind the tip of the chain
+    pub tip_lag: u64,
+    /// Where the latest available checkpoint is polled from, every `poll_interval`. Without
+    /// it, the fetcher polls the store for the checkpoints that are not available yet
+    pub tip_source: Option<Arc<dyn TipSource>>,
+    /// Only process this random subset of the checkpoints, the others counting as processed
+    pub sampling: Option<Sampling>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            retry_policy: RetryPolicy::default(),
+            max_rps: None,
+            headers: HeaderMap::new(),
+            proxy_url: None,
+            root_certificates: Vec::new(),
+            verify: false,
+            cache_policy: CachePolicy::default(),
+            cache_compression: 0,
+            chain: None,
+            strict: false,
+            decode_concurrency: None,
+            ordered: true,
+            poll_interval: None,
+            follow: false,
+            tip_lag: 0,
+            tip_source: None,
+            sampling: None,
+        }
+    }
+}
+
+/// Reports the latest checkpoint of the chain, so that the fetcher polls it and waits for the
+/// checkpoints to exist instead of polling the store for each of them.
+#[async_trait]
+pub trait TipSource: Send + Sync + std::fmt::Debug {
+    async fn latest_checkpoint(&self) -> Result<u64>;
+}
+
+#[cfg(feature = "full-node")]
+/// The latest checkpoint known to a full node. Checkpoints may reach the store a moment
+/// after the full node, in which case the fetcher polls the store until they do.
+pub struct FullNodeTip(pub SuiClient);
+
+#[cfg(feature = "full-node")]
+impl std::fmt::Debug for FullNodeTip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FullNodeTip")
+    }
+}
+
+#[cfg(feature = "full-node")]
+#[async_trait]
+impl TipSource for FullNodeTip {
+    async fn latest_checkpoint(&self) -> Result<u64> {
+        Ok(self
+            .0
+            .read_api()
+            .get_latest_checkpoint_sequence_number()
+            .await?)
+    }
+}
+
+/// A token bucket refilled at `rate` tokens per second, holding at most one second of tokens.
+pub struct RateLimiter {
+    rate: f64,
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    pub fn new(rate: f64) -> Self {
+        assert!(rate > 0.0, "The rate must be positive");
+        Self {
+            rate,
+            bucket: Mutex::new((rate.max(1.0), Instant::now())),
+        }
+    }
+
+    /// Wait until a token is available and take it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let (tokens, last) = &mut *bucket;
+                let now = Instant::now();
+                *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.rate)
+                    .min(self.rate.max(1.0));
+                *last = now;
+                if *tokens >= 1.0 {
+                    *tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - *tokens) / self.rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// A checkpoint store, set aside for a backoff period after each failed request.
+struct Endpoint {
+    url: String,
+    store: Box<dyn ObjectStore>,
+    /// Consecutive failed requests
+    failures: AtomicU32,
+    retry_at: std::sync::Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn new(url: String, store: Box<dyn ObjectStore>) -> Self {
+        Self {
+            url,
+            store,
+            failures: AtomicU32::new(0),
+            retry_at: std::sync::Mutex::new(None),
+        }
+    }
+
+    fn retry_at(&self) -> Option<Instant> {
+        *self.retry_at.lock().unwrap()
+    }
+
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.retry_at().is_none_or(|retry_at| retry_at <= now)
+    }
+
+    /// Mark the endpoint as unhealthy, returning how long it is set aside.
+    fn failed(&self, retry_policy: &RetryPolicy) -> Duration {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed);
+        let delay = retry_policy.backoff(failures);
+        *self.retry_at.lock().unwrap() = Some(Instant::now() + delay);
+        delay
+    }
+
+    fn succeeded(&self) {
+        if self.failures.swap(0, Ordering::Relaxed) > 0 {
+            *self.retry_at.lock().unwrap() = None;
+        }
+    }
+}
+
+/// Downloads checkpoints from a remote store, keeping a copy of each in a local cache
+/// folder. Checkpoints that do not exist yet are polled until they become available.
+/// Besides HTTP, the store can be a bucket (`s3://`, `gs://` or `az://`, with the `s3`, `gcs`
+/// and `azure` features) or, with a `file://` URL, a local directory whose checkpoints are
+/// read directly.
+///
+/// Requests are spread over several stores in turn, if given. A store failing a request
+/// is skipped until the end of its backoff period, doubled with every consecutive failure.
+pub struct CheckpointFetcher {
+    endpoints: Vec<Endpoint>,
+    next_endpoint: AtomicUsize,
+    cache_folder: Option<PathBuf>,
+    #[cfg(feature = "cache-index")]
+    cache_index: Option<Arc<CacheIndex>>,
+    poll_interval: Duration,
+    follow: bool,
+    tip_lag: u64,
+    tip_source: Option<Arc<dyn TipSource>>,
+    checkpoint_store: Option<Arc<dyn CheckpointStore>>,
+    /// The latest checkpoint known to be available
+    tip: AtomicU64,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    verify: bool,
+    #[cfg(feature = "cache-index")]
+    cache_policy: CachePolicy,
+    cache_compression: i32,
+    chain: Option<String>,
+    /// Number of checkpoints written to the cache since the last eviction
+    #[cfg(feature = "cache-index")]
+    cache_writes: AtomicU64,
+    /// The cached checkpoints fetched but not decoded yet, which evictions keep
+    in_flight: Arc<std::sync::Mutex<HashSet<u64>>>,
+    metrics: Option<HarvestMetrics>,
+}
+
+impl std::fmt::Debug for CheckpointFetcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let urls: Vec<_> = self.endpoints.iter().map(|e| e.url.as_str()).collect();
+        f.debug_struct("CheckpointFetcher")
+            .field("endpoints", &urls)
+            .field("checkpoint_store", &self.checkpoint_store)
+            .finish()
+    }
+}
+
+impl CheckpointFetcher {
+    /// Fetch checkpoints from the stores at `remote_store_urls`.
+    pub fn new(
+        remote_store_urls: &[Url],
+        cache_folder: Option<PathBuf>,
+        reader_options: &ReaderOptions,
+        options: FetchOptions,
+        metrics: Option<HarvestMetrics>,
+    ) -> HarvestResult<Self> {
+        let mut stores = Vec::new();
+        let mut all_local = true;
+        for url in remote_store_urls {
+            all_local &= url.scheme() == "file";
+            stores.push((url.to_string(), open_store(url, reader_options, &options)?));
+        }
+
+        // Local checkpoint files do not need to be cached
+        let cache_folder = cache_folder.filter(|_| !all_local);
+        Self::with_stores(stores, cache_folder, reader_options, options, metrics)
+    }
+
+    /// Fetch checkpoints from an already configured store.
+    pub fn with_store(
+        store: Box<dyn ObjectStore>,
+        cache_folder: Option<PathBuf>,
+        reader_options: &ReaderOptions,
+        options: FetchOptions,
+        metrics: Option<HarvestMetrics>,
+    ) -> HarvestResult<Self> {
+        let stores = vec![(store.to_string(), store)];
+        Self::with_stores(stores, cache_folder, reader_options, options, metrics)
+    }
+
+    /// Fetch checkpoints from already configured stores, identified by a name in the logs.
+    pub fn with_stores(
+        stores: Vec<(String, Box<dyn ObjectStore>)>,
+        cache_folder: Option<PathBuf>,
+        reader_options: &ReaderOptions,
+        options: FetchOptions,
+        metrics: Option<HarvestMetrics>,
+    ) -> HarvestResult<Self> {
+        if stores.is_empty() {
+            return Err(unavailable("No checkpoint store"));
+        }
+        Self::build(stores, None, cache_folder, reader_options, options, metrics)
+    }
+
+    /// Read the checkpoints from `store` instead of remote store URLs, e.g. an archive with
+    /// a custom layout. Its checkpoints are not cached.
+    pub fn with_checkpoint_store(
+        store: Arc<dyn CheckpointStore>,
+        reader_options: &ReaderOptions,
+        options: FetchOptions,
+        metrics: Option<HarvestMetrics>,
+    ) -> HarvestResult<Self> {
+        Self::build(
+            Vec::new(),
+            Some(store),
+            None,
+            reader_options,
+            options,
+            metrics,
+        )
+    }
+
+    fn build(
+        stores: Vec<(String, Box<dyn ObjectStore>)>,
+        checkpoint_store: Option<Arc<dyn CheckpointStore>>,
+        cache_folder: Option<PathBuf>,
+        reader_options: &ReaderOptions,
+        options: FetchOptions,
+        metrics: Option<HarvestMetrics>,
+    ) -> HarvestResult<Self> {
+        #[cfg(feature = "cache-index")]
+        let cache_index = cache_folder
+            .as_deref()
+            .map(CacheIndex::open)
+            .transpose()
+            .map_err(|e| HarvestError::CacheIo(e.into()))?
+            .map(Arc::new);
+
+        Ok(Self {
+            endpoints: stores
+                .into_iter()
+                .map(|(url, store)| Endpoint::new(url, store))
+                .collect(),
+            next_endpoint: AtomicUsize::new(0),
+            cache_folder,
+            #[cfg(feature = "cache-index")]
+            cache_index,
+            poll_interval: options
+                .poll_interval
+                .unwrap_or(Duration::from_millis(reader_options.tick_interval_ms)),
+            follow: options.follow,
+            tip_lag: options.tip_lag,
+            tip_source: options.tip_source,
+            checkpoint_store,
+            tip: AtomicU64::new(0),
+            retry_policy: options.retry_policy,
+            rate_limiter: options.max_rps.map(RateLimiter::new),
+            verify: options.verify,
+            #[cfg(feature = "cache-index")]
+            cache_policy: options.cache_policy,
+            cache_compression: options.cache_compression,
+            chain: options.chain,
+            #[cfg(feature = "cache-index")]
+            cache_writes: AtomicU64::new(0),
+            in_flight: Arc::default(),
+            metrics,
+        })
+    }
+
+    /// Whether a checkpoint that is not found may still be produced, when following the
+    /// chain and no later checkpoint was found. Otherwise it is missing from the store,
+    /// e.g. pruned.
+    fn is_ahead(&self, sequence_number: u64) -> bool {
+        self.follow && sequence_number >= self.tip.load(Ordering::Relaxed)
+    }
+
+    /// The next healthy endpoint in turn, or the first to recover if none is.
+    fn pick_endpoint(&self) -> &Endpoint {
+        let now = Instant::now();
+        let start = self.next_endpoint.fetch_add(1, Ordering::Relaxed);
+        let count = self.endpoints.len();
+        (0..count)
+            .map(|i| &self.endpoints[(start + i) % count])
+            .find(|endpoint| endpoint.is_healthy(now))
+            .or_else(|| self.endpoints.iter().min_by_key(|e| e.retry_at()))
+            .expect("There is at least one endpoint")
+    }
+
+    pub async fn fetch(&self, sequence_number: u64) -> HarvestResult<CheckpointData> {
+        let file = self.fetch_file(sequence_number).await?;
+        self.decode(sequence_number, file)
+    }
+
+    /// Deserialize a checkpoint file returned by `fetch_file`, and verify it if requested.
+    /// This is CPU bound, unlike the download. Files on disk are decoded as they are read,
+    /// without loading them in memory first.
+    pub fn decode(
+        &self,
+        sequence_number: u64,
+        file: CheckpointFile,
+    ) -> HarvestResult<CheckpointData> {
+        let checkpoint = match file {
+            CheckpointFile::Memory(bytes) => decode_checkpoint(sequence_number, &bytes)?,
+            CheckpointFile::Spooled(file) => read_checkpoint(sequence_number, file)?,
+            CheckpointFile::Disk(path) => {
+                let reader =
+                    cache::open_entry(&path).map_err(|e| entry_error(sequence_number, e))?;
+                read_checkpoint(sequence_number, reader)?
+            }
+            CheckpointFile::Cached { path, file } => {
+                let checkpoint = cache::read_entry(file)
+                    .map_err(|e| entry_error(sequence_number, e))
+                    .and_then(|reader| read_checkpoint(sequence_number, reader));
+                self.in_flight.lock().unwrap().remove(&sequence_number);
+                checkpoint?
+            }
+        };
+        if self.verify {
+            verify_checkpoint(sequence_number, &checkpoint).map_err(|e| {
+                HarvestError::Deserialization {
+                    sequence_number,
+                    source: e.into(),
+                }
+            })?;
+        }
+        Ok(checkpoint)
+    }
+
+    /// Download the summary of a checkpoint from the next store, only reading the start of
+    /// its file, e.g. to check which chain the store holds without downloading the whole
+    /// checkpoint. The cache is not used.
+    pub async fn fetch_summary(
+        &self,
+        sequence_number: u64,
+    ) -> HarvestResult<CertifiedCheckpointSummary> {
+        let path = Path::from(format!("{sequence_number}.{CHECKPOINT_EXTENSION}"));
+        let endpoint = self.pick_endpoint();
+        let bytes = self
+            .retry_policy
+            .retry_with_backoff("download a checkpoint summary", || async {
+                let mut stream = endpoint.store.get(&path).await?.into_stream();
+                let mut bytes = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    bytes.extend_from_slice(&chunk?);
+                    // Dropping the stream stops the download
+                    if !matches!(decode_summary(sequence_number, &bytes), Ok(None)) {
+                        break;
+                    }
+                }
+                Ok::<_, object_store::Error>(bytes)
+            })
+            .await
+            .map_err(|e| HarvestError::FetchFailed {
+                sequence_number,
+                retries: self.retry_policy.max_retries,
+                source: e.into(),
+            })?;
+        decode_summary(sequence_number, &bytes)?.ok_or_else(|| HarvestError::Deserialization {
+            sequence_number,
+            source: "The checkpoint file is truncated".into(),
+        })
+    }
+
+    /// Download a checkpoint, or find it in the cache, without deserializing it. Downloads
+    /// are streamed to the cache file when there is a cache, and checkpoints of local stores
+    /// are left where they are.
+    pub async fn fetch_file(&self, sequence_number: u64) -> HarvestResult<CheckpointFile> {
+        if let Some(store) = &self.checkpoint_store {
+            return self.fetch_from(store.as_ref(), sequence_number).await;
+        }
+        let filename = format!("{sequence_number}.{CHECKPOINT_EXTENSION}");
+        let cache_file = self.cache_folder.as_ref().map(|f| f.join(&filename));
+
+        // Try the cache first. An entry evicted by another process, or of another chain, is
+        // downloaded again
+        if let Some(path) = &cache_file {
+            let cached = open_cached(path).await?;
+            if let Some(file) = cached.filter(|file| is_of_chain(file, self.chain.as_deref())) {
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    metrics.cache_hits.inc();
+                }
+                #[cfg(feature = "cache-index")]
+                self.update_index(move |index| index.hit(sequence_number))
+                    .await?;
+                self.tip.fetch_max(sequence_number, Ordering::Relaxed);
+                self.in_flight.lock().unwrap().insert(sequence_number);
+                return Ok(CheckpointFile::Cached {
+                    path: path.clone(),
+                    file,
+                });
+            }
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.cache_misses.inc();
+        }
+        #[cfg(feature = "cache-index")]
+        self.update_index(CacheIndex::miss).await?;
+        self.wait_for_tip(sequence_number).await?;
+
+        let path = Path::from(filename.as_str());
+        let mut retry = 0;
+        loop {
+            // Wait for the endpoint to recover if all of them are unhealthy
+            let endpoint = self.pick_endpoint();
+            if let Some(retry_at) = endpoint.retry_at() {
+                tokio::time::sleep_until(retry_at.into()).await;
+            }
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.acquire().await;
+            }
+            let timer = FetchTimer::start(self.metrics.as_ref());
+            let result = match endpoint.store.get(&path).await {
+                Ok(response) => match response.payload {
+                    GetResultPayload::File(_, path) => Ok(CheckpointFile::Disk(path)),
+                    GetResultPayload::Stream(stream) => {
+                        self.receive(sequence_number, stream, cache_file.as_deref())
+                            .await?
+                    }
+                },
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(file) => {
+                    timer.observe();
+                    endpoint.succeeded();
+                    self.tip.fetch_max(sequence_number, Ordering::Relaxed);
+                    #[cfg(feature = "cache-index")]
+                    if cache_file.is_some() {
+                        self.enforce_cache_policy().await?;
+                    }
+                    return Ok(file);
+                }
+                // The checkpoint is not yet available, wait for it
+                Err(object_store::Error::NotFound { .. }) if self.is_ahead(sequence_number) => {
+                    timer.discard();
+                    endpoint.succeeded();
+                    tokio::time::sleep(self.poll_interval).await
+                }
+                // Transient failures (rate limits, timeouts, ...) are retried with backoff
+                Err(e) if is_transient(&e) && retry < self.retry_policy.max_retries => {
+                    timer.discard();
+                    let delay = endpoint.failed(&self.retry_policy);
+                    tracing::warn!(
+                        "Failed to fetch checkpoint {sequence_number} from {} ({e}), \
+                        skipping it for {delay:?}",
+                        endpoint.url
+                    );
+                    retry += 1;
+                }
+                // The store refuses the credentials, no other checkpoint can be read
+                Err(
+                    e @ (object_store::Error::Unauthenticated { .. }
+                    | object_store::Error::PermissionDenied { .. }),
+                ) => return Err(HarvestError::StoreUnavailable(e.into())),
+                Err(source) => {
+                    if is_transient(&source) {
+                        endpoint.failed(&self.retry_policy);
+                    }
+                    return Err(HarvestError::FetchFailed {
+                        sequence_number,
+                        retries: retry,
+                        source: source.into(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Read a checkpoint from a `CheckpointStore`, polling it for the checkpoints past the
+    /// tip when following the chain, and retrying the transient failures.
+    async fn fetch_from(
+        &self,
+        store: &dyn CheckpointStore,
+        sequence_number: u64,
+    ) -> HarvestResult<CheckpointFile> {
+        self.wait_for_tip(sequence_number).await?;
+        let mut retry = 0;
+        loop {
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.acquire().await;
+            }
+            let timer = FetchTimer::start(self.metrics.as_ref());
+            match store.get_contents(sequence_number).await {
+                Ok(Some(file)) => {
+                    timer.observe();
+                    self.tip.fetch_max(sequence_number, Ordering::Relaxed);
+                    return Ok(file);
+                }
+                // The checkpoint is not yet available, wait for it
+                Ok(None) if self.is_ahead(sequence_number) => {
+                    timer.discard();
+                    tokio::time::sleep(self.poll_interval).await
+                }
+                Ok(None) => {
+                    return Err(HarvestError::FetchFailed {
+                        sequence_number,
+                        retries: retry,
+                        source: format!("Checkpoint not found in {store:?}").into(),
+                    })
+                }
+                Err(e) if e.is_transient() && retry < self.retry_policy.max_retries => {
+                    timer.discard();
+                    let delay = self.retry_policy.backoff(retry);
+                    tracing::warn!(
+                        "Failed to fetch checkpoint {sequence_number} from {store:?} ({:#}), \
+                        retrying in {delay:?}",
+                        anyhow::Error::from(e)
+                    );
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The latest checkpoint reported by the tip source or the checkpoint store, if any.
+    pub async fn latest_checkpoint(&self) -> HarvestResult<Option<u64>> {
+        if let Some(source) = &self.tip_source {
+            return source
+                .latest_checkpoint()
+                .await
+                .map(Some)
+                .map_err(|e| HarvestError::Rpc(e.into()));
+        }
+        match &self.checkpoint_store {
+            Some(store) => store.latest().await,
+            None => Ok(None),
+        }
+    }
+
+    /// Wait until checkpoint `sequence_number` is `tip_lag` checkpoints behind the latest
+    /// available one, as reported by the tip source or found in the store. Failures to get
+    /// the latest checkpoint are retried with the backoff of the retry policy, and the last
+    /// one is returned once its retries are exhausted.
+    async fn wait_for_tip(&self, sequence_number: u64) -> HarvestResult<()> {
+        if self.tip_lag == 0 && self.tip_source.is_none() {
+            return Ok(());
+        }
+        let target = sequence_number.saturating_add(self.tip_lag);
+        let mut failures = 0;
+        while self.tip.load(Ordering::Relaxed) < target {
+            let latest = match &self.tip_source {
+                Some(source) => source
+                    .latest_checkpoint()
+                    .await
+                    .map_err(|e| HarvestError::Rpc(e.into())),
+                None if self.checkpoint_store.is_some() => self
+                    .latest_checkpoint()
+                    .await
+                    .map(|latest| latest.unwrap_or(target)),
+                None => {
+                    let path = Path::from(format!("{target}.{CHECKPOINT_EXTENSION}"));
+                    let endpoint = self.pick_endpoint();
+                    match endpoint.store.head(&path).await {
+                        Ok(_) => {
+                            endpoint.succeeded();
+                            Ok(target)
+                        }
+                        // The checkpoint is not produced yet, the store is healthy
+                        Err(object_store::Error::NotFound { .. }) => {
+                            endpoint.succeeded();
+                            Ok(0)
+                        }
+                        Err(e) => {
+                            endpoint.failed(&self.retry_policy);
+                            Err(HarvestError::FetchFailed {
+                                sequence_number: target,
+                                retries: failures,
+                                source: e.into(),
+                            })
+                        }
+                    }
+                }
+            };
+            match latest {
+                Ok(latest) => {
+                    failures = 0;
+                    self.tip.fetch_max(latest, Ordering::Relaxed);
+                    if latest < target {
+                        tokio::time::sleep(self.poll_interval).await;
+                    }
+                }
+                Err(e) if failures < self.retry_policy.max_retries => {
+                    let delay = self.retry_policy.backoff(failures);
+                    tracing::warn!(
+                        "Failed to get the latest checkpoint ({e:?}), retrying in {delay:?}"
+                    );
+                    failures += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Receive a downloaded checkpoint file chunk by chunk, writing it to `cache_file` if
+    /// any, or to a temporary file otherwise. The outer error is a failure to write the
+    /// file, the inner one a failure of the download, which may be retried.
+    async fn receive<S, B>(
+        &self,
+        sequence_number: u64,
+        mut stream: S,
+        cache_file: Option<&std::path::Path>,
+    ) -> HarvestResult<object_store::Result<CheckpointFile>>
+    where
+        S: Stream<Item = object_store::Result<B>> + Unpin,
+        B: AsRef<[u8]>,
+    {
+        let Some(file) = cache_file else {
+            let spool_io = |e: std::io::Error| HarvestError::CacheIo(e.into());
+            let mut spool = tokio::fs::File::from_std(tempfile::tempfile().map_err(spool_io)?);
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(chunk) => spool.write_all(chunk.as_ref()).await.map_err(spool_io)?,
+                    Err(e) => return Ok(Err(e)),
+                }
+            }
+            spool.rewind().await.map_err(spool_io)?;
+            return Ok(Ok(CheckpointFile::Spooled(spool.into_std().await)));
+        };
+
+        let cache_io = |e: anyhow::Error| HarvestError::CacheIo(e.into());
+        let mut writer =
+            cache::EntryWriter::create(file, self.cache_compression, self.chain.as_deref())
+                .await
+                .map_err(cache_io)?;
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => writer.write(chunk.as_ref()).await.map_err(cache_io)?,
+                Err(e) => {
+                    writer.abort().await;
+                    return Ok(Err(e));
+                }
+            }
+        }
+        let (size, reader) = writer.finish().await.map_err(cache_io)?;
+        self.in_flight.lock().unwrap().insert(sequence_number);
+        #[cfg(feature = "cache-index")]
+        self.update_index(move |index| index.insert(sequence_number, size))
+            .await?;
+        Ok(Ok(CheckpointFile::Cached {
+            path: file.to_path_buf(),
+            file: reader,
+        }))
+    }
+
+    #[cfg(feature = "cache-index")]
+    /// Update the cache index on the blocking thread pool. The evictions rely on it to
+    /// know the entries and their last use, so failing to update it fails the fetch.
+    async fn update_index<F>(&self, update: F) -> HarvestResult<()>
+    where
+        F: FnOnce(&CacheIndex) -> Result<()> + Send + 'static,
+    {
+        let Some(index) = self.cache_index.clone() else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || update(&index))
+            .await
+            .map_err(|e| HarvestError::CacheIo(e.into()))?
+            .map_err(|e| HarvestError::CacheIo(e.into()))
+    }
+
+    #[cfg(feature = "cache-index")]
+    /// Evict cached checkpoints every `EVICTION_INTERVAL` writes.
+    async fn enforce_cache_policy(&self) -> HarvestResult<()> {
+        let (Some(folder), Some(index)) = (&self.cache_folder, &self.cache_index) else {
+            return Ok(());
+        };
+        if self.cache_policy.is_unbounded()
+            || self.cache_writes.fetch_add(1, Ordering::Relaxed) % EVICTION_INTERVAL != 0
+        {
+            return Ok(());
+        }
+
+        let (folder, index, policy) = (folder.clone(), index.clone(), self.cache_policy.clone());
+        let in_flight = self.in_flight.clone();
+        tokio::task::spawn_blocking(move || {
+            let in_use = in_flight.lock().unwrap().clone();
+            cache::evict(&folder, &index, &policy, &in_use)
+        })
+        .await
+        .map_err(|e| HarvestError::CacheIo(e.into()))?
+        .map_err(|e| HarvestError::CacheIo(e.into()))?;
+        Ok(())
+    }
+}
+
+/// Where a worker reads its checkpoints from.
+pub(crate) enum CheckpointSource {
+    /// The URLs of remote stores, see `CheckpointFetcher::new`
+    Urls(Vec<Url>),
+    Store(Arc<dyn CheckpointStore>),
+}
+
+impl From<Vec<Url>> for CheckpointSource {
+    fn from(urls: Vec<Url>) -> Self {
+        Self::Urls(urls)
+    }
+}
+
+impl CheckpointSource {
+    pub(crate) fn fetcher(
+        self,
+        cache_folder: Option<PathBuf>,
+        reader_options: &ReaderOptions,
+        options: FetchOptions,
+        metrics: Option<HarvestMetrics>,
+    ) -> HarvestResult<CheckpointFetcher> {
+        match self {
+            Self::Urls(urls) => {
+                CheckpointFetcher::new(&urls, cache_folder, reader_options, options, metrics)
+            }
+            Self::Store(store) => {
+                CheckpointFetcher::with_checkpoint_store(store, reader_options, options, metrics)
+            }
+        }
+    }
+}
+
+/// Open the cache entry at `path`, or `None` if it is not cached.
+async fn open_cached(path: &std::path::Path) -> HarvestResult<Option<std::fs::File>> {
+    match tokio::fs::File::open(path).await {
+        Ok(file) => Ok(Some(file.into_std().await)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(HarvestError::CacheIo(e.into())),
+    }
+}
+
+/// Where a fetched checkpoint file is, before it is deserialized.
+#[derive(Debug)]
+pub enum CheckpointFile {
+    /// A file of a local store
+    Disk(PathBuf),
+    /// An entry of the cache folder, already open so that it can be read even if it is
+    /// evicted in the meantime
+    Cached { path: PathBuf, file: std::fs::File },
+    /// A downloaded file, when there is no cache to write it to, spooled to a temporary
+    /// file deleted once closed
+    Spooled(std::fs::File),
+    /// The contents of a file returned by a `CheckpointStore`
+    Memory(Vec<u8>),
+}
+
+/// The first byte of the checkpoint files written with BCS, the only encoding in use.
+const BCS_ENCODING: u8 = 1;
+
+/// Checkpoint files start with a byte identifying their encoding, followed by the BCS data.
+pub(crate) fn decode_checkpoint(
+    sequence_number: u64,
+    bytes: &[u8],
+) -> HarvestResult<CheckpointData> {
+    read_checkpoint(sequence_number, bytes)
+}
+
+/// Decode the summary at the start of a checkpoint file from its first `bytes`, or `None` if
+/// they do not hold all of it yet.
+fn decode_summary(
+    sequence_number: u64,
+    bytes: &[u8],
+) -> HarvestResult<Option<CertifiedCheckpointSummary>> {
+    let invalid = |source: BoxError| HarvestError::Deserialization {
+        sequence_number,
+        source,
+    };
+    let Some((&encoding, contents)) = bytes.split_first() else {
+        return Ok(None);
+    };
+    if encoding != BCS_ENCODING {
+        return Err(invalid(
+            format!("Unknown checkpoint encoding {encoding}").into(),
+        ));
+    }
+    // The summary is the first field of `CheckpointData`, followed by the rest of its fields
+    let mut summary = None;
+    match bcs::from_bytes_seed(Prefix(&mut summary), contents) {
+        Ok(()) | Err(bcs::Error::RemainingInput) => Ok(summary),
+        Err(bcs::Error::Eof) => Ok(None),
+        Err(e) => Err(invalid(e.into())),
+    }
+}
+
+/// Deserializes a value into its slot, keeping it even if more input follows it.
+struct Prefix<'a, T>(&'a mut Option<T>);
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for Prefix<'_, T> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        *self.0 = Some(T::deserialize(deserializer)?);
+        Ok(())
+    }
+}
+
+/// Read checkpoint `sequence_number` of `chain` back from `cache_folder`, without any
+/// network access, or `None` if it is not cached.
+pub fn read_cached(
+    cache_folder: &std::path::Path,
+    sequence_number: u64,
+    chain: &str,
+) -> HarvestResult<Option<CheckpointData>> {
+    let path = cache_folder.join(format!("{sequence_number}.{CHECKPOINT_EXTENSION}"));
+    let file = match std::fs::File::open(&path) {
+        Ok(file) if is_of_chain(&file, Some(chain)) => file,
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(HarvestError::CacheIo(e.into())),
+    };
+    let reader = cache::read_entry(file).map_err(|e| entry_error(sequence_number, e))?;
+    read_checkpoint(sequence_number, reader).map(Some)
+}
+
+/// Whether the cache entry `file` is of `chain`, if known. Entries whose header cannot be
+/// read are of none.
+fn is_of_chain(file: &std::fs::File, chain: Option<&str>) -> bool {
+    match chain {
+        None => true,
+        Some(chain) => cache::entry_chain(file).is_ok_and(|c| c.as_deref() == Some(chain)),
+    }
+}
+
+/// An entry that cannot be read is skipped like a checkpoint that cannot be decoded.
+fn entry_error(sequence_number: u64, error: anyhow::Error) -> HarvestError {
+    HarvestError::Deserialization {
+        sequence_number,
+        source: error.into(),
+    }
+}
+
+/// Deserialize a checkpoint file as it is read from `reader`, without loading the file in
+/// memory first. The whole `CheckpointData` is still built.
+pub(crate) fn read_checkpoint(
+    sequence_number: u64,
+    reader: impl Read,
+) -> HarvestResult<CheckpointData> {
+    let invalid = |source: BoxError| HarvestError::Deserialization {
+        sequence_number,
+        source,
+    };
+    let mut reader = BufReader::new(reader);
+    let mut encoding = [0; 1];
+    reader
+        .read_exact(&mut encoding)
+        .map_err(|e| invalid(e.into()))?;
+    if encoding[0] != BCS_ENCODING {
+        return Err(invalid(
+            format!("Unknown checkpoint encoding {}", encoding[0]).into(),
+        ));
+    }
+    bcs::from_reader(reader).map_err(|e| invalid(e.into()))
+}
+
+/// The options of the HTTP clients of the remote stores: the headers, proxy and certificate
+/// authorities of `options`.
+pub(crate) fn client_options(timeout: Duration, options: &FetchOptions) -> ClientOptions {
+    let mut client_options = ClientOptions::new()
+        .with_timeout(timeout)
+        .with_default_headers(options.headers.clone());
+    if let Some(proxy_url) = &options.proxy_url {
+        client_options = client_options.with_proxy_url(proxy_url);
+    }
+    for certificate in &options.root_certificates {
+        client_options = client_options.with_root_certificate(certificate.clone());
+    }
+    client_options
+}
+
+/// Open the store at `url`, with its own retries disabled as they are handled by the fetcher.
+fn open_store(
+    url: &Url,
+    reader_options: &ReaderOptions,
+    options: &FetchOptions,
+) -> HarvestResult<Box<dyn ObjectStore>> {
+    let retry = RetryConfig {
+        max_retries: 0,
+        ..RetryConfig::default()
+    };
+    let client_options = client_options(Duration::from_secs(reader_options.timeout_secs), options);
+
+    let store: Box<dyn ObjectStore> = match url.scheme() {
+        "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|_| unavailable(format!("Invalid checkpoints directory '{url}'")))?;
+            Box::new(LocalFileSystem::new_with_prefix(path).map_err(unavailable)?)
+        }
+        // Buckets take their credentials from the environment, e.g. `AWS_ACCESS_KEY_ID`
+        #[cfg(feature = "s3")]
+        "s3" => Box::new(
+            AmazonS3Builder::from_env()
+                .with_url(url.as_str())
+                .with_client_options(client_options)
+                .with_retry(retry)
+                .build()
+                .map_err(unavailable)?,
+        ),
+        #[cfg(feature = "gcs")]
+        "gs" => Box::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_url(url.as_str())
+                .with_client_options(client_options)
+                .with_retry(retry)
+                .build()
+                .map_err(unavailable)?,
+        ),
+        #[cfg(feature = "azure")]
+        "az" => Box::new(
+            MicrosoftAzureBuilder::from_env()
+                .with_url(url.as_str())
+                .with_client_options(client_options)
+                .with_retry(retry)
+                .build()
+                .map_err(unavailable)?,
+        ),
+        "http" | "https" => Box::new(
+            HttpBuilder::new()
+                .with_url(url.as_str())
+                .with_client_options(client_options)
+                .with_retry(retry)
+                .build()
+                .map_err(unavailable)?,
+        ),
+        #[cfg(not(feature = "s3"))]
+        "s3" => {
+            return Err(unavailable(
+                "The S3 checkpoint stores need the `s3` feature",
+            ))
+        }
+        #[cfg(not(feature = "gcs"))]
+        "gs" => {
+            return Err(unavailable(
+                "The GCS checkpoint stores need the `gcs` feature",
+            ))
+        }
+        #[cfg(not(feature = "azure"))]
+        "az" => {
+            return Err(unavailable(
+                "The Azure checkpoint stores need the `azure` feature",
+            ))
+        }
+        scheme => {
+            return Err(unavailable(format!(
+                "Unsupported checkpoint store '{scheme}'"
+            )))
+        }
+    };
+
+    // The URL of a bucket may point to a folder inside it
+    Ok(match url.scheme() {
+        "s3" | "gs" | "az" if !url.path().trim_matches('/').is_empty() => {
+            Box::new(PrefixStore::new(store, url.path().trim_matches('/')))
+        }
+        _ => store,
+    })
+}
+
+fn unavailable(error: impl Into<BoxError>) -> HarvestError {
+    HarvestError::StoreUnavailable(error.into())
+}
+
+/// Check that the contents of a checkpoint match the digests committed to by its summary:
+/// the contents digest, and the digests of every transaction, its effects and its events.
+/// The committee signatures on the summary are not verified.
+pub fn verify_checkpoint(sequence_number: u64, checkpoint: &CheckpointData) -> Result<()> {
+    let summary = checkpoint.checkpoint_summary.data();
+    let contents = &checkpoint.checkpoint_contents;
+
+    ensure!(
+        summary.sequence_number == sequence_number,
+        "Checkpoint {sequence_number} has sequence number {}",
+        summary.sequence_number
+    );
+    ensure!(
+        contents.digest() == &summary.content_digest,
+        "Checkpoint {sequence_number} contents do not match its summary"
+    );
+    ensure!(
+        contents.size() == checkpoint.transactions.len(),
+        "Checkpoint {sequence_number} has {} transactions but its contents list {}",
+        checkpoint.transactions.len(),
+        contents.size()
+    );
+
+    for (digests, tx) in contents.iter().zip(&checkpoint.transactions) {
+        let digest = tx.transaction.digest();
+        ensure!(
+            digest == &digests.transaction,
+            "Checkpoint {sequence_number} has unexpected transaction {digest}"
+        );
+        ensure!(
+            tx.effects.digest() == digests.effects,
+            "Checkpoint {sequence_number} has unexpected effects for transaction {digest}"
+        );
+        let events_digest = tx.events.as_ref().map(|events| events.digest());
+        ensure!(
+            events_digest.as_ref() == tx.effects.events_digest(),
+            "Checkpoint {sequence_number} has unexpected events for transaction {digest}"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use move_core_types::{
+    account_address::AccountAddress,
+    language_storage::{StructTag, TypeTag},
+};
+use regex::Regex;
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    event::Event,
+    BRIDGE_PACKAGE_ID, DEEPBOOK_PACKAGE_ID, MOVE_STDLIB_PACKAGE_ID, SUI_FRAMEWORK_PACKAGE_ID,
+    SUI_SYSTEM_PACKAGE_ID,
+};
+
+use crate::{expression::FilterExpr, EventRecord};
+
+/// A pattern over Move event types of the form `address::module::name`, where the
+/// module and struct names may be replaced by the wildcard `*`. Type parameters of
+/// the event are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventTypePattern {
+    pub address: AccountAddress,
+    pub module: Option<String>,
+    pub name: Option<String>,
+}
+
+impl EventTypePattern {
+    pub fn matches(&self, type_: &StructTag) -> bool {
+        type_.address == self.address
+            && self
+                .module
+                .as_deref()
+                .is_none_or(|module| type_.module.as_str() == module)
+            && self
+                .name
+                .as_deref()
+                .is_none_or(|name| type_.name.as_str() == name)
+    }
+}
+
+impl FromStr for EventTypePattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<_> = s.split("::").collect();
+        let [address, module, name] = parts[..] else {
+            bail!("Expected an event type of the form `address::module::name`, got `{s}`");
+        };
+
+        let address = AccountAddress::from_hex_literal(address)
+            .map_err(|e| anyhow!("Invalid address `{address}`: {e}"))?;
+        let component = |c: &str| (c != "*").then(|| c.to_string());
+
+        Ok(Self {
+            address,
+            module: component(module),
+            name: component(name),
+        })
+    }
+}
+
+impl fmt::Display for EventTypePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}::{}::{}",
+            self.address.to_canonical_string(true),
+            self.module.as_deref().unwrap_or("*"),
+            self.name.as_deref().unwrap_or("*")
+        )
+    }
+}
+
+/// The packages of the Move standard library, the Sui framework and system, DeepBook and
+/// the bridge, whose events drown those of the applications.
+pub const FRAMEWORK_PACKAGES: [ObjectID; 5] = [
+    MOVE_STDLIB_PACKAGE_ID,
+    SUI_FRAMEWORK_PACKAGE_ID,
+    SUI_SYSTEM_PACKAGE_ID,
+    DEEPBOOK_PACKAGE_ID,
+    BRIDGE_PACKAGE_ID,
+];
+
+/// The predicate applied by the worker to every extracted event. Each non-empty
+/// criterion must match for the event to be kept.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub packages: Vec<ObjectID>,
+    pub event_types: Vec<EventTypePattern>,
+    /// Only keep the events whose canonical type string, e.g.
+    /// `0x000…0002::coin::CoinDeposit<0x000…0002::sui::SUI>`, matches one of these expressions
+    pub type_regexes: Vec<Regex>,
+    /// Only keep the events of generic types having all these type parameters, in any
+    /// order, e.g. the swaps of the pools of two coins
+    pub type_params: Vec<TypeTag>,
+    pub expression: Option<FilterExpr>,
+    /// Only keep the events sent by or referencing this address, see `references`
+    pub address: Option<SuiAddress>,
+    /// Drop the events emitted by these packages or whose type they define
+    pub excluded_packages: Vec<ObjectID>,
+}
+
+impl EventFilter {
+    /// Parse the criteria sent by remote clients; an empty `expression` matches every event.
+    pub fn parse(packages: &[String], event_types: &[String], expression: &str) -> Result<Self> {
+        Ok(Self {
+            packages: packages
+                .iter()
+                .map(|package| {
+                    package
+                        .parse()
+                        .map_err(|e| anyhow!("Invalid package `{package}`: {e}"))
+                })
+                .collect::<Result<_>>()?,
+            event_types: event_types
+                .iter()
+                .map(|pattern| pattern.parse())
+                .collect::<Result<_>>()?,
+            type_regexes: Vec::new(),
+            type_params: Vec::new(),
+            expression: (!expression.is_empty())
+                .then(|| expression.parse())
+                .transpose()?,
+            address: None,
+            excluded_packages: Vec::new(),
+        })
+    }
+
+    /// Whether the filter keeps every event.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+            && self.event_types.is_empty()
+            && self.type_regexes.is_empty()
+            && self.type_params.is_empty()
+            && self.expression.is_none()
+            && self.address.is_none()
+            && self.excluded_packages.is_empty()
+    }
+
+    /// A description of the criteria that does not depend on their order, one per line, so
+    /// that the same filter always describes the same event cache.
+    pub fn canonical(&self) -> String {
+        let line = |name: &str, mut values: Vec<String>| {
+            values.sort();
+            values.dedup();
+            format!("{name}: {}\n", values.join(" "))
+        };
+        let addresses = |ids: &[ObjectID]| ids.iter().map(|id| id.to_string()).collect();
+        [
+            line("packages", addresses(&self.packages)),
+            line(
+                "event_types",
+                self.event_types.iter().map(|p| p.to_string()).collect(),
+            ),
+            line(
+                "type_regexes",
+                self.type_regexes
+                    .iter()
+                    .map(|regex| regex.as_str().to_string())
+                    .collect(),
+            ),
+            line(
+                "type_params",
+                self.type_params
+                    .iter()
+                    .map(|param| param.to_canonical_string(true))
+                    .collect(),
+            ),
+            line(
+                "expression",
+                self.expression.iter().map(|e| e.to_string()).collect(),
+            ),
+            line(
+                "address",
+                self.address.iter().map(|a| a.to_string()).collect(),
+            ),
+            line("excluded_packages", addresses(&self.excluded_packages)),
+        ]
+        .concat()
+    }
+
+    pub fn matches(&self, (_index, _id, event): &EventRecord) -> bool {
+        (self.packages.is_empty() || self.packages.contains(&event.package_id))
+            && (self.event_types.is_empty()
+                || self
+                    .event_types
+                    .iter()
+                    .any(|pattern| pattern.matches(&event.type_)))
+            && (self.type_regexes.is_empty() || {
+                let type_ = event.type_.to_canonical_string(true);
+                self.type_regexes.iter().any(|regex| regex.is_match(&type_))
+            })
+            && self
+                .type_params
+                .iter()
+                .all(|param| event.type_.type_params.contains(param))
+            && self
+                .expression
+                .as_ref()
+                .is_none_or(|expression| expression.evaluate(event))
+            && self
+                .address
+                .is_none_or(|address| event.sender == address || references(event, address))
+            && !self.excluded_packages.iter().any(|package| {
+                *package == event.package_id
+                    || AccountAddress::from(*package) == event.type_.address
+            })
+    }
+}
+
+/// Whether the contents of the event hold `address`, in any of its fields. Addresses and
+/// object IDs are serialized as their 32 raw bytes, so this needs no decoding.
+pub fn references(event: &Event, address: SuiAddress) -> bool {
+    let address = address.to_inner();
+    event
+        .contents
+        .windows(address.len())
+        .any(|window| window == address)
+}
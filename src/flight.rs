@@ -0,0 +1,270 @@
+use std::{collections::VecDeque, net::SocketAddr, pin::Pin, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use arrow_array::RecordBatch;
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder,
+    flight_descriptor::DescriptorType,
+    flight_service_server::{FlightService, FlightServiceServer},
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaAsIpc, SchemaResult, Ticket,
+};
+use arrow_ipc::writer::IpcWriteOptions;
+use arrow_schema::ArrowError;
+use async_trait::async_trait;
+use futures::{stream, Future, Stream, TryStreamExt};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+use tokio::sync::watch;
+use tonic::{
+    transport::{server::TcpIncoming, Server},
+    Request, Response, Status, Streaming,
+};
+
+use crate::{
+    sinks::{
+        arrow::{event_schema, EventColumns},
+        EventSink,
+    },
+    EventRecord,
+};
+
+/// Path of the only flight, also its ticket.
+const EVENTS_FLIGHT: &str = "events";
+
+/// The latest batches of events of the run.
+#[derive(Debug, Default)]
+struct Batches {
+    batches: VecDeque<RecordBatch>,
+    /// Number of batches dropped before the first retained one
+    dropped: usize,
+    rows: usize,
+    /// Whether the run is over, so that no batch will be added
+    complete: bool,
+}
+
+/// Serves the extracted events over Arrow Flight, as the `events` flight. Its latest
+/// `retained` batches are kept in memory, and every `do_get` streams them from the oldest
+/// one, waiting for the next ones until the run is over; consumers can thus pull the events
+/// during the run or after, those connecting late missing the dropped batches.
+#[derive(Clone)]
+pub struct FlightRelay {
+    batches: Arc<watch::Sender<Batches>>,
+    retained: usize,
+}
+
+impl FlightRelay {
+    pub fn new(retained: usize) -> Self {
+        Self {
+            batches: Arc::new(watch::Sender::new(Batches::default())),
+            retained: retained.max(1),
+        }
+    }
+
+    /// The sink adding the extracted events to the flight, a batch for every
+    /// `checkpoints_per_batch` consecutive checkpoints.
+    pub fn sink(&self, checkpoints_per_batch: u64) -> FlightSink {
+        FlightSink {
+            batches: self.batches.clone(),
+            retained: self.retained,
+            checkpoints_per_batch,
+            first_checkpoint: None,
+            columns: EventColumns::default(),
+        }
+    }
+
+    /// Listen on `address`, returning the future serving the Arrow Flight service until the
+    /// process exits.
+    pub fn bind(self, address: SocketAddr) -> Result<impl Future<Output = Result<()>>> {
+        let incoming = TcpIncoming::new(address, true, None)
+            .map_err(|e| anyhow!("Cannot listen on {address}: {e}"))?;
+        Ok(async move {
+            Server::builder()
+                .add_service(FlightServiceServer::new(self))
+                .serve_with_incoming(incoming)
+                .await?;
+            Ok(())
+        })
+    }
+
+    /// The description of the flight, with its number of events once the run is over.
+    fn flight_info(&self) -> Result<FlightInfo, Status> {
+        let info = FlightInfo::new()
+            .try_with_schema(&event_schema())
+            .map_err(|e| Status::internal(e.to_string()))?
+            .with_descriptor(FlightDescriptor::new_path(vec![EVENTS_FLIGHT.to_string()]))
+            .with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(EVENTS_FLIGHT)));
+        let batches = self.batches.borrow();
+        Ok(match batches.complete {
+            true => info.with_total_records(batches.rows as i64),
+            false => info,
+        })
+    }
+}
+
+type FlightStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl FlightService for FlightRelay {
+    type HandshakeStream = FlightStream<HandshakeResponse>;
+    type ListFlightsStream = FlightStream<FlightInfo>;
+    type DoGetStream = FlightStream<FlightData>;
+    type DoPutStream = FlightStream<PutResult>;
+    type DoActionStream = FlightStream<arrow_flight::Result>;
+    type ListActionsStream = FlightStream<ActionType>;
+    type DoExchangeStream = FlightStream<FlightData>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("No authentication is needed"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let info = self.flight_info()?;
+        Ok(Response::new(Box::pin(stream::once(async { Ok(info) }))))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let descriptor = request.into_inner();
+        if descriptor.r#type() != DescriptorType::Path || descriptor.path != [EVENTS_FLIGHT] {
+            return Err(Status::not_found("The only flight is `events`"));
+        }
+        Ok(Response::new(self.flight_info()?))
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("Use get_flight_info"))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let schema = SchemaAsIpc::new(&event_schema(), &IpcWriteOptions::default())
+            .try_into()
+            .map_err(|e: ArrowError| Status::internal(e.to_string()))?;
+        Ok(Response::new(schema))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        if request.into_inner().ticket != EVENTS_FLIGHT.as_bytes() {
+            return Err(Status::not_found("The only ticket is `events`"));
+        }
+
+        let start = (self.batches.subscribe(), 0);
+        let batches = stream::unfold(start, |(mut receiver, next)| async move {
+            loop {
+                // A consumer behind the retained batches resumes from the oldest one
+                let (next, batch, complete) = {
+                    let batches = receiver.borrow_and_update();
+                    let next = next.max(batches.dropped);
+                    let batch = batches.batches.get(next - batches.dropped).cloned();
+                    (next, batch, batches.complete)
+                };
+                match batch {
+                    Some(batch) => return Some((Ok(batch), (receiver, next + 1))),
+                    None if complete => return None,
+                    // Wait for the next batch
+                    None => receiver.changed().await.ok()?,
+                }
+            }
+        });
+        let data = FlightDataEncoderBuilder::new()
+            .with_schema(event_schema())
+            .build(batches)
+            .map_err(|e| Status::internal(e.to_string()));
+        Ok(Response::new(Box::pin(data)))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("The events are read only"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("There are no actions"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(Box::pin(stream::empty())))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("The events are read only"))
+    }
+}
+
+/// Adds the events of every checkpoint to the flight of a `FlightRelay`.
+pub struct FlightSink {
+    batches: Arc<watch::Sender<Batches>>,
+    retained: usize,
+    checkpoints_per_batch: u64,
+    first_checkpoint: Option<u64>,
+    columns: EventColumns,
+}
+
+impl FlightSink {
+    fn push_batch(&mut self) -> Result<()> {
+        self.first_checkpoint = None;
+        if self.columns.is_empty() {
+            return Ok(());
+        }
+        let batch = self.columns.take_batch()?;
+        self.batches.send_modify(|batches| {
+            batches.rows += batch.num_rows();
+            batches.batches.push_back(batch);
+            if batches.batches.len() > self.retained {
+                batches.batches.pop_front();
+                batches.dropped += 1;
+            }
+        });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for FlightSink {
+    async fn process(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let first = *self.first_checkpoint.get_or_insert(summary.sequence_number);
+        events.iter().for_each(|record| self.columns.push(record));
+
+        if summary.sequence_number + 1 - first >= self.checkpoints_per_batch {
+            self.push_batch()?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.push_batch()?;
+        self.batches.send_modify(|batches| batches.complete = true);
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::Future;
+use sui_types::messages_checkpoint::{CertifiedCheckpointSummary, CheckpointSequenceNumber};
+
+use crate::{channel::Receiver, error::HarvestResult, EventRecord};
+
+/// Processing logic called with the items extracted from every checkpoint, in checkpoint
+/// order. An alternative to draining the receiver returned by the workers.
+#[async_trait]
+pub trait CheckpointHandler<T = EventRecord>: Send {
+    async fn handle(&mut self, summary: CertifiedCheckpointSummary, items: Vec<T>) -> Result<()>;
+}
+
+/// Drive the executor returned by a worker constructor, calling the handler with the items
+/// of every checkpoint. Returns when all checkpoints are processed, or on the first error
+/// of either the executor or the handler.
+pub async fn run_with_handler<T, H>(
+    executor: impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+    mut receiver: Receiver<(CertifiedCheckpointSummary, Vec<T>)>,
+    handler: &mut H,
+) -> Result<HashMap<String, CheckpointSequenceNumber>>
+where
+    H: CheckpointHandler<T> + ?Sized,
+{
+    let consume = async {
+        // The receiver is closed once the executor drops the worker
+        while let Some((summary, items)) = receiver.recv().await {
+            handler.handle(summary, items).await?;
+        }
+        anyhow::Ok(())
+    };
+    let execute = async { anyhow::Ok(executor.await?) };
+    let (watermarks, ()) = tokio::try_join!(execute, consume)?;
+    Ok(watermarks)
+}
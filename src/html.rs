@@ -0,0 +1,269 @@
+use std::fmt::Write;
+
+use crate::{labels::named, report::Report};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1, h2 { font-weight: 500; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { padding: 0.2em 0.8em; text-align: left; border-bottom: 1px solid #eee; }
+th { cursor: pointer; user-select: none; background: #f6f6f6; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+td.bar { width: 20em; }
+td.bar div { background: #4a7bd0; height: 0.8em; }
+code { font-size: 0.9em; }
+.chart { display: flex; align-items: flex-end; height: 12em; gap: 1px; margin-bottom: 2em; }
+.chart div { flex: 1; background: #4a7bd0; min-height: 1px; }
+";
+
+/// Sort the rows of a table by the clicked column, toggling the order.
+const SCRIPT: &str = "
+document.querySelectorAll('table').forEach(table => {
+  table.querySelectorAll('th').forEach((th, column) => th.addEventListener('click', () => {
+    const body = table.tBodies[0];
+    const ascending = th.dataset.order !== 'asc';
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    const key = row => {
+      const cell = row.cells[column];
+      return cell.dataset.value !== undefined ? Number(cell.dataset.value) : cell.textContent;
+    };
+    const rows = Array.from(body.rows).sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      return (x < y ? -1 : x > y ? 1 : 0) * (ascending ? 1 : -1);
+    });
+    rows.forEach(row => body.appendChild(row));
+  }));
+});
+";
+
+/// A self-contained HTML page with the given sections, without external resources.
+pub fn page(title: &str, sections: &[String]) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+        <style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n{}\n<script>{SCRIPT}</script>\n\
+        </body>\n</html>\n",
+        sections.join("\n"),
+        title = escape(title),
+    )
+}
+
+/// The event histogram as sortable tables, with bars relative to the largest count.
+pub fn histogram_section(report: &Report) -> String {
+    let mut html = String::new();
+    let summary = &report.summary;
+    let _ = writeln!(
+        html,
+        "<p>{} events from {} packages, with an average of {} &plusmn; {:.1} events each, and {} bytes of contents.</p>",
+        report.total_events, summary.total_packages, summary.average_events, summary.stdev_events, report.total_bytes
+    );
+    for (name, distribution) in [
+        ("package", &summary.per_package),
+        ("checkpoint", &summary.per_checkpoint),
+    ] {
+        if let Some(distribution) = distribution {
+            let _ = writeln!(
+                html,
+                "<p>Events per {name}: {}.</p>",
+                escape(&distribution.to_string())
+            );
+        }
+    }
+
+    let rows: Vec<_> = report
+        .addresses
+        .iter()
+        .flat_map(|address| {
+            let modules = address.modules.iter().flatten();
+            address
+                .types
+                .iter()
+                .chain(modules.flat_map(|module| &module.types))
+                .map(|type_| {
+                    (
+                        type_.count,
+                        vec![
+                            named(&address.address, &address.label).to_string(),
+                            type_.short_type.clone(),
+                        ],
+                    )
+                })
+        })
+        .collect();
+    table(&mut html, "Events by type", &["Address", "Type"], &rows);
+
+    let rows: Vec<_> = report
+        .addresses
+        .iter()
+        .flat_map(|address| {
+            address.modules.iter().flatten().map(|module| {
+                (
+                    module.count,
+                    vec![
+                        named(&address.address, &address.label).to_string(),
+                        module.module.clone(),
+                    ],
+                )
+            })
+        })
+        .collect();
+    if !rows.is_empty() {
+        table(&mut html, "Events by module", &["Address", "Module"], &rows);
+    }
+
+    let rows: Vec<_> = report
+        .packages
+        .iter()
+        .map(|package| {
+            (
+                package.count,
+                vec![named(&package.package, &package.label).to_string()],
+            )
+        })
+        .collect();
+    table(&mut html, "Events by package", &["Package"], &rows);
+
+    if let Some(senders) = &report.senders {
+        let rows: Vec<_> = senders
+            .iter()
+            .map(|sender| {
+                (
+                    sender.count,
+                    vec![named(&sender.sender, &sender.label).to_string()],
+                )
+            })
+            .collect();
+        table(&mut html, "Events by sender", &["Sender"], &rows);
+    }
+
+    if let Some(calls) = &report.calls {
+        let rows: Vec<_> = calls
+            .iter()
+            .map(|call| {
+                (
+                    call.count,
+                    vec![
+                        named(&call.package, &call.label).to_string(),
+                        call.function.clone(),
+                    ],
+                )
+            })
+            .collect();
+        table(
+            &mut html,
+            "Calls by function",
+            &["Package", "Function"],
+            &rows,
+        );
+    }
+
+    if let Some(transactions) = &report.transactions {
+        let rows: Vec<_> = transactions
+            .iter()
+            .flat_map(|transaction| {
+                transaction.types.iter().map(|type_| {
+                    (
+                        type_.count,
+                        vec![
+                            transaction.digest.clone(),
+                            named(&transaction.sender, &transaction.label).to_string(),
+                            type_.short_type.clone(),
+                        ],
+                    )
+                })
+            })
+            .collect();
+        table(
+            &mut html,
+            "Events by transaction",
+            &["Transaction", "Sender", "Type"],
+            &rows,
+        );
+    }
+
+    if let Some(time_series) = &report.time_series {
+        let _ = writeln!(
+            html,
+            "<h2>Events per {}s</h2>\n<div class=\"chart\">",
+            time_series.width_ms / 1000
+        );
+        let max = time_series
+            .buckets
+            .iter()
+            .map(|b| b.total)
+            .max()
+            .unwrap_or(0);
+        for bucket in &time_series.buckets {
+            let _ = writeln!(
+                html,
+                "<div style=\"height: {:.1}%\" title=\"{}: {} events\"></div>",
+                percent(bucket.total, max),
+                bucket.start_ms,
+                bucket.total
+            );
+        }
+        html.push_str("</div>\n");
+    }
+
+    if let Some(epochs) = &report.epochs {
+        let rows: Vec<_> = epochs
+            .iter()
+            .map(|epoch| {
+                let change = epoch
+                    .change_pct
+                    .map(|change| format!("{change:+.1}%"))
+                    .unwrap_or_default();
+                (
+                    epoch.total,
+                    vec![
+                        epoch.epoch.to_string(),
+                        format!("{}..={}", epoch.first_checkpoint, epoch.last_checkpoint),
+                        format!("{:.2}", epoch.events_per_checkpoint),
+                        change,
+                    ],
+                )
+            })
+            .collect();
+        table(
+            &mut html,
+            "Events by epoch",
+            &["Epoch", "Checkpoints", "Per checkpoint", "Change"],
+            &rows,
+        );
+    }
+    html
+}
+
+/// A sortable table of `rows`, each made of a count and the given text columns.
+fn table(html: &mut String, title: &str, columns: &[&str], rows: &[(usize, Vec<String>)]) {
+    let max = rows.iter().map(|(count, _)| *count).max().unwrap_or(0);
+    let _ = writeln!(html, "<h2>{}</h2>\n<table>\n<thead><tr>", escape(title));
+    for column in columns {
+        let _ = write!(html, "<th>{}</th>", escape(column));
+    }
+    html.push_str("<th>Count</th><th></th></tr></thead>\n<tbody>\n");
+    for (count, cells) in rows {
+        html.push_str("<tr>");
+        for cell in cells {
+            let _ = write!(html, "<td><code>{}</code></td>", escape(cell));
+        }
+        let _ = writeln!(
+            html,
+            "<td class=\"number\" data-value=\"{count}\">{count}</td>\
+            <td class=\"bar\" data-value=\"{count}\"><div style=\"width: {:.1}%\"></div></td></tr>",
+            percent(*count, max)
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+}
+
+fn percent(value: usize, max: usize) -> f64 {
+    value as f64 * 100.0 / max.max(1) as f64
+}
+
+/// Escape the text of an element or attribute, e.g. the type parameters of a type.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
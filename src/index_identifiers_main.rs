@@ -5,12 +5,15 @@ use std::{
 };
 
 use anyhow::Result;
-use futures::{stream::{FuturesOrdered, FuturesUnordered}, StreamExt};
+use futures::{
+    stream::{FuturesOrdered, FuturesUnordered},
+    StreamExt,
+};
 use sui_sdk::SuiClientBuilder;
 
 use async_trait::async_trait;
 
-use sui_data_ingestion_core::Worker;
+use harvestlib::Worker;
 
 use sui_types::{base_types::SuiAddress, full_checkpoint_content::CheckpointData, object::Owner};
 
@@ -52,7 +55,6 @@ pub struct IdentifierIndexWorker {
 
 impl IdentifierIndexWorker {
     pub async fn run() {
-
         let args = Args::parse();
 
         // define the events folder
@@ -84,11 +86,11 @@ impl IdentifierIndexWorker {
         // let url = Url::parse(&remote_store_url).expect("Cannot parse url");
         // let (store, _path) = object_store::parse_url(&url).expect("Failed to open store from url");
         let store = object_store::http::HttpBuilder::new()
-        .with_url(remote_store_url)
-        // .with_client_options(client_options)
-        // .with_retry(5)
-        .build()
-        .expect("Failed to build http store");
+            .with_url(remote_store_url)
+            // .with_client_options(client_options)
+            // .with_retry(5)
+            .build()
+            .expect("Failed to build http store");
 
         // A tokio that downloads checkpoint data and sends it to the worker
         let join = tokio::spawn(async move {
@@ -99,7 +101,6 @@ impl IdentifierIndexWorker {
             loop {
                 while fut.len() < concurrency {
                     let future = async {
-
                         let xxx = checkpoint_number.fetch_add(1, Ordering::SeqCst);
 
                         let path = Path::from(format!("{}.chk", xxx));
@@ -142,7 +143,7 @@ impl IdentifierIndexWorker {
                     all_data.extend(index_terms);
                     initial += 1;
 
-                    println!("Checkpoint: {} transactions: {}", checkpoint_seq, all_txs);
+                    tracing::info!("Checkpoint: {} transactions: {}", checkpoint_seq, all_txs);
 
                     if all_txs >= 1_000_000 {
                         // encode the batch with bcs
@@ -156,7 +157,7 @@ impl IdentifierIndexWorker {
                         encoder.write_all(&bcs_data).unwrap();
                         let gz_data = encoder.finish().unwrap();
 
-                        println!(
+                        tracing::info!(
                             "LEN BYTES: {} transactions: {} uncompressed: {}",
                             gz_data.len(),
                             all_txs,
@@ -165,7 +166,7 @@ impl IdentifierIndexWorker {
 
                         // Filename
                         let filename = format!("{:016x}.index.bcs.gz", first);
-                        println!("Writing batch to file: {}", filename);
+                        tracing::info!("Writing batch to file: {}", filename);
 
                         // Make a file in events folder with the first and last sequence numbers
                         let file = events_folder.join(filename);
@@ -264,12 +265,19 @@ impl Worker for IdentifierIndexWorker {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                tracing_subscriber::EnvFilter::new("warn,index_identifiers=info")
+            }),
+        )
+        .with_writer(std::io::stderr)
+        .init();
 
     let full_node_url = "https://fullnode.mainnet.sui.io:443";
 
     let sui_mainnet = SuiClientBuilder::default().build(full_node_url).await?;
-    println!("Sui mainnet version: {}", sui_mainnet.api_version());
+    tracing::info!("Sui mainnet version: {}", sui_mainnet.api_version());
 
     // Get and print the latest checkpoint
     let _latest_checkpoint = sui_mainnet
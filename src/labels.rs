@@ -0,0 +1,166 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{Context, Result};
+use sui_types::base_types::ObjectID;
+
+use crate::report::{BalanceReport, CoinFlowReport, DiffReport, GasReport, PackageReport, Report};
+
+/// Popular mainnet packages. Events keep the ID of the first version of the package
+/// defining their type, and upgraded packages emitting events are only labeled if listed.
+const WELL_KNOWN: &[(&str, &str)] = &[
+    ("0x1", "Move stdlib"),
+    ("0x2", "Sui framework"),
+    ("0x3", "Sui system"),
+    ("0xb", "Sui bridge"),
+    ("0xdee9", "DeepBook v2"),
+    (
+        "0x2c8d603bc51326b8c13cef9dd07031a408a48dddb541963357661df5d3204809",
+        "DeepBook v3",
+    ),
+    (
+        "0x1eabed72c53feb3805120a081dc15963c204dc8d091542592abaf7a35689b2fb",
+        "Cetus CLMM",
+    ),
+    (
+        "0x91bfbc386a41afcfd9b2533058d7e915a1d3829089cc268ff4333d54d6339ca1",
+        "Turbos CLMM",
+    ),
+    (
+        "0x5306f64e312b581766351c07af79c72fcb1cd25147157fdc2f8ad76de9a3fb6a",
+        "Wormhole",
+    ),
+    (
+        "0x26efee2b51c911237888e5dc6702868abca3c7ac12c53f76ef8eba0697695e3d",
+        "Wormhole token bridge",
+    ),
+    (
+        "0xd22b24490e0bae52676651b4f56660a5ff8022a2576e0089f79b3c88d44e08f0",
+        "SuiNS",
+    ),
+];
+
+/// Human readable names of package and account addresses, read from a TOML file of
+/// `address = name` entries, e.g. `"0xdee9" = "DeepBook"`.
+#[derive(Debug, Clone, Default)]
+pub struct Labels(HashMap<ObjectID, String>);
+
+impl Labels {
+    /// The compiled-in names of popular mainnet packages.
+    pub fn well_known() -> Self {
+        Self(
+            WELL_KNOWN
+                .iter()
+                .map(|(address, name)| {
+                    let address = ObjectID::from_str(address).expect("Invalid well-known address");
+                    (address, name.to_string())
+                })
+                .collect(),
+        )
+    }
+
+    /// Add the labels of `other`, replacing those of the same addresses.
+    pub fn extend(&mut self, other: Labels) {
+        self.0.extend(other.0);
+    }
+
+    /// The labels of `address = name` entries, e.g. those of a labels file.
+    pub fn from_entries(entries: HashMap<String, String>) -> Result<Self> {
+        entries
+            .into_iter()
+            .map(|(address, name)| {
+                let address = ObjectID::from_str(&address)
+                    .with_context(|| format!("Invalid address `{address}` in the labels file"))?;
+                Ok((address, name))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The label of `address`, if it has one.
+    pub fn get(&self, address: &str) -> Option<String> {
+        ObjectID::from_str(address)
+            .ok()
+            .and_then(|address| self.0.get(&address))
+            .cloned()
+    }
+
+    fn label_packages(&self, packages: &mut [PackageReport]) {
+        for package in packages {
+            package.label = self.get(&package.package);
+        }
+    }
+}
+
+/// The label of an address if it has one, or the address, as printed in the text reports.
+pub fn named<'a>(address: &'a str, label: &'a Option<String>) -> &'a str {
+    label.as_deref().unwrap_or(address)
+}
+
+/// A report whose addresses can be named by their labels, which the text reports print
+/// instead of the addresses and the JSON reports next to them.
+pub trait Labeled {
+    fn label(&mut self, labels: &Labels);
+}
+
+impl Labeled for Report {
+    fn label(&mut self, labels: &Labels) {
+        if labels.is_empty() {
+            return;
+        }
+        for address in &mut self.addresses {
+            address.label = labels.get(&address.address);
+        }
+        labels.label_packages(&mut self.packages);
+        for sender in self.senders.iter_mut().flatten() {
+            sender.label = labels.get(&sender.sender);
+        }
+        for call in self.calls.iter_mut().flatten() {
+            call.label = labels.get(&call.package);
+        }
+        for transaction in self.transactions.iter_mut().flatten() {
+            transaction.label = labels.get(&transaction.sender);
+        }
+        for bucket in self.time_series.iter_mut().flat_map(|t| &mut t.buckets) {
+            labels.label_packages(&mut bucket.packages);
+        }
+        for epoch in self.epochs.iter_mut().flatten() {
+            labels.label_packages(&mut epoch.packages);
+        }
+    }
+}
+
+impl Labeled for BalanceReport {
+    fn label(&mut self, labels: &Labels) {
+        for owner in self.coins.iter_mut().flat_map(|coin| &mut coin.owners) {
+            owner.label = labels.get(&owner.owner);
+        }
+    }
+}
+
+impl Labeled for CoinFlowReport {
+    fn label(&mut self, labels: &Labels) {
+        for flow in self.senders.iter_mut().chain(&mut self.recipients) {
+            flow.label = labels.get(&flow.owner);
+        }
+    }
+}
+
+impl Labeled for GasReport {
+    fn label(&mut self, labels: &Labels) {
+        for package in &mut self.packages {
+            package.label = labels.get(&package.package);
+        }
+    }
+}
+
+impl Labeled for DiffReport {
+    fn label(&mut self, labels: &Labels) {
+        for type_ in &mut self.types {
+            type_.label = labels.get(&type_.address);
+        }
+    }
+}
@@ -1,37 +1,76 @@
-use std::{collections::HashMap, path::PathBuf};
+pub mod analyzer;
+pub mod balances;
+pub mod cache;
+pub mod channel;
+pub mod commit;
+#[cfg(feature = "tui")]
+pub mod dashboard;
+#[cfg(feature = "full-node")]
+pub mod decode;
+#[cfg(feature = "embed")]
+pub mod embed;
+pub mod error;
+pub mod event_cache;
+pub mod expression;
+pub mod fetcher;
+pub mod filter;
+#[cfg(feature = "flight")]
+pub mod flight;
+pub mod handler;
+pub mod html;
+pub mod labels;
+#[cfg(feature = "full-node")]
+pub mod lineage;
+pub mod metrics;
+pub mod presets;
+pub mod progress;
+pub mod report;
+pub mod rolling;
+pub mod sampling;
+#[cfg(feature = "script")]
+pub mod script;
+#[cfg(feature = "grpc")]
+pub mod serve;
+pub mod sinks;
+pub mod store;
+pub mod subscribe;
+pub mod summaries;
+pub mod transactions;
+pub mod type_index;
 
-use anyhow::Result;
-use async_trait::async_trait;
-use prometheus::Registry;
-use sui_data_ingestion_core::{
-    DataIngestionMetrics, IndexerExecutor, ProgressStore, ReaderOptions, Worker, WorkerPool,
+use std::{
+    collections::HashMap,
+    ops::{Range, RangeInclusive},
+    path::PathBuf,
+    sync::Arc,
 };
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
 use sui_types::{
     event::{Event, EventID},
     full_checkpoint_content::CheckpointData,
     messages_checkpoint::{CertifiedCheckpointSummary, CheckpointSequenceNumber},
 };
 
-use futures::Future;
-use serde::{Deserialize, Serialize};
-use tokio::sync::{
-    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
-    oneshot,
-};
+use channel::{ChannelConfig, OverflowPolicy, Receiver, Sender};
+use commit::{Commit, CommitLog, ProgressStore, COMMIT_TASK};
+use error::{HarvestError, HarvestResult};
+use event_cache::EventCache;
+use fetcher::{CheckpointFetcher, CheckpointSource, FetchOptions, ReaderOptions};
+use futures::{Future, StreamExt};
+use metrics::HarvestMetrics;
+use move_core_types::language_storage::StructTag;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use store::CheckpointStore;
+use subscribe::EventStream;
+use tracing::Instrument;
+use transactions::{extract_transactions, TransactionRecord};
+use type_index::TypeIndex;
+use url::Url;
 
-pub struct ShimProgressStore(pub u64);
-
-#[async_trait]
-impl ProgressStore for ShimProgressStore {
-    async fn load(&mut self, _: String) -> Result<CheckpointSequenceNumber> {
-        Ok(self.0)
-    }
-    async fn save(&mut self, _: String, _: CheckpointSequenceNumber) -> Result<()> {
-        Ok(())
-    }
-}
 // derive serialize
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventIndex {
     pub checkpoint_sequence_number: u64,
     pub transaction_sequence_number: u64,
@@ -59,135 +98,869 @@ where
     F: Fn(&EventRecord) -> bool,
 {
     filter: F,
-    sender: UnboundedSender<(CertifiedCheckpointSummary, Vec<EventRecord>)>,
+    sender: Sender<(CertifiedCheckpointSummary, Vec<EventRecord>)>,
+    event_cache: Option<EventCache>,
+    type_index: Option<TypeIndex>,
 }
 
 impl<F> EventExtractWorker<F>
 where
     F: Fn(&EventRecord) -> bool + Send + Sync + 'static,
 {
-    pub async fn new(
-        initial: u64,
-        length: u64,
-        filter: F,
-        remote_store_url: String,
-        concurrency: usize,
-        reader_options: Option<ReaderOptions>,
-        cache_folder: Option<PathBuf>,
-    ) -> Result<(
-        impl Future<Output = Result<HashMap<String, CheckpointSequenceNumber>>>,
-        UnboundedReceiver<(CertifiedCheckpointSummary, Vec<EventRecord>)>,
+    /// Configure a worker keeping the events accepted by `filter`.
+    pub fn builder(filter: F) -> EventExtractWorkerBuilder<F> {
+        EventExtractWorkerBuilder::new(filter)
+    }
+}
+
+/// Default source of the checkpoints
+pub const MAINNET_CHECKPOINTS_URL: &str = "https://checkpoints.mainnet.sui.io";
+
+/// The stores the workers read from by default, that of `MAINNET_CHECKPOINTS_URL`.
+pub(crate) fn default_remote_store_urls() -> Vec<Url> {
+    vec![Url::parse(MAINNET_CHECKPOINTS_URL).expect("The mainnet URL is valid")]
+}
+
+/// Configures and starts an `EventExtractWorker`. By default it follows the chain from
+/// checkpoint 0 of the mainnet checkpoint store, downloading 5 checkpoints at a time,
+/// without cache or metrics.
+pub struct EventExtractWorkerBuilder<F, S = CheckpointFetcher> {
+    filter: F,
+    start: u64,
+    limit: Option<u64>,
+    ranges: Option<Vec<Range<u64>>>,
+    remote_store_urls: Vec<Url>,
+    checkpoint_store: Option<S>,
+    concurrency: usize,
+    reader_options: Option<ReaderOptions>,
+    fetch_options: FetchOptions,
+    cache_folder: Option<PathBuf>,
+    event_cache: Option<EventCache>,
+    type_index: Option<TypeIndex>,
+    channel: ChannelConfig,
+    metrics: Option<HarvestMetrics>,
+}
+
+impl<F> EventExtractWorkerBuilder<F>
+where
+    F: Fn(&EventRecord) -> bool + Send + Sync + 'static,
+{
+    pub fn new(filter: F) -> Self {
+        Self {
+            filter,
+            start: 0,
+            limit: None,
+            ranges: None,
+            remote_store_urls: default_remote_store_urls(),
+            checkpoint_store: None,
+            concurrency: 5,
+            reader_options: None,
+            fetch_options: FetchOptions::default(),
+            cache_folder: None,
+            event_cache: None,
+            type_index: None,
+            channel: ChannelConfig::default(),
+            metrics: None,
+        }
+    }
+}
+
+impl<F, S> EventExtractWorkerBuilder<F, S>
+where
+    F: Fn(&EventRecord) -> bool + Send + Sync + 'static,
+    S: CheckpointStore + 'static,
+{
+    /// First checkpoint to process.
+    pub fn start(mut self, start: u64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Number of checkpoints to process, unlimited by default.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Process these ranges of checkpoints one after the other, instead of `limit`
+    /// checkpoints from `start`, in a single run sharing the downloads and the cache.
+    pub fn ranges(mut self, ranges: impl IntoIterator<Item = RangeInclusive<u64>>) -> Self {
+        self.ranges = Some(
+            ranges
+                .into_iter()
+                .map(|range| *range.start()..range.end().saturating_add(1))
+                .collect(),
+        );
+        self
+    }
+
+    /// See `fetcher::CheckpointFetcher::new` for the supported URLs.
+    pub fn remote_store_url(mut self, url: Url) -> Self {
+        self.remote_store_urls = vec![url];
+        self
+    }
+
+    /// Spread the downloads over these stores, failing over between them.
+    pub fn remote_store_urls(mut self, urls: impl IntoIterator<Item = Url>) -> Self {
+        self.remote_store_urls = urls.into_iter().collect();
+        self
+    }
+
+    /// Read the checkpoints from `store` instead of the remote store URL, e.g. an archive
+    /// with a custom layout. Its checkpoints are not cached.
+    pub fn checkpoint_store<T: CheckpointStore + 'static>(
+        self,
+        store: T,
+    ) -> EventExtractWorkerBuilder<F, T> {
+        EventExtractWorkerBuilder {
+            filter: self.filter,
+            start: self.start,
+            limit: self.limit,
+            ranges: self.ranges,
+            remote_store_urls: self.remote_store_urls,
+            checkpoint_store: Some(store),
+            concurrency: self.concurrency,
+            reader_options: self.reader_options,
+            fetch_options: self.fetch_options,
+            cache_folder: self.cache_folder,
+            event_cache: self.event_cache,
+            type_index: self.type_index,
+            channel: self.channel,
+            metrics: self.metrics,
+        }
+    }
+
+    /// The same builder, with the filter returned by `map` from the current one.
+    fn map_filter<G>(self, map: impl FnOnce(F) -> G) -> EventExtractWorkerBuilder<G, S> {
+        EventExtractWorkerBuilder {
+            filter: map(self.filter),
+            start: self.start,
+            limit: self.limit,
+            ranges: self.ranges,
+            remote_store_urls: self.remote_store_urls,
+            checkpoint_store: self.checkpoint_store,
+            concurrency: self.concurrency,
+            reader_options: self.reader_options,
+            fetch_options: self.fetch_options,
+            cache_folder: self.cache_folder,
+            event_cache: self.event_cache,
+            type_index: self.type_index,
+            channel: self.channel,
+            metrics: self.metrics,
+        }
+    }
+
+    /// Where the worker reads the checkpoints from.
+    fn source(&mut self) -> CheckpointSource {
+        match self.checkpoint_store.take() {
+            Some(store) => CheckpointSource::Store(Arc::new(store)),
+            None => std::mem::take(&mut self.remote_store_urls).into(),
+        }
+    }
+
+    /// Number of checkpoints downloaded concurrently.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn reader_options(mut self, options: ReaderOptions) -> Self {
+        self.reader_options = Some(options);
+        self
+    }
+
+    /// Replaces all fetch options, including the number of retries.
+    pub fn fetch_options(mut self, options: FetchOptions) -> Self {
+        self.fetch_options = options;
+        self
+    }
+
+    /// Whether the checkpoints are received strictly in sequence number order, the default.
+    /// Otherwise each is processed as soon as it is downloaded and deserialized, so that a
+    /// slow download does not hold back the others.
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.fetch_options.ordered = ordered;
+        self
+    }
+
+    /// Number of times a failed download is retried before giving up.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.fetch_options.retry_policy.max_retries = retries;
+        self
+    }
+
+    /// Cache the downloaded checkpoints in this folder.
+    pub fn cache_folder(mut self, folder: impl Into<PathBuf>) -> Self {
+        self.cache_folder = Some(folder.into());
+        self
+    }
+
+    /// Store the filtered events of every checkpoint in `cache`, and replay those already
+    /// stored instead of downloading their checkpoints. This only applies to `build`, as
+    /// the transactions of `build_with_transactions` are not cached.
+    pub fn event_cache(mut self, cache: EventCache) -> Self {
+        self.event_cache = Some(cache);
+        self
+    }
+
+    /// Record the locations of all the events of every downloaded checkpoint in `index`,
+    /// before filtering them. Checkpoints replayed from the event cache are not indexed.
+    pub fn type_index(mut self, index: TypeIndex) -> Self {
+        self.type_index = Some(index);
+        self
+    }
+
+    pub fn channel(mut self, config: ChannelConfig) -> Self {
+        self.channel = config;
+        self
+    }
+
+    /// Block the worker when this number of checkpoints is waiting for the receiver.
+    pub fn channel_capacity(self, capacity: usize) -> Self {
+        self.channel(ChannelConfig::bounded(capacity, OverflowPolicy::Block))
+    }
+
+    pub fn metrics(mut self, metrics: HarvestMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    fn length(&self) -> u64 {
+        self.limit.unwrap_or(u64::MAX - self.start)
+    }
+
+    /// The ranges of checkpoints to process, a single one unless set with `ranges`.
+    fn checkpoint_ranges(&self) -> Vec<Range<u64>> {
+        self.ranges
+            .clone()
+            .unwrap_or_else(|| vec![self.start..self.start.saturating_add(self.length())])
+    }
+
+    /// Start the worker, returning the future driving it and the receiver of the events
+    /// of every checkpoint, in checkpoint order unless `ordered` is unset.
+    pub async fn build(
+        mut self,
+    ) -> HarvestResult<(
+        impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+        Receiver<(CertifiedCheckpointSummary, Vec<EventRecord>)>,
     )> {
-        let (sender, mut receiver) =
-            unbounded_channel::<(CertifiedCheckpointSummary, Vec<EventRecord>)>();
-        let (sender_out, receiver_out) =
-            unbounded_channel::<(CertifiedCheckpointSummary, Vec<EventRecord>)>();
-        let (exit_sender, exit_receiver) = oneshot::channel();
-
-        tokio::spawn(async move {
-            let mut data = HashMap::new();
-            let mut next_wait_for = initial;
-            loop {
-                if let Some((checkpoint_summary, item)) = receiver.recv().await {
-                    data.insert(
-                        checkpoint_summary.sequence_number,
-                        (checkpoint_summary, item),
-                    );
+        let ranges = self.checkpoint_ranges();
+        let source = self.source();
+        let (filter, event_cache, type_index) = (self.filter, self.event_cache, self.type_index);
+        run_ordered(
+            |sender| EventExtractWorker {
+                filter,
+                sender,
+                event_cache,
+                type_index,
+            },
+            ranges,
+            source,
+            self.concurrency,
+            self.reader_options,
+            Some(self.fetch_options),
+            self.cache_folder,
+            Some(self.channel),
+            self.metrics,
+        )
+        .await
+    }
 
-                    while data.contains_key(&next_wait_for) {
-                        let data_item = data.remove(&next_wait_for).unwrap();
-                        let Ok(_) = sender_out.send(data_item) else {
-                            return;
-                        };
-                        next_wait_for += 1;
-
-                        // Exit automatically if we reach the end
-                        if next_wait_for == initial + length {
-                            exit_sender.send(()).unwrap();
-                            return;
-                        }
-                    }
-                }
-            }
+    /// Like `build`, but only keep the events of type `tag` that the filter accepts, and
+    /// receive them deserialized into the Rust type `T` mirroring their Move struct, e.g.
+    /// `EventExtractWorker::builder(|_| true).subscribe::<PoolCreated>(tag)`.
+    pub async fn subscribe<T: DeserializeOwned>(
+        self,
+        tag: StructTag,
+    ) -> HarvestResult<(
+        impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+        EventStream<T>,
+    )> {
+        let filter_tag = tag.clone();
+        let builder = self.map_filter(|filter| {
+            move |record: &EventRecord| record.2.type_ == filter_tag && filter(record)
         });
+        let (executor, receiver) = builder.build().await?;
+        Ok((executor, EventStream::new(tag, receiver)))
+    }
 
-        let worker = Self { filter, sender };
+    /// Like `build`, but resume from the watermark of `store` if it is past `start`, and
+    /// deliver every checkpoint with a `Commit` that advances the watermark once
+    /// acknowledged. Checkpoints are delivered in order, and those skipped by the worker
+    /// count as acknowledged once a later checkpoint is delivered. The capacity of the
+    /// `channel` applies, but not its overflow policy: delivery blocks when it is full.
+    ///
+    /// A checkpoint processed but not acknowledged before a crash is delivered again; for
+    /// exactly-once processing into a database, the `store` can keep the watermark in the
+    /// database, saving it along with the data of the acknowledged checkpoints.
+    pub async fn build_with_commits(
+        mut self,
+        mut store: impl ProgressStore + Send + 'static,
+    ) -> HarvestResult<(
+        impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+        Receiver<(CertifiedCheckpointSummary, Vec<EventRecord>, Commit)>,
+    )> {
+        let watermark = store.load(COMMIT_TASK.to_string()).await?;
+        let end = self.start.saturating_add(self.length());
+        self.start = self.start.max(watermark);
+        self.limit = Some(end.saturating_sub(self.start));
+        // The checkpoints of the ranges before the watermark were already processed
+        if let Some(ranges) = &mut self.ranges {
+            ranges
+                .iter_mut()
+                .for_each(|range| range.start = range.start.max(watermark));
+            ranges.retain(|range| !range.is_empty());
+        }
+        self.fetch_options.ordered = true;
+        let first = self
+            .checkpoint_ranges()
+            .first()
+            .map_or(self.start, |range| range.start);
+        let log = CommitLog::new(first, Box::new(store));
 
-        // Also make a custom executor
-        let metrics = DataIngestionMetrics::new(&Registry::new());
-        let progress_store = ShimProgressStore(initial);
-        let mut executor = IndexerExecutor::new(progress_store, 1, metrics);
-        let worker_pool = WorkerPool::new(worker, "workflow".to_string(), concurrency);
-        executor.register(worker_pool).await?;
+        // Attach a commit to every checkpoint of the worker. A dropped checkpoint would never
+        // be acknowledged, stalling the watermark, so this channel always blocks
+        let (sender, receiver) = channel::channel(ChannelConfig {
+            overflow: OverflowPolicy::Block,
+            ..self.channel.clone()
+        });
+        self.channel = ChannelConfig::bounded(1, OverflowPolicy::Block);
+        let mut next_checkpoint = first;
+        let (join, mut checkpoints) = self.build().await?;
+        let forward = async move {
+            while let Some((summary, events)) = checkpoints.recv().await {
+                let sequence_number = summary.sequence_number;
+                log.acknowledge(next_checkpoint..sequence_number).await?;
+                next_checkpoint = sequence_number + 1;
+                sender
+                    .send((summary, events, log.commit(sequence_number)))
+                    .await?;
+            }
+            Ok::<_, HarvestError>(())
+        };
 
-        let folder = cache_folder.unwrap_or_else(|| tempfile::tempdir().unwrap().into_path());
+        let join = async move {
+            let (progress, ()) = futures::try_join!(join, forward)?;
+            Ok(progress)
+        };
+        Ok((join, receiver))
+    }
 
-        let join = executor.run(
-            folder,
-            Some(remote_store_url),
-            vec![],
-            reader_options.unwrap_or_default(),
-            exit_receiver,
-        );
+    /// Like `build`, but also extract the transactions accepted by `transaction_filter`,
+    /// downloading each checkpoint only once. The events and transactions of a checkpoint
+    /// are sent as one message, so that the overflow policy drops or keeps them together.
+    pub async fn build_with_transactions<G>(
+        mut self,
+        transaction_filter: G,
+    ) -> HarvestResult<(
+        impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+        Receiver<(
+            CertifiedCheckpointSummary,
+            Vec<EventRecord>,
+            Vec<TransactionRecord>,
+        )>,
+    )>
+    where
+        G: Fn(&TransactionRecord) -> bool + Send + Sync + 'static,
+    {
+        let ranges = self.checkpoint_ranges();
+        let source = self.source();
+        let (sender, receiver) = channel::channel(self.channel);
+        let worker = EventTransactionWorker {
+            filter: self.filter,
+            transaction_filter,
+            sender,
+            event_cache: self.event_cache,
+            type_index: self.type_index,
+        };
 
-        Ok((join, receiver_out))
+        let join = run_worker(
+            worker,
+            ranges,
+            source,
+            self.concurrency,
+            self.reader_options,
+            Some(self.fetch_options),
+            self.cache_folder,
+            self.metrics,
+        )?;
+        Ok((join, receiver))
     }
 }
 
+/// Processes the checkpoints fed by the fetcher, one at a time.
 #[async_trait]
-impl<F> Worker for EventExtractWorker<F>
+pub trait Worker: Send + Sync {
+    async fn process_checkpoint(&self, checkpoint: CheckpointData) -> Result<()>;
+}
+
+/// A worker that can process some checkpoints again without downloading them, from what it
+/// stored while processing them in an earlier run.
+#[async_trait]
+pub(crate) trait Replay {
+    /// Whether checkpoint `sequence_number` can be replayed, checked before downloading it.
+    fn can_replay(&self, _sequence_number: u64) -> bool {
+        false
+    }
+
+    /// Process checkpoint `sequence_number` again, after `can_replay` accepted it.
+    async fn replay(&self, _sequence_number: u64) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Extracts the events and the transactions of every checkpoint, see
+/// `EventExtractWorkerBuilder::build_with_transactions`.
+struct EventTransactionWorker<F, G> {
+    filter: F,
+    transaction_filter: G,
+    sender: Sender<(
+        CertifiedCheckpointSummary,
+        Vec<EventRecord>,
+        Vec<TransactionRecord>,
+    )>,
+    event_cache: Option<EventCache>,
+    type_index: Option<TypeIndex>,
+}
+
+// The transactions are not cached, so the checkpoints are always downloaded
+impl<F, G> Replay for EventTransactionWorker<F, G> {}
+
+#[async_trait]
+impl<F, G> Worker for EventTransactionWorker<F, G>
 where
     F: Fn(&EventRecord) -> bool + Send + Sync,
+    G: Fn(&TransactionRecord) -> bool + Send + Sync,
 {
     async fn process_checkpoint(&self, checkpoint: CheckpointData) -> Result<()> {
-        let timestamp = checkpoint.checkpoint_summary.timestamp_ms;
-
-        // Deconstruct checkpoint data
-        let CheckpointData {
-            checkpoint_summary,
-            checkpoint_contents: _, // We don't need this
-            transactions,
-        } = checkpoint;
-
-        // Extract all events from the checkpoint
-        let mut events = vec![];
-        transactions
-            .into_iter()
-            .enumerate()
-            .for_each(|(tx_seq, tx)| {
-                if tx.events.is_none() {
-                    return;
+        let mut transactions = extract_transactions(&checkpoint);
+        transactions.retain(|record| (self.transaction_filter)(record));
+        let (checkpoint_summary, events) = filter_events(
+            &self.filter,
+            self.event_cache.as_ref(),
+            self.type_index.as_ref(),
+            checkpoint,
+        )
+        .await;
+        self.sender
+            .send((checkpoint_summary, events, transactions))
+            .await?;
+        Ok(())
+    }
+}
+
+/// Run the worker built by `make_worker` over the `ranges` of checkpoints, in turn.
+/// Checkpoints are downloaded concurrently but handed to the worker in order, unless
+/// `FetchOptions::ordered` is unset, so the items it sends to the provided channel reach the
+/// returned receiver in checkpoint order.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run_ordered<W, T>(
+    make_worker: impl FnOnce(Sender<(CertifiedCheckpointSummary, Vec<T>)>) -> W,
+    ranges: Vec<Range<u64>>,
+    source: CheckpointSource,
+    concurrency: usize,
+    reader_options: Option<ReaderOptions>,
+    fetch_options: Option<FetchOptions>,
+    cache_folder: Option<PathBuf>,
+    channel: Option<ChannelConfig>,
+    metrics: Option<HarvestMetrics>,
+) -> HarvestResult<(
+    impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+    Receiver<(CertifiedCheckpointSummary, Vec<T>)>,
+)>
+where
+    W: Worker + Replay + 'static,
+    T: Send + 'static,
+{
+    let (sender, receiver) =
+        channel::channel::<(CertifiedCheckpointSummary, Vec<T>)>(channel.unwrap_or_default());
+    let join = run_worker(
+        make_worker(sender),
+        ranges,
+        source,
+        concurrency,
+        reader_options,
+        fetch_options,
+        cache_folder,
+        metrics,
+    )?;
+    Ok((join, receiver))
+}
+
+/// Feed the checkpoints of the `ranges`, in turn, to `worker`, in order unless
+/// `FetchOptions::ordered` is unset, replaying those it can instead of downloading them.
+/// Checkpoints that cannot be fetched or decoded are skipped, unless `FetchOptions::strict` is
+/// set.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn run_worker<W: Worker + Replay + 'static>(
+    worker: W,
+    ranges: Vec<Range<u64>>,
+    source: CheckpointSource,
+    concurrency: usize,
+    reader_options: Option<ReaderOptions>,
+    fetch_options: Option<FetchOptions>,
+    cache_folder: Option<PathBuf>,
+    metrics: Option<HarvestMetrics>,
+) -> HarvestResult<impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>> {
+    let reader_options = reader_options.unwrap_or_default();
+    let mut fetch_options = fetch_options.unwrap_or_default();
+    fetch_options.follow |= ranges.last().is_some_and(|range| range.end == u64::MAX);
+    let (strict, ordered) = (fetch_options.strict, fetch_options.ordered);
+    let sampling = fetch_options.sampling;
+    let sampled = move |sequence_number| sampling.is_none_or(|s| s.contains(sequence_number));
+    let decode_concurrency = fetch_options
+        .decode_concurrency
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |cpus| cpus.get()));
+    let fetcher = Arc::new(source.fetcher(
+        cache_folder,
+        &reader_options,
+        fetch_options,
+        metrics.clone(),
+    )?);
+
+    let join = async move {
+        // Download `concurrency` checkpoints at a time, and deserialize `decode_concurrency`
+        // of them on the blocking thread pool, keeping their order unless unordered.
+        // Checkpoints the worker replays come out as `None`
+        let worker = &worker;
+        let checkpoints = ranges.clone().into_iter().flatten();
+        let downloads = futures::stream::iter(checkpoints).map(|sequence_number| {
+            let fetcher = fetcher.clone();
+            async move {
+                if !sampled(sequence_number) || worker.can_replay(sequence_number) {
+                    return (sequence_number, Ok(None));
                 }
-                tx.events
-                    .unwrap()
-                    .data
-                    .into_iter()
-                    .enumerate()
-                    .for_each(|(event_seq, event)| {
-                        // Define the event record
-                        let record = (
-                            EventIndex::new(
-                                checkpoint_summary.sequence_number,
-                                tx_seq as u64,
-                                timestamp,
-                            ),
-                            EventID {
-                                tx_digest: *tx.transaction.digest(),
-                                event_seq: event_seq as u64,
-                            },
-                            event,
-                        );
-
-                        // Filter the events
-                        if (self.filter)(&record) {
-                            events.push(record);
-                        }
-                    });
-            });
+                let file = fetcher
+                    .fetch_file(sequence_number)
+                    .instrument(tracing::debug_span!("fetch", checkpoint = sequence_number))
+                    .await;
+                (sequence_number, file.map(Some))
+            }
+        });
+        let downloads = if ordered {
+            downloads.buffered(concurrency).left_stream()
+        } else {
+            downloads.buffer_unordered(concurrency).right_stream()
+        };
+        let checkpoints = downloads.map(|(sequence_number, downloaded)| {
+            let fetcher = fetcher.clone();
+            async move {
+                let file = match downloaded {
+                    Ok(Some(file)) => file,
+                    Ok(None) => return (sequence_number, Ok(None)),
+                    Err(e) => return (sequence_number, Err(e)),
+                };
+                let span = tracing::debug_span!("decode", checkpoint = sequence_number);
+                let checkpoint = tokio::task::spawn_blocking(move || {
+                    span.in_scope(|| fetcher.decode(sequence_number, file))
+                })
+                .await
+                .map_err(|e| HarvestError::Deserialization {
+                    sequence_number,
+                    source: e.into(),
+                })
+                .and_then(|decoded| decoded.map(Some));
+                (sequence_number, checkpoint)
+            }
+        });
+        let decode_concurrency = decode_concurrency.max(1);
+        let mut checkpoints = if ordered {
+            checkpoints.buffered(decode_concurrency).left_stream()
+        } else {
+            checkpoints
+                .buffer_unordered(decode_concurrency)
+                .right_stream()
+        };
+
+        let mut processed = 0;
+        while let Some((sequence_number, checkpoint)) = checkpoints.next().await {
+            processed += 1;
+            if !sampled(sequence_number) {
+                continue;
+            }
+            let span = tracing::debug_span!("deliver", checkpoint = sequence_number);
+            let result = match checkpoint {
+                Ok(Some(checkpoint)) => worker
+                    .process_checkpoint(checkpoint)
+                    .instrument(span)
+                    .await
+                    .map_err(HarvestError::from),
+                Ok(None) => worker
+                    .replay(sequence_number)
+                    .instrument(span)
+                    .await
+                    .map_err(HarvestError::from),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) =>
+                {
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = &metrics {
+                        metrics.checkpoints_processed.inc();
+                        metrics.latest_checkpoint.set(sequence_number as i64);
+                    }
+                }
+                // Unless strict, a checkpoint that cannot be retrieved leaves a gap in the
+                // items sent by the worker, detected with `progress::Gaps`
+                Err(
+                    e @ (HarvestError::FetchFailed { .. } | HarvestError::Deserialization { .. }),
+                ) if !strict => {
+                    tracing::warn!(
+                        "Skipping checkpoint {sequence_number}: {:#}",
+                        anyhow::Error::from(e)
+                    );
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = &metrics {
+                        metrics.checkpoints_skipped.inc();
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Every checkpoint of the ranges before this one was processed or skipped
+        let mut watermark = ranges.first().map_or(0, |range| range.start);
+        for range in &ranges {
+            let length = range.end - range.start;
+            watermark = range.start + processed.min(length);
+            if processed < length {
+                break;
+            }
+            processed -= length;
+        }
+        Ok::<_, HarvestError>(HashMap::from([("workflow".to_string(), watermark)]))
+    };
+
+    Ok(join)
+}
+
+#[async_trait]
+impl<F> Worker for EventExtractWorker<F>
+where
+    F: Fn(&EventRecord) -> bool + Send + Sync,
+{
+    async fn process_checkpoint(&self, checkpoint: CheckpointData) -> Result<()> {
+        let (checkpoint_summary, events) = filter_events(
+            &self.filter,
+            self.event_cache.as_ref(),
+            self.type_index.as_ref(),
+            checkpoint,
+        )
+        .await;
 
         // Send them to the aggregator
-        self.sender.send((checkpoint_summary, events))?;
+        self.sender.send((checkpoint_summary, events)).await?;
+
+        Ok(())
+    }
+}
+
+/// The events of `checkpoint` accepted by `filter`, after indexing all of them in
+/// `type_index` and caching the accepted ones in `event_cache`.
+#[cfg_attr(not(feature = "cache-index"), allow(unused_variables))]
+async fn filter_events<F>(
+    filter: &F,
+    event_cache: Option<&EventCache>,
+    type_index: Option<&TypeIndex>,
+    checkpoint: CheckpointData,
+) -> (CertifiedCheckpointSummary, Vec<EventRecord>)
+where
+    F: Fn(&EventRecord) -> bool,
+{
+    let (checkpoint_summary, mut events) = extract_events(checkpoint);
 
+    // The index covers all the events, like the cached checkpoints
+    #[cfg(feature = "cache-index")]
+    if let Some(index) = type_index {
+        if let Err(e) = index.insert(checkpoint_summary.sequence_number, &events) {
+            tracing::warn!(
+                "Failed to index the events of checkpoint {}: {e}",
+                checkpoint_summary.sequence_number
+            );
+        }
+    }
+
+    // Filter the events
+    events.retain(|record| filter(record));
+
+    // The cache only saves later runs some work
+    if let Some(cache) = event_cache {
+        if let Err(e) = cache.put(&checkpoint_summary, &events).await {
+            tracing::warn!(
+                "Failed to cache the events of checkpoint {}: {e}",
+                checkpoint_summary.sequence_number
+            );
+        }
+    }
+    (checkpoint_summary, events)
+}
+
+#[async_trait]
+impl<F> Replay for EventExtractWorker<F>
+where
+    F: Fn(&EventRecord) -> bool + Send + Sync,
+{
+    fn can_replay(&self, sequence_number: u64) -> bool {
+        self.event_cache
+            .as_ref()
+            .is_some_and(|cache| cache.contains(sequence_number))
+    }
+
+    async fn replay(&self, sequence_number: u64) -> Result<()> {
+        let Some(cache) = &self.event_cache else {
+            bail!("Checkpoint {sequence_number} cannot be replayed without an event cache");
+        };
+        // Like invalid checkpoints, events that cannot be read back are skipped unless strict
+        let cached = cache
+            .get(sequence_number)
+            .map_err(|e| HarvestError::Deserialization {
+                sequence_number,
+                source: e.into(),
+            })?;
+        let Some(checkpoint) = cached else {
+            return Err(HarvestError::Deserialization {
+                sequence_number,
+                source: "The events are no longer cached".into(),
+            }
+            .into());
+        };
+        self.sender.send(checkpoint).await?;
         Ok(())
     }
 }
+
+/// A boxed event filter, so that filters of different types can share a worker.
+pub type BoxedEventFilter = Box<dyn Fn(&EventRecord) -> bool + Send + Sync>;
+
+/// Extract events with several named filters, downloading each checkpoint only once.
+/// Every filter gets its own receiver, configured by `channel`; with the `Block` policy
+/// a slow consumer holds back all the others. Dropping a receiver only stops the events
+/// of its filter, and the worker stops once every receiver is dropped.
+pub struct MultiEventExtractWorker {
+    filters: Vec<(
+        BoxedEventFilter,
+        Sender<(CertifiedCheckpointSummary, Vec<EventRecord>)>,
+    )>,
+}
+
+impl MultiEventExtractWorker {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        initial: u64,
+        length: u64,
+        filters: Vec<(String, BoxedEventFilter)>,
+        remote_store_urls: Vec<Url>,
+        concurrency: usize,
+        reader_options: Option<ReaderOptions>,
+        fetch_options: Option<FetchOptions>,
+        cache_folder: Option<PathBuf>,
+        channel: Option<ChannelConfig>,
+        metrics: Option<HarvestMetrics>,
+    ) -> Result<(
+        impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+        HashMap<String, Receiver<(CertifiedCheckpointSummary, Vec<EventRecord>)>>,
+    )> {
+        let mut receivers = HashMap::new();
+        let mut senders = Vec::new();
+        for (name, filter) in filters {
+            let (sender, receiver) = channel::channel(channel.clone().unwrap_or_default());
+            if receivers.insert(name.clone(), receiver).is_some() {
+                bail!("Duplicate filter name '{name}'");
+            }
+            senders.push((filter, sender));
+        }
+
+        let join = run_worker(
+            Self { filters: senders },
+            vec![initial..initial.saturating_add(length)],
+            remote_store_urls.into(),
+            concurrency,
+            reader_options,
+            fetch_options,
+            cache_folder,
+            metrics,
+        )?;
+        Ok((join, receivers))
+    }
+}
+
+impl Replay for MultiEventExtractWorker {}
+
+#[async_trait]
+impl Worker for MultiEventExtractWorker {
+    async fn process_checkpoint(&self, checkpoint: CheckpointData) -> Result<()> {
+        let (checkpoint_summary, events) = extract_events(checkpoint);
+
+        // The filters whose receiver was dropped are skipped, and the worker only stops
+        // once every receiver is gone
+        let mut open = false;
+        for (filter, sender) in self
+            .filters
+            .iter()
+            .filter(|(_, sender)| !sender.is_closed())
+        {
+            let matching = events
+                .iter()
+                .filter(|record| filter(record))
+                .cloned()
+                .collect();
+            match sender.send((checkpoint_summary.clone(), matching)).await {
+                Ok(()) => open = true,
+                Err(HarvestError::ReceiverDropped) => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if !open {
+            return Err(HarvestError::ReceiverDropped.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Extract all events of a checkpoint, in the order they were emitted.
+pub fn extract_events(
+    checkpoint: CheckpointData,
+) -> (CertifiedCheckpointSummary, Vec<EventRecord>) {
+    let timestamp = checkpoint.checkpoint_summary.timestamp_ms;
+
+    // Deconstruct checkpoint data
+    let CheckpointData {
+        checkpoint_summary,
+        checkpoint_contents: _, // We don't need this
+        transactions,
+    } = checkpoint;
+
+    let mut events = vec![];
+    transactions
+        .into_iter()
+        .enumerate()
+        .for_each(|(tx_seq, tx)| {
+            if tx.events.is_none() {
+                return;
+            }
+            tx.events
+                .unwrap()
+                .data
+                .into_iter()
+                .enumerate()
+                .for_each(|(event_seq, event)| {
+                    // Define the event record
+                    events.push((
+                        EventIndex::new(
+                            checkpoint_summary.sequence_number,
+                            tx_seq as u64,
+                            timestamp,
+                        ),
+                        EventID {
+                            tx_digest: *tx.transaction.digest(),
+                            event_seq: event_seq as u64,
+                        },
+                        event,
+                    ));
+                });
+        });
+
+    (checkpoint_summary, events)
+}
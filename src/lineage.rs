@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sui_sdk::SuiClient;
+use sui_types::base_types::ObjectID;
+
+use crate::EventRecord;
+
+/// The original ID of every package, i.e. that of its first version, which all its upgrades
+/// share as the address of their modules. Looked up once per package on the full node.
+pub struct PackageLineage {
+    client: SuiClient,
+    originals: HashMap<ObjectID, ObjectID>,
+}
+
+impl PackageLineage {
+    pub fn new(client: SuiClient) -> Self {
+        Self {
+            client,
+            originals: HashMap::new(),
+        }
+    }
+
+    /// The original ID of a package, the package itself when it cannot be looked up.
+    pub async fn original(&mut self, package: ObjectID) -> ObjectID {
+        if let Some(original) = self.originals.get(&package) {
+            return *original;
+        }
+        let original = match self.lookup(package).await {
+            Ok(original) => original,
+            Err(e) => {
+                tracing::warn!("Cannot find the original package of {package}: {e}");
+                package
+            }
+        };
+        self.originals.insert(package, original);
+        original
+    }
+
+    async fn lookup(&self, package: ObjectID) -> Result<ObjectID> {
+        let modules = self
+            .client
+            .read_api()
+            .get_normalized_move_modules_by_package(package)
+            .await?;
+        match modules.values().next() {
+            Some(module) => Ok(ObjectID::from_hex_literal(&module.address)?),
+            None => Ok(package),
+        }
+    }
+
+    /// The event with its package, and the package of its type, replaced by their original
+    /// IDs, so that the events of all versions of a package are counted together.
+    pub async fn collapse(&mut self, (index, id, event): &EventRecord) -> EventRecord {
+        let mut event = event.clone();
+        event.package_id = self.original(event.package_id).await;
+        event.type_.address = self.original(event.type_.address.into()).await.into();
+        (index.clone(), *id, event)
+    }
+}
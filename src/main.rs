@@ -1,183 +1,3384 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    future::Future,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use harvestlib::EventExtractWorker;
-use move_core_types::language_storage::StructTag;
-use statrs::statistics::Statistics;
-use sui_sdk::SuiClientBuilder;
-use sui_types::TypeTag;
+use futures::FutureExt;
+#[cfg(any(feature = "kafka", feature = "nats", feature = "redis"))]
+use harvestlib::sinks::MessageFormat;
+use harvestlib::{
+    analyzer::{analyzer, Analyzer, AnalyzerReport},
+    balances::{BalanceChange, BalanceChangeExtractWorker},
+    cache::{self, CacheIndex, CachePolicy, CHECKPOINT_EXTENSION},
+    channel::{self, ChannelConfig, OverflowPolicy, Receiver},
+    decode::EventDecoder,
+    event_cache::{self, EventCache},
+    expression::FilterExpr,
+    extract_events,
+    fetcher::{
+        read_cached, CheckpointFetcher, CheckpointFile, FetchOptions, FullNodeTip, ReaderOptions,
+        RetryPolicy,
+    },
+    filter::{EventFilter, EventTypePattern, FRAMEWORK_PACKAGES},
+    labels::{named, Labeled, Labels},
+    lineage::PackageLineage,
+    metrics::{serve_metrics, HarvestMetrics},
+    presets::{preset, watch::WatchPreset},
+    progress::{Gaps, Progress},
+    report::{
+        parse_duration, type_to_short_string, BalanceHistogram, BalanceReport, BucketWidth,
+        CheckpointSummaryHistogram, CheckpointSummaryReport, CoinFlowHistogram, CoinFlowReport,
+        Depth, EventDiff, GasHistogram, GasReport, ReportOptions,
+    },
+    rolling::RollingWindow,
+    sampling::{SampleEstimator, Sampling},
+    sinks::{
+        alert::{AlertRule, AlertSink},
+        anomaly::{AnomalyOptions, AnomalySink},
+        clickhouse::ClickHouseSink,
+        csv::CsvSink,
+        notify::{NotificationSink, NotifyTemplate},
+        postgres::PostgresSink,
+        sqlite::SqliteSink,
+        webhook::WebhookSink,
+        websocket::WebSocketSink,
+        EventSink, JsonEvent,
+    },
+    summaries::CheckpointSummaryWorker,
+    transactions::{TransactionExtractWorker, TransactionRecord},
+    type_index::{self, TypeIndex},
+    EventExtractWorker, EventRecord, MAINNET_CHECKPOINTS_URL,
+};
+use http::{HeaderName, HeaderValue};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use object_store::Certificate;
+use prometheus::Registry;
+use regex::Regex;
+use serde::Serialize;
+use sui_sdk::{
+    rpc_types::{CheckpointId, SuiTransactionBlockResponseOptions},
+    SuiClient, SuiClientBuilder,
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    full_checkpoint_content::CheckpointData,
+    messages_checkpoint::CertifiedCheckpointSummary,
+    parse_sui_type_tag, TypeTag, SUI_SYSTEM_PACKAGE_ID,
+};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+use url::Url;
+
+/// Configuration file read when `--config` is not given, if it exists
+const CONFIG_FILE: &str = "harvest.toml";
+/// Default of `--cache-dir`, holding the cached checkpoints and the files below
+const CACHE_FOLDER: &str = "cache";
+const PROGRESS_FILE: &str = "progress.json";
+/// Progress of the bounded runs of `stats`, one file per statistics so that they do not
+/// resume each other
+const BALANCE_CHANGES_PROGRESS_FILE: &str = "progress-balance-changes.json";
+const COIN_FLOW_PROGRESS_FILE: &str = "progress-coin-flow.json";
+const GAS_PROGRESS_FILE: &str = "progress-gas.json";
+const CHECKPOINTS_PROGRESS_FILE: &str = "progress-checkpoints.json";
+/// Watermark of the follow command, kept apart from the progress of bounded runs
+const WATERMARK_FILE: &str = "follow.json";
+/// Last processed checkpoint of `serve`, kept apart from that of `follow`
+#[cfg(feature = "grpc")]
+const SERVE_WATERMARK_FILE: &str = "serve.json";
+/// How often the tip of the chain is polled to compute the checkpoint lag metric
+const LAG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Channel capacity of `--bulk` runs, unless set with `--channel-capacity`
+const BULK_CHANNEL_CAPACITY: usize = 4096;
+/// Minimum number of checkpoints between two saves of the progress of `--bulk` runs
+const BULK_PROGRESS_INTERVAL: u64 = 1000;
 
 /// A simple event monitor and library to consume events from the Sui blockchain.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    #[command(flatten)]
+    source: SourceArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Report on the events of a range of checkpoints, and export them to the sinks
+    Events {
+        #[command(flatten)]
+        range: RangeArgs,
+        #[command(flatten)]
+        events: EventArgs,
+    },
+    /// Report statistics other than events on a range of checkpoints
+    ///
+    /// The statistics cover every transaction of the range, without the event filters and
+    /// sinks of the event commands.
+    Stats {
+        /// What to compute from the checkpoints
+        #[arg(value_enum, required_unless_present = "coin_flow")]
+        kind: Option<Stats>,
+
+        /// Instead, report the mints, burns and transfers of this coin type, e.g. `0x2::sui::SUI`
+        #[arg(long, value_name = "COIN_TYPE", value_parser = parse_sui_type_tag, conflicts_with = "kind")]
+        coin_flow: Option<TypeTag>,
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// Compare the number of events of every type in two ranges of checkpoints, e.g. before
+    /// and after an upgrade
+    Diff {
+        /// First range of checkpoints, e.g. `1000000-1001000` (inclusive)
+        #[arg(long, value_parser = parse_range)]
+        range_a: RangeInclusive<u64>,
+
+        /// Second range of checkpoints, compared to the first
+        #[arg(long, value_parser = parse_range)]
+        range_b: RangeInclusive<u64>,
+
+        /// Only count events emitted by this package (can be repeated)
+        #[arg(long = "package")]
+        packages: Vec<ObjectID>,
+
+        /// Only count events of this type, e.g. `0xdee9::clob_v2::*` (can be repeated)
+        #[arg(long = "event-type")]
+        event_types: Vec<EventTypePattern>,
+
+        /// Only count events matching this expression
+        #[arg(long)]
+        filter: Option<FilterExpr>,
+
+        /// Only report the N types whose number of events changed the most
+        #[arg(long)]
+        top: Option<usize>,
+    },
+    /// Process events in real time, first catching up from the last processed checkpoint
+    Follow {
+        #[command(flatten)]
+        follow: FollowArgs,
+        #[command(flatten)]
+        events: EventArgs,
+    },
+    /// Follow the events and stream them to gRPC subscribers
+    #[cfg(feature = "grpc")]
+    Serve {
+        /// Address of the gRPC server
+        #[arg(long, default_value = "0.0.0.0:50051")]
+        listen: std::net::SocketAddr,
+
+        /// Disconnect the subscribers falling behind by this number of checkpoints
+        #[arg(long, default_value_t = 1000)]
+        subscriber_buffer: usize,
+
+        #[command(flatten)]
+        follow: FollowArgs,
+        #[command(flatten)]
+        events: EventArgs,
+    },
+    /// Print the decoded events of a transaction, from the checkpoint holding it
+    Tx {
+        /// Digest of the transaction
+        digest: TransactionDigest,
+
+        /// Checkpoint of the transaction, instead of asking the full node or scanning the cache
+        #[arg(long)]
+        checkpoint: Option<u64>,
+    },
+    /// List the events of some types in a range of checkpoints from the cache and the event
+    /// index alone, without any network access
+    Query {
+        /// Type of the events, e.g. `0xdee9::clob_v2::*`
+        #[arg(long = "type")]
+        type_: EventTypePattern,
+
+        /// First checkpoint of the range
+        #[arg(long)]
+        start: u64,
+
+        /// Last checkpoint of the range (inclusive)
+        #[arg(long)]
+        end: u64,
+    },
+    /// Manage the local checkpoint cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+}
+
+impl Command {
+    fn event_args(&self) -> Option<&EventArgs> {
+        match self {
+            Command::Events { events, .. } | Command::Follow { events, .. } => Some(events),
+            #[cfg(feature = "grpc")]
+            Command::Serve { events, .. } => Some(events),
+            _ => None,
+        }
+    }
+
+    /// The file of the cache folder saving the progress of a bounded run of the command, read
+    /// by `--resume`.
+    fn progress_file(&self) -> &'static str {
+        match self {
+            Command::Stats {
+                coin_flow: Some(_), ..
+            } => COIN_FLOW_PROGRESS_FILE,
+            Command::Stats {
+                kind: Some(Stats::BalanceChanges),
+                ..
+            } => BALANCE_CHANGES_PROGRESS_FILE,
+            Command::Stats {
+                kind: Some(Stats::Gas),
+                ..
+            } => GAS_PROGRESS_FILE,
+            Command::Stats {
+                kind: Some(Stats::Checkpoints),
+                ..
+            } => CHECKPOINTS_PROGRESS_FILE,
+            _ => PROGRESS_FILE,
+        }
+    }
+}
+
+/// Where a real time run starts, and when it stops.
+#[derive(clap::Args, Debug)]
+struct FollowArgs {
+    /// Stop after this number of checkpoints
+    #[arg(short, long)]
+    count: Option<u64>,
+
+    /// Start from this checkpoint instead of the watermark of the previous run
+    #[arg(long, conflicts_with = "from_latest")]
+    from: Option<u64>,
+
+    /// Start from the latest checkpoint, ignoring the watermark of the previous run
+    #[arg(long)]
+    from_latest: bool,
+
+    /// Delay between two polls for the next checkpoint once at the tip, e.g. `500ms` or `2s`
+    /// [default: 100ms]
+    #[arg(long, value_parser = parse_duration)]
+    poll_interval: Option<Duration>,
+
+    /// Stay this number of checkpoints behind the latest one
+    #[arg(long, default_value_t = 0)]
+    tip_lag: u64,
+
+    /// How the latest checkpoint is found: by polling the checkpoint store, or by polling the
+    /// full node, as its JSON-RPC API has no checkpoint subscription
+    #[arg(long, value_enum, default_value_t = TipSourceKind::Store)]
+    tip_source: TipSourceKind,
+
+    /// Print a summary of every this number of checkpoints: their rate of events and the types
+    /// whose counts changed the most since the previous window
+    #[arg(long, value_name = "CHECKPOINTS", value_parser = clap::value_parser!(u64).range(1..))]
+    summary_every: Option<u64>,
+
+    /// Write the summaries to this file, one JSON object per line, rather than to the standard
+    /// output
+    #[arg(long, requires = "summary_every")]
+    summary_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TipSourceKind {
+    /// Poll the checkpoint store for the next checkpoint
+    Store,
+    /// Poll the full node for the latest checkpoint, and only download those it reports
+    Node,
+}
+
+impl FollowArgs {
+    /// Apply the options following the tip of the chain to those of the fetcher.
+    fn apply(&self, options: &mut FetchOptions, client: &SuiClient) {
+        options.poll_interval = self.poll_interval;
+        options.tip_lag = self.tip_lag;
+        if self.tip_source == TipSourceKind::Node {
+            options.tip_source = Some(Arc::new(FullNodeTip(client.clone())));
+        }
+    }
+
+    /// The range to process given the latest checkpoint and the watermark file of the command.
+    fn resolve(&self, latest: u64, watermark_file: &Path) -> Result<(u64, u64)> {
+        let initial = match self.from {
+            Some(from) => from,
+            None if self.from_latest || !watermark_file.exists() => latest,
+            None => Progress::load(watermark_file)?.next_checkpoint,
+        };
+        if initial < latest {
+            tracing::info!(
+                "Catching up from checkpoint {} ({} behind the latest checkpoint) ...",
+                initial,
+                latest - initial
+            );
+        } else {
+            tracing::info!("Following the latest checkpoint ({}) ...", initial);
+        }
+        Ok((initial, self.count.unwrap_or(u64::MAX - initial)))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum CacheCommand {
+    /// Remove the cached checkpoints and events, or only the checkpoints beyond
+    /// `--cache-max-gb` and `--cache-ttl`
+    Clean,
+    /// Show the size of the cache, its hit rate and the ranges of checkpoints it covers
+    Stats,
+    /// Rebuild the index of the cache from the files of its folder
+    Reindex,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Stats {
+    /// Net coin balance changes by coin type and owner
+    BalanceChanges,
+    /// Gas used by package and entry function
+    Gas,
+    /// Transactions, gas and timestamp of every checkpoint, read from the summaries on the
+    /// full node without downloading the checkpoints
+    Checkpoints,
+}
+
+/// Where the checkpoints come from and how they are downloaded, shared by all commands.
+#[derive(clap::Args, Debug)]
+struct SourceArgs {
+    /// Number of checkpoints downloaded concurrently
+    #[arg(
+        long = "fetch-concurrency",
+        visible_alias = "concurrent",
+        global = true,
+        default_value_t = 5
+    )]
+    concurrent: u64,
+
+    /// Number of checkpoints deserialized in parallel [default: number of CPUs]
+    #[arg(long, global = true)]
+    decode_concurrency: Option<usize>,
+
+    /// Sui network, selecting the default full node and checkpoint URLs and the folder of
+    /// the cache
+    #[arg(long, global = true, value_enum, default_value_t = Network::Mainnet)]
+    network: Network,
+
+    /// URL of Sui full nodes [default: that of the network]
+    #[arg(long, global = true)]
+    full_node_url: Option<String>,
+
+    /// URL of Sui checkpoint nodes, or of a `s3://`, `gs://` or `az://` bucket mirroring them.
+    /// Can be repeated to spread the downloads over several stores, failing over between them
+    /// [default: that of the network]
+    #[arg(long, global = true, value_delimiter = ',')]
+    checkpoints_node_url: Vec<Url>,
+
+    /// Read the checkpoint files from this local directory instead of a checkpoint node
+    #[arg(long, global = true, conflicts_with = "checkpoints_node_url")]
+    checkpoints_dir: Option<PathBuf>,
+
+    /// Header sent with every request to the checkpoint stores and the full node, e.g.
+    /// `Authorization: Bearer <key>` for a private provider. Can be repeated
+    #[arg(long = "header", global = true, value_parser = parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// HTTP(S) proxy of the requests to the checkpoint stores, not to the full node, e.g.
+    /// `http://proxy:3128` [default: that of the `HTTPS_PROXY` environment variable]
+    #[arg(long, global = true)]
+    proxy: Option<String>,
+
+    /// PEM file of certificate authorities for the checkpoint stores to trust on top of the
+    /// built-in ones, e.g. that of a corporate proxy
+    #[arg(long, global = true, value_parser = parse_ca_bundle)]
+    ca_bundle: Option<CaBundle>,
+
+    /// Number of times a failed checkpoint download is retried before giving up
+    #[arg(long, global = true, default_value_t = 5)]
+    retries: u32,
+
+    /// Maximum number of checkpoint download requests per second
+    #[arg(long, global = true)]
+    max_rps: Option<f64>,
+
+    /// Check the contents of every checkpoint against its summary before processing it
+    #[arg(long, global = true)]
+    verify: bool,
+
+    /// Fail instead of skipping the checkpoints that cannot be fetched or decoded, and when
+    /// checkpoints are missing at the end of the run
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Folder of the cache, holding the checkpoints of mainnet, those of the other networks in
+    /// a folder of their own, and the progress of the runs
+    #[arg(long, global = true, default_value = CACHE_FOLDER)]
+    cache_dir: PathBuf,
+
+    /// Evict the least recently used cached checkpoints beyond this size, in GB
+    #[arg(long, global = true)]
+    cache_max_gb: Option<f64>,
+
+    /// Evict the cached checkpoints unused for this long, e.g. `12h` or `7d`
+    #[arg(long, global = true, value_parser = parse_duration)]
+    cache_ttl: Option<Duration>,
+
+    /// Zstd compression level of the cached checkpoints, 0 to store them uncompressed
+    #[arg(long, global = true, default_value_t = 3)]
+    cache_compression: i32,
+
+    /// Maximum number of processed checkpoints waiting for the consumer [default: 1024]
+    #[arg(long, global = true)]
+    channel_capacity: Option<usize>,
+
+    /// What to do when the consumer falls behind: `block`, `drop-oldest` or `drop-newest`. The
+    /// progress of the runs dropping checkpoints is not saved, so they cannot be resumed
+    #[arg(long, global = true, default_value = "block")]
+    overflow: OverflowPolicy,
+
+    /// Serve Prometheus metrics on this port
+    #[arg(long, global = true)]
+    metrics_port: Option<u16>,
+
+    /// Format of the final report, or `ndjson` to stream the events as JSON lines instead
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// TOML file of names of package and account addresses, printed instead of them in the
+    /// text reports and as their `label` in the JSON reports, e.g. `"0xdee9" = "DeepBook"`,
+    /// on top of the built-in names of popular mainnet packages
+    #[arg(long, global = true)]
+    labels: Option<PathBuf>,
+
+    /// Do not name the popular mainnet packages in the reports, only those of `--labels`
+    #[arg(long, global = true)]
+    no_labels: bool,
+
+    /// Format of the logs on stderr, filtered with `RUST_LOG`, e.g. `harvestlib=debug` for the
+    /// timings of the fetch, decode and deliver spans of every checkpoint
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Estimate the transactions, download size and time of the range of checkpoints, then exit
+    /// without processing it
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// TOML file of default values of the options, by long name, e.g. `fetch-concurrency = 10`
+    /// [default: harvest.toml, if it exists]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Identifier of the chain of the full node, set by `check_chain` once connected
+    #[arg(skip)]
+    chain: Option<String>,
+}
+
+impl SourceArgs {
+    /// URLs of the stores holding the checkpoint files.
+    fn checkpoints_urls(&self) -> Result<Vec<Url>> {
+        match &self.checkpoints_dir {
+            Some(dir) => {
+                let path = dir.canonicalize()?;
+                Url::from_directory_path(&path)
+                    .map(|url| vec![url])
+                    .map_err(|_| anyhow!("Invalid checkpoints directory {}", path.display()))
+            }
+            None if !self.checkpoints_node_url.is_empty() => Ok(self.checkpoints_node_url.clone()),
+            None => self
+                .network
+                .checkpoints_url()
+                .map(|url| vec![Url::parse(url).expect("The network URLs are valid")])
+                .ok_or_else(|| {
+                    anyhow!(
+                        "The {} network has no checkpoint store, give --checkpoints-dir or \
+                    --checkpoints-node-url",
+                        self.network.name()
+                    )
+                }),
+        }
+    }
+
+    fn full_node_url(&self) -> &str {
+        self.full_node_url
+            .as_deref()
+            .unwrap_or(self.network.full_node_url())
+    }
+
+    /// The folder of the cached checkpoints of the network, so that those of different
+    /// networks never mix. That of mainnet is the cache folder itself, where earlier versions
+    /// cached its checkpoints.
+    fn cache_folder(&self) -> PathBuf {
+        match self.network {
+            Network::Mainnet => self.cache_dir.clone(),
+            network => self.cache_dir.join(network.name()),
+        }
+    }
+
+    /// A file of the cache folder shared by the networks, like the progress of the runs.
+    fn cache_file(&self, name: &str) -> PathBuf {
+        self.cache_dir.join(name)
+    }
+
+    fn fetch_options(&self) -> FetchOptions {
+        FetchOptions {
+            retry_policy: RetryPolicy {
+                max_retries: self.retries,
+                ..RetryPolicy::default()
+            },
+            max_rps: self.max_rps,
+            headers: self.headers.iter().cloned().collect(),
+            proxy_url: self.proxy.clone(),
+            root_certificates: self
+                .ca_bundle
+                .as_ref()
+                .map_or_else(Vec::new, |bundle| bundle.0.clone()),
+            verify: self.verify,
+            cache_policy: self.cache_policy(),
+            cache_compression: self.cache_compression,
+            chain: self.chain.clone(),
+            strict: self.strict,
+            decode_concurrency: self.decode_concurrency,
+            ..FetchOptions::default()
+        }
+    }
+
+    /// The client of the full node, sending it every header of `--header`, e.g. the
+    /// `Authorization` header of a private provider.
+    async fn full_node_client(&self) -> Result<SuiClient> {
+        if self.proxy.is_some() || self.ca_bundle.is_some() {
+            tracing::warn!(
+                "The proxy and the certificate authorities are not used by the full node client"
+            );
+        }
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = value
+                    .to_str()
+                    .with_context(|| format!("The value of header {name} is not text"))?;
+                Ok((name.to_string(), value.to_string()))
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        Ok(SuiClientBuilder::default()
+            .custom_headers(headers)
+            .build(self.full_node_url())
+            .await?)
+    }
+
+    fn channel_config(&self) -> ChannelConfig {
+        ChannelConfig::bounded(
+            self.channel_capacity.unwrap_or(channel::DEFAULT_CAPACITY),
+            self.overflow,
+        )
+    }
+
+    /// Whether the progress of the run can be saved: checkpoints dropped by the channel
+    /// were never processed, so resuming after them would skip them.
+    fn saves_progress(&self) -> bool {
+        if self.overflow.is_lossy() {
+            tracing::warn!("The progress of the run is not saved, as checkpoints may be dropped");
+        }
+        !self.overflow.is_lossy()
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        CachePolicy {
+            max_bytes: self.cache_max_gb.map(|gb| (gb * 1e9) as u64),
+            ttl: self.cache_ttl,
+        }
+    }
+
+    /// The labels of `--labels`, on top of the names of the popular mainnet packages unless
+    /// disabled or on another network.
+    fn labels(&self) -> Result<Labels> {
+        let mut labels = match !self.no_labels && self.network == Network::Mainnet {
+            true => Labels::well_known(),
+            false => Labels::default(),
+        };
+        if let Some(path) = &self.labels {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Cannot read the labels file {}", path.display()))?;
+            let entries = toml::from_str(&text)
+                .with_context(|| format!("Invalid labels file {}", path.display()))?;
+            labels.extend(Labels::from_entries(entries)?);
+        }
+        Ok(labels)
+    }
+}
+
+/// The range of past checkpoints to process.
+#[derive(clap::Args, Debug)]
+struct RangeArgs {
     /// Number of checkpoints to process
     #[arg(short, long, default_value_t = 10)]
     count: u64,
 
-    /// Number of checkpoints to process
-    #[arg(long, default_value_t = 5)]
-    concurrent: u64,
+    /// First checkpoint to process
+    #[arg(long)]
+    start: Option<u64>,
+
+    /// Last checkpoint to process (inclusive)
+    #[arg(long)]
+    end: Option<u64>,
+
+    /// Continue the last interrupted run of the command from its last processed checkpoint,
+    /// reporting only on the remaining checkpoints
+    #[arg(long, conflicts_with_all = ["start", "end"])]
+    resume: bool,
+
+    /// Process this range of checkpoints instead, e.g. `1000000-1001000` (inclusive);
+    /// repeated, the ranges are processed in a single run and reported apart
+    #[arg(long = "range", value_parser = parse_range, conflicts_with_all = ["start", "end", "resume"])]
+    ranges: Vec<RangeInclusive<u64>>,
+}
+
+impl RangeArgs {
+    /// The first checkpoint and number of checkpoints to process.
+    fn resolve(&self, latest_checkpoint: u64, progress_file: &Path) -> Result<(u64, u64)> {
+        // An explicit range takes precedence over counting back from the tip
+        let (start, end) = match (self.start, self.end) {
+            _ if !self.ranges.is_empty() => {
+                let mut ranges = self.ranges.clone();
+                ranges.sort_by_key(|range| *range.start());
+                (*ranges[0].start(), *ranges[ranges.len() - 1].end())
+            }
+            _ if self.resume => {
+                let progress = Progress::load(progress_file)?;
+                if progress.is_complete() {
+                    bail!("The last run already completed");
+                }
+                // The state of the analyzers is not saved with the progress
+                tracing::warn!(
+                    "Resuming from checkpoint {}: the reports are partial, only covering checkpoints {}..={}",
+                    progress.next_checkpoint,
+                    progress.next_checkpoint,
+                    progress.end_checkpoint
+                );
+                (progress.next_checkpoint, progress.end_checkpoint)
+            }
+            (Some(start), Some(end)) => (start, end),
+            (Some(start), None) => (start, start.saturating_add(self.count.saturating_sub(1))),
+            (None, Some(end)) => (end.saturating_sub(self.count.saturating_sub(1)), end),
+            (None, None) => (
+                latest_checkpoint.saturating_sub(self.count.saturating_sub(1)),
+                latest_checkpoint,
+            ),
+        };
+        if start > end {
+            bail!("Start checkpoint {start} is after end checkpoint {end}");
+        }
+        if end > latest_checkpoint {
+            bail!("Checkpoint {end} is past the latest checkpoint ({latest_checkpoint})");
+        }
+
+        tracing::info!("Get events from checkpoints {} ... {}", start, end);
+        Ok((start, end - start + 1))
+    }
+
+    /// The ranges of `--range`, sorted, when several are processed in a single run.
+    fn ranges(&self) -> Result<Option<Vec<RangeInclusive<u64>>>> {
+        if self.ranges.len() < 2 {
+            return Ok(None);
+        }
+        let mut ranges = self.ranges.clone();
+        ranges.sort_by_key(|range| *range.start());
+        if let Some(pair) = ranges
+            .windows(2)
+            .find(|pair| pair[0].end() >= pair[1].start())
+        {
+            bail!(
+                "Ranges {}-{} and {}-{} overlap",
+                pair[0].start(),
+                pair[0].end(),
+                pair[1].start(),
+                pair[1].end()
+            );
+        }
+        Ok(Some(ranges))
+    }
+}
+
+/// Which events to keep, how to report on them and where to export them.
+#[derive(clap::Args, Debug)]
+struct EventArgs {
+    /// Only keep events emitted by this package (can be repeated)
+    #[arg(long = "package")]
+    packages: Vec<ObjectID>,
+
+    /// Only keep events of this type, e.g. `0xdee9::clob_v2::*` (can be repeated)
+    #[arg(long = "event-type")]
+    event_types: Vec<EventTypePattern>,
+
+    /// Only keep events whose type, as a canonical string with the full addresses and the type
+    /// parameters, matches this regular expression, e.g. `.*::liquidity_pool::.*Swap.*` (can
+    /// be repeated)
+    #[arg(long = "type-regex", value_name = "REGEX")]
+    type_regexes: Vec<Regex>,
+
+    /// Only keep events of a generic type having this type parameter, e.g. `0x2::sui::SUI`
+    /// (can be repeated, for events having all of them)
+    #[arg(long = "type-param", value_name = "TYPE", value_parser = parse_sui_type_tag)]
+    type_params: Vec<TypeTag>,
+
+    /// Drop the events emitted by this package or of a type it defines (can be repeated)
+    #[arg(long = "exclude", value_name = "PACKAGE")]
+    excluded_packages: Vec<ObjectID>,
+
+    /// Drop the events of the Move standard library, the Sui framework and system, DeepBook
+    /// and the bridge (0x1, 0x2, 0x3, 0xdee9 and 0xb)
+    #[arg(long, conflicts_with = "system_events")]
+    exclude_system: bool,
+
+    /// Only keep events matching this expression, e.g. `module == "coin" && type contains
+    /// "Deposit"`
+    #[arg(long)]
+    filter: Option<FilterExpr>,
+
+    /// Also cache the filtered events of every checkpoint, for later runs with the same
+    /// filters to skip downloading and deserializing it
+    #[arg(long)]
+    cache_events: bool,
+
+    /// Also index the locations of all the events of every downloaded checkpoint by type, in
+    /// `cache/events.sqlite`, or `cache/<network>/events.sqlite` off mainnet, for later
+    /// queries to only read the checkpoints holding the events of some types
+    #[arg(long)]
+    index_events: bool,
+
+    /// Bottom percentage to suppress
+    #[arg(short, long, default_value_t = 0.5)]
+    suppress: f64,
+
+    /// Only report the N most frequent packages, and the N most frequent types of each
+    #[arg(long, conflicts_with = "suppress")]
+    top: Option<usize>,
+
+    /// Also report the number of events by sender
+    #[arg(long)]
+    by_sender: bool,
+
+    /// Also report the number of calls of every Move function, by the transactions emitting
+    /// the kept events if they are filtered
+    #[arg(long)]
+    by_call: bool,
+
+    /// Also report the transactions that emitted the most events, the 10 first without `--top`
+    #[arg(long)]
+    by_transaction: bool,
+
+    /// Also report the number of events by package in time buckets, e.g. `1m` or `1h`
+    #[arg(long)]
+    time_buckets: Option<BucketWidth>,
+
+    /// Also report the number of events by epoch, and their rate compared to the previous epoch
+    #[arg(long)]
+    by_epoch: bool,
+
+    /// Sort the addresses, types and packages by the total size of the contents of their
+    /// events, and print the sizes
+    #[arg(long)]
+    by_bytes: bool,
+
+    /// List the types of every address under their module (`type`), only count the events of
+    /// its modules (`module`), or only those of the address (`package`) [default: the types of
+    /// every address, not grouped by module]
+    #[arg(long, value_enum)]
+    depth: Option<Depth>,
+
+    /// Count the events of all the versions of a package under its original ID, looked up on
+    /// the full node
+    #[arg(long)]
+    collapse_upgrades: bool,
+
+    /// Also write the reports of the analyzers, with sortable tables and charts, to this HTML file
+    #[arg(long)]
+    html: Option<PathBuf>,
+
+    /// Run this analyzer on the events, `histogram`, `deepbook` or `system` (can be repeated)
+    #[arg(
+        long = "analyzer",
+        value_parser = parse_analyzer,
+        default_value = "histogram"
+    )]
+    analyzers: Vec<String>,
+
+    /// Also run this built-in analysis of a protocol's events, `deepbook` or `system` (can be
+    /// repeated)
+    #[arg(long = "preset", value_parser = parse_preset)]
+    presets: Vec<String>,
+
+    /// Only keep the events of the Sui system package, and report the epoch changes, validator
+    /// set changes and staking requests, as `--package 0x3 --analyzer system`
+    #[arg(long, conflicts_with_all = ["analyzers", "presets"])]
+    system_events: bool,
+
+    /// Only keep the events sent by this address or holding it in their contents, and list
+    /// them in order
+    #[arg(long, conflicts_with_all = ["analyzers", "presets", "system_events"])]
+    watch_address: Option<SuiAddress>,
+
+    /// Show a live dashboard instead of printing the decoded events
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with = "decode")]
+    tui: bool,
+
+    /// Decode and print the contents of every extracted event, with the types of the objects
+    /// they refer to
+    #[arg(long)]
+    decode: bool,
+
+    /// For historical backfills: a large blocking channel letting the downloads run ahead of
+    /// the consumer, the progress saved every 1000 checkpoints or more rather than after each
+    /// one, and no progress bar or dashboard
+    #[arg(long, conflicts_with_all = ["decode", "overflow"])]
+    bulk: bool,
+
+    /// Only process a random fraction of the checkpoints, e.g. `0.1` for 10%, and estimate the
+    /// numbers of events of the whole range from them, with confidence intervals
+    #[arg(long, value_parser = parse_fraction)]
+    sample: Option<f64>,
+
+    /// Seed choosing the sampled checkpoints, for another run to process the same ones
+    /// [default: random]
+    #[arg(long, requires = "sample")]
+    sample_seed: Option<u64>,
+
+    /// Run this WASM module on every decoded event, to drop events or emit derived records
+    #[cfg(feature = "script")]
+    #[arg(long)]
+    script: Option<PathBuf>,
+
+    /// Write the derived records of the script to this file, one JSON value per line, rather
+    /// than to the standard output
+    #[cfg(feature = "script")]
+    #[arg(long, requires = "script")]
+    script_output: Option<PathBuf>,
+
+    /// Also write every extracted event to this CSV file
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// Also insert every extracted event into this SQLite database
+    #[arg(long)]
+    sqlite: Option<PathBuf>,
+
+    /// Also write checkpoints and events into the PostgreSQL database at this URL
+    #[arg(long)]
+    postgres_url: Option<String>,
+
+    /// Also write checkpoints, transactions and events into the ClickHouse server at this HTTP URL
+    #[arg(long)]
+    clickhouse_url: Option<String>,
+
+    /// Also POST every extracted event as JSON to this URL
+    #[arg(long)]
+    webhook_url: Option<String>,
+
+    /// Maximum number of events in each webhook request
+    #[arg(long, default_value_t = 100)]
+    webhook_batch: usize,
+
+    /// Also push every extracted event as JSON to the WebSocket clients connecting to this port
+    #[arg(long)]
+    ws_port: Option<u16>,
+
+    /// Also post a message for every extracted event to this Slack or Discord webhook
+    #[arg(long)]
+    notify_url: Option<String>,
+
+    /// Message of the events of a type, e.g. `0xdee9::clob_v2::OrderFilled=Fill by {sender}`
+    /// (can be repeated)
+    #[arg(long = "notify-template")]
+    notify_templates: Vec<NotifyTemplate>,
+
+    /// Maximum number of notification messages per second
+    #[arg(long, default_value_t = 1.0)]
+    notify_rate: f64,
+
+    /// Log an alert when the rate of the events of a type crosses a threshold, e.g.
+    /// `type=0xdee9::clob_v2::OrderFilled rate>100/min` (can be repeated)
+    #[arg(long = "alert")]
+    alerts: Vec<AlertRule>,
+
+    /// Also post the alerts to this Slack or Discord webhook, at most `--notify-rate` messages
+    /// per second
+    #[arg(long, requires = "alerts")]
+    alert_url: Option<String>,
+
+    /// Number of times a failed write to the ClickHouse, BigQuery, webhook or notification
+    /// sinks is retried, with exponential backoff
+    #[arg(long, default_value_t = 5)]
+    sink_retries: u32,
+
+    /// Log the spikes and droughts of the rate of every event type, also exported with
+    /// `--metrics-port`
+    #[arg(long)]
+    anomalies: bool,
+
+    /// Width of the intervals whose event counts are compared, e.g. `1m`
+    #[arg(long, default_value = "1m", value_parser = parse_duration, requires = "anomalies")]
+    anomaly_interval: Duration,
+
+    /// Number of past intervals the mean and standard deviation of a rate are computed over
+    #[arg(long, default_value_t = 30, requires = "anomalies")]
+    anomaly_window: usize,
+
+    /// Number of standard deviations from the mean flagged as a spike or a drought
+    #[arg(long, default_value_t = 3.0, requires = "anomalies")]
+    anomaly_threshold: f64,
+
+    /// Also append every extracted event to a stream of the Redis server at this URL
+    #[cfg(feature = "redis")]
+    #[arg(long, requires = "redis_stream")]
+    redis_url: Option<String>,
+
+    /// Redis stream to append events to
+    #[cfg(feature = "redis")]
+    #[arg(long)]
+    redis_stream: Option<String>,
+
+    /// Approximate maximum number of events kept in the Redis stream, the oldest being trimmed
+    #[cfg(feature = "redis")]
+    #[arg(long, default_value_t = 100_000)]
+    redis_max_len: usize,
+
+    /// Encoding of the events in the Redis stream (`json` or `bcs`)
+    #[cfg(feature = "redis")]
+    #[arg(long, default_value = "json")]
+    redis_format: MessageFormat,
+
+    /// Also publish every extracted event to the NATS JetStream server at this URL, on a
+    /// subject of its type
+    #[cfg(feature = "nats")]
+    #[arg(long)]
+    nats_url: Option<String>,
+
+    /// JetStream stream capturing the subjects of the events, created if needed
+    #[cfg(feature = "nats")]
+    #[arg(long, default_value = "SUI_EVENTS")]
+    nats_stream: String,
+
+    /// Prefix of the subjects of the events, followed by `.<package>.<module>.<name>`
+    #[cfg(feature = "nats")]
+    #[arg(long, default_value = "sui.events")]
+    nats_subject_prefix: String,
+
+    /// Encoding of the NATS messages (`json` or `bcs`)
+    #[cfg(feature = "nats")]
+    #[arg(long, default_value = "json")]
+    nats_format: MessageFormat,
+
+    /// Also publish every extracted event to these Kafka brokers
+    #[cfg(feature = "kafka")]
+    #[arg(long, requires = "kafka_topic")]
+    kafka_brokers: Option<String>,
+
+    /// Kafka topic to publish events to
+    #[cfg(feature = "kafka")]
+    #[arg(long)]
+    kafka_topic: Option<String>,
+
+    /// Encoding of the Kafka messages (`json` or `bcs`)
+    #[cfg(feature = "kafka")]
+    #[arg(long, default_value = "json")]
+    kafka_format: MessageFormat,
+
+    /// Also write every extracted event to Parquet files in this folder
+    #[cfg(feature = "parquet")]
+    #[arg(long)]
+    parquet: Option<PathBuf>,
+
+    /// Number of checkpoints covered by each Parquet file
+    #[cfg(feature = "parquet")]
+    #[arg(long, default_value_t = 1000)]
+    parquet_checkpoints: u64,
+
+    /// Also write every extracted event to this Arrow IPC file, for Polars or PyArrow to map
+    /// it in memory
+    #[cfg(feature = "arrow")]
+    #[arg(long)]
+    arrow: Option<PathBuf>,
+
+    /// Serve the extracted events over Arrow Flight on this address, during the run and after
+    /// it until interrupted
+    #[cfg(feature = "flight")]
+    #[arg(long)]
+    flight: Option<std::net::SocketAddr>,
+
+    /// Number of checkpoints covered by each batch of the Arrow file and of the Arrow Flight stream
+    #[cfg(feature = "arrow")]
+    #[arg(long, default_value_t = 1000)]
+    arrow_checkpoints: u64,
+
+    /// Number of the latest batches kept in memory for the Arrow Flight consumers, the older
+    /// ones being dropped
+    #[cfg(feature = "flight")]
+    #[arg(long, default_value_t = 100)]
+    flight_batches: usize,
+
+    /// Also stream every extracted event into this BigQuery table, `project.dataset.table`,
+    /// created if needed
+    #[cfg(feature = "bigquery")]
+    #[arg(long)]
+    bigquery_table: Option<String>,
+
+    /// Service account key file of the BigQuery table, instead of the application default
+    /// credentials
+    #[cfg(feature = "bigquery")]
+    #[arg(long, requires = "bigquery_table")]
+    bigquery_key: Option<String>,
+}
+
+impl EventArgs {
+    /// How the failed writes to the remote sinks are retried, unlike the downloads of the
+    /// checkpoints by `--retries`.
+    fn sink_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.sink_retries,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Whether the live dashboard is shown.
+    #[cfg(feature = "tui")]
+    fn tui(&self) -> bool {
+        self.tui
+    }
+
+    #[cfg(not(feature = "tui"))]
+    fn tui(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// One human readable line per record
+    Text,
+    /// One JSON object per record, with the fields of its spans
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Network {
+    Mainnet,
+    Testnet,
+    Devnet,
+    /// A local network, e.g. of `sui start`, reading its checkpoints from `--checkpoints-dir`
+    Localnet,
+}
+
+impl Network {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mainnet => "mainnet",
+            Self::Testnet => "testnet",
+            Self::Devnet => "devnet",
+            Self::Localnet => "localnet",
+        }
+    }
+
+    fn full_node_url(self) -> &'static str {
+        match self {
+            Self::Mainnet => "https://fullnode.mainnet.sui.io:443",
+            Self::Testnet => "https://fullnode.testnet.sui.io:443",
+            Self::Devnet => "https://fullnode.devnet.sui.io:443",
+            Self::Localnet => "http://127.0.0.1:9000",
+        }
+    }
+
+    /// The public checkpoint store of the network, none for a local network.
+    fn checkpoints_url(self) -> Option<&'static str> {
+        match self {
+            Self::Mainnet => Some(MAINNET_CHECKPOINTS_URL),
+            Self::Testnet => Some("https://checkpoints.testnet.sui.io"),
+            Self::Devnet => Some("https://checkpoints.devnet.sui.io"),
+            Self::Localnet => None,
+        }
+    }
+}
+
+/// Log to stderr, by default the information of the harvester and the warnings of its
+/// dependencies, with the timings of the spans when enabled.
+fn init_logging(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("warn,harvest=info,harvestlib=info"));
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(|| LogWriter)
+        .with_span_events(FmtSpan::CLOSE);
+    match format {
+        LogFormat::Text => subscriber.with_target(false).init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Colored, human readable text
+    Text,
+    /// Structured JSON on stdout
+    Json,
+    /// One JSON object per line on stdout, for every event as it is processed instead of the
+    /// final report of the events
+    Ndjson,
+}
+
+fn print_balance_report(report: &BalanceReport) {
+    for coin in &report.coins {
+        println!(
+            "\x1b[34m{:<5}\x1b[0m {} (in {}, out {})",
+            coin.changes,
+            coin.short_type.red(),
+            coin.inflow,
+            coin.outflow
+        );
+
+        for owner in &coin.owners {
+            println!(
+                "       \x1b[34m{:>20}\x1b[0m : {}",
+                owner.amount,
+                named(&owner.owner, &owner.label).green()
+            );
+        }
+    }
+}
+
+fn print_coin_flow_report(report: &CoinFlowReport) {
+    println!(
+        "{} transactions of {}: minted {}, burned {}, transferred {}",
+        report.transactions,
+        report.short_type.red(),
+        report.minted,
+        report.burned,
+        report.transferred
+    );
+    for (title, owners) in [
+        ("Top senders:", &report.senders),
+        ("Top recipients:", &report.recipients),
+    ] {
+        println!("\n{}", title);
+        for owner in owners {
+            println!(
+                "\x1b[34m{:>20}\x1b[0m : {}",
+                owner.amount,
+                named(&owner.owner, &owner.label).green()
+            );
+        }
+    }
+}
+
+fn print_gas_report(report: &GasReport) {
+    println!(
+        "{} transactions: computation {}, storage {}, rebate {} (without Move calls: computation {})",
+        report.transactions,
+        report.total.computation_cost,
+        report.total.storage_cost,
+        report.total.storage_rebate,
+        report.other.computation_cost
+    );
+    for package in &report.packages {
+        println!(
+            "\x1b[34m{:<14}\x1b[0m {} ({} transactions, storage {}, rebate {})",
+            package.gas.computation_cost,
+            named(&package.package, &package.label).red(),
+            package.transactions,
+            package.gas.storage_cost,
+            package.gas.storage_rebate
+        );
+
+        for function in &package.functions {
+            println!(
+                "       \x1b[34m{:>14}\x1b[0m : {} ({} calls)",
+                function.gas.computation_cost,
+                function.function.green(),
+                function.calls
+            );
+        }
+    }
+}
+
+fn print_checkpoint_summary_report(report: &CheckpointSummaryReport) {
+    for summary in &report.summaries {
+        println!(
+            "\x1b[34m{:<12}\x1b[0m epoch {}, timestamp {}: {} transactions, computation {}, storage {}, rebate {}",
+            summary.sequence_number,
+            summary.epoch,
+            summary.timestamp,
+            summary.transactions,
+            summary.gas.computation_cost,
+            summary.gas.storage_cost,
+            summary.gas.storage_rebate
+        );
+    }
+    println!(
+        "{} checkpoints in {} epochs: {} transactions, computation {}, storage {}, rebate {}",
+        report.checkpoints,
+        report.epochs,
+        report.transactions,
+        report.gas.computation_cost,
+        report.gas.storage_cost,
+        report.gas.storage_rebate
+    );
+    if let Some(tps) = report.transactions_per_second {
+        println!("{tps:.1} transactions per second");
+    }
+    if let Some(busiest) = report.busiest {
+        println!(
+            "Most transactions in checkpoint {}",
+            busiest.to_string().green()
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let Args {
+        command,
+        mut source,
+    } = parse_args()?;
+    init_logging(source.log_format);
+
+    if let Command::Cache { action } = &command {
+        return match action {
+            CacheCommand::Clean => clean_cache(&source),
+            CacheCommand::Stats => print_cache_stats(&source.cache_folder(), source.output),
+            CacheCommand::Reindex => {
+                let folder = source.cache_folder();
+                let checkpoints = CacheIndex::open(&folder)?.rebuild(&folder)?;
+                println!("Indexed {checkpoints} cached checkpoints");
+                Ok(())
+            }
+        };
+    }
+
+    if let Command::Query { type_, start, end } = &command {
+        return query_cache(&source, type_, *start, *end);
+    }
+
+    if command
+        .event_args()
+        .is_some_and(|events| events.decode && source.output == OutputFormat::Json)
+    {
+        bail!("Decoded events can only be printed with the text or NDJSON output");
+    }
+    if command.event_args().is_some_and(|events| {
+        events.bulk && (events.tui() || source.output == OutputFormat::Ndjson)
+    }) {
+        bail!("Bulk runs only report at the end, without dashboard or streamed events");
+    }
+    // The overflow policy has a default value, which clap conflicts would ignore
+    if source.strict && source.overflow.is_lossy() {
+        bail!("Strict runs cannot drop checkpoints, use the `block` overflow policy");
+    }
+    if source.max_rps.is_some_and(|rate| rate <= 0.0) {
+        bail!("The maximum request rate must be positive");
+    }
+    if command
+        .event_args()
+        .is_some_and(|events| events.notify_rate <= 0.0)
+    {
+        bail!("The notification rate must be positive");
+    }
+
+    let sui_mainnet = source.full_node_client().await?;
+    tracing::info!(
+        "Sui {} version: {}",
+        source.network.name(),
+        sui_mainnet.api_version()
+    );
+    source.chain = Some(check_chain(&source, &sui_mainnet).await?);
+
+    if let Command::Tx { digest, checkpoint } = &command {
+        return print_transaction_events(&source, sui_mainnet, *digest, *checkpoint).await;
+    }
+
+    // Get and print the latest checkpoint
+    let latest_checkpoint = sui_mainnet
+        .read_api()
+        .get_latest_checkpoint_sequence_number()
+        .await?;
+
+    let (initial, limit) = match &command {
+        Command::Follow { follow, .. } => {
+            follow.resolve(latest_checkpoint, &source.cache_file(WATERMARK_FILE))?
+        }
+        #[cfg(feature = "grpc")]
+        Command::Serve { follow, .. } => {
+            follow.resolve(latest_checkpoint, &source.cache_file(SERVE_WATERMARK_FILE))?
+        }
+        Command::Events { range, .. } | Command::Stats { range, .. } => range.resolve(
+            latest_checkpoint,
+            &source.cache_file(command.progress_file()),
+        )?,
+        Command::Diff {
+            range_a, range_b, ..
+        } => {
+            let (start, end) = (
+                *range_a.start().min(range_b.start()),
+                *range_a.end().max(range_b.end()),
+            );
+            if end > latest_checkpoint {
+                bail!("Checkpoint {end} is past the latest checkpoint ({latest_checkpoint})");
+            }
+            if range_a.start() <= range_b.end() && range_b.start() <= range_a.end() {
+                bail!("The compared ranges overlap");
+            }
+            (start, end - start + 1)
+        }
+        Command::Tx { .. } | Command::Query { .. } | Command::Cache { .. } => {
+            unreachable!("The tx, query and cache commands do not process ranges of checkpoints")
+        }
+    };
+
+    let ranges = match &command {
+        Command::Events { range, .. } => range.ranges()?,
+        Command::Diff {
+            range_a, range_b, ..
+        } => Some(vec![range_a.clone(), range_b.clone()]),
+        Command::Stats { range, .. } if range.ranges.len() > 1 => {
+            bail!("Several ranges can only be processed by the events command")
+        }
+        _ => None,
+    };
+
+    if source.dry_run {
+        if ranges.is_some() {
+            bail!("Several ranges cannot be estimated at once, estimate them one by one");
+        }
+        return dry_run(&source, &sui_mainnet, initial, limit, latest_checkpoint).await;
+    }
+
+    // The dashboard displays the metrics even when they are not served
+    let dashboard = command.event_args().is_some_and(EventArgs::tui);
+    let metrics = (source.metrics_port.is_some() || dashboard)
+        .then(|| start_metrics(source.metrics_port, sui_mainnet.clone()));
+
+    match &command {
+        Command::Events { events, .. } | Command::Follow { events, .. } => {
+            let (progress_file, follow) = match &command {
+                Command::Follow { follow, .. } => (WATERMARK_FILE, Some(follow)),
+                _ => (PROGRESS_FILE, None),
+            };
+            harvest_events(
+                &source,
+                events,
+                sui_mainnet,
+                initial,
+                limit,
+                ranges,
+                source.cache_file(progress_file),
+                follow,
+                vec![],
+                metrics,
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Serve {
+            listen,
+            subscriber_buffer,
+            follow,
+            events,
+        } => {
+            let relay = harvestlib::serve::EventRelay::new(*subscriber_buffer);
+            let sink = relay.sink();
+            let server = relay.bind(*listen)?;
+            tracing::info!("Serving events on {}", listen);
+            let run = harvest_events(
+                &source,
+                events,
+                sui_mainnet,
+                initial,
+                limit,
+                None,
+                source.cache_file(SERVE_WATERMARK_FILE),
+                Some(follow),
+                vec![Box::new(sink)],
+                metrics,
+            );
+            // The run is stopped if the server fails, as it would then serve nobody
+            tokio::select! {
+                result = run => result,
+                result = server => result.context("gRPC server failed"),
+            }
+        }
+        Command::Stats {
+            coin_flow: Some(coin_type),
+            ..
+        } => harvest_coin_flow(&source, coin_type.clone(), initial, limit, metrics).await,
+        Command::Stats {
+            kind: Some(Stats::BalanceChanges),
+            ..
+        } => harvest_balance_changes(&source, initial, limit, metrics).await,
+        Command::Stats {
+            kind: Some(Stats::Gas),
+            ..
+        } => harvest_gas(&source, initial, limit, metrics).await,
+        Command::Stats {
+            kind: Some(Stats::Checkpoints),
+            ..
+        } => harvest_checkpoint_summaries(&source, sui_mainnet, initial, limit, metrics).await,
+        Command::Stats { kind: None, .. } => unreachable!("Clap requires a kind or --coin-flow"),
+        Command::Diff {
+            range_a,
+            range_b,
+            packages,
+            event_types,
+            filter,
+            top,
+        } => {
+            let filter = EventFilter {
+                packages: packages.clone(),
+                event_types: event_types.clone(),
+                type_regexes: Vec::new(),
+                type_params: Vec::new(),
+                expression: filter.clone(),
+                address: None,
+                excluded_packages: Vec::new(),
+            };
+            let ranges = (range_a.clone(), range_b.clone());
+            harvest_diff(&source, ranges, filter, *top, metrics).await
+        }
+        Command::Tx { .. } | Command::Query { .. } | Command::Cache { .. } => {
+            unreachable!("The tx, query and cache commands do not process ranges of checkpoints")
+        }
+    }
+}
+
+/// Resolve when the process receives SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal(SignalKind::terminate())
+            .expect("Cannot install the SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Drive the executor until it completes or a shutdown signal is received. Dropping the
+/// executor cancels in-flight downloads and closes the channel, so the consumer still
+/// reports on the checkpoints processed so far. Returns whether the run completed.
+async fn run_until_shutdown<T, E>(executor: impl Future<Output = Result<T, E>>) -> Result<bool>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    tokio::select! {
+        result = executor => {
+            result?;
+            Ok(true)
+        }
+        _ = shutdown_signal() => {
+            tracing::info!("Shutting down, reporting on the checkpoints processed so far");
+            Ok(false)
+        }
+    }
+}
+
+/// Drive the executor while the dashboard is shown, until the user quits the dashboard.
+/// Quitting before the end of the run cancels the executor. Returns whether the run completed.
+#[cfg(feature = "tui")]
+async fn run_with_dashboard<T, E>(
+    executor: impl Future<Output = Result<T, E>>,
+    dashboard: harvestlib::dashboard::Dashboard,
+) -> Result<bool>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut quit = tokio::task::spawn_blocking(move || dashboard.run());
+    let completed = tokio::select! {
+        result = run_until_shutdown(executor) => {
+            let completed = result?;
+            quit.await??;
+            completed
+        }
+        result = &mut quit => {
+            result??;
+            false
+        }
+    };
+    Ok(completed)
+}
+
+/// Serve the metrics on the given port, if any, and keep the checkpoint lag up to date.
+fn start_metrics(port: Option<u16>, client: SuiClient) -> HarvestMetrics {
+    let registry = Registry::new();
+    let metrics = HarvestMetrics::new(&registry);
+
+    if let Some(port) = port {
+        tokio::spawn(async move {
+            if let Err(e) = serve_metrics(registry, ([0, 0, 0, 0], port).into()).await {
+                tracing::error!("Metrics server failed: {}", e);
+            }
+        });
+    }
+
+    let lag_metrics = metrics.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Ok(tip) = client
+                .read_api()
+                .get_latest_checkpoint_sequence_number()
+                .await
+            {
+                let latest = lag_metrics.latest_checkpoint.get();
+                lag_metrics.checkpoint_lag.set((tip as i64 - latest).max(0));
+            }
+            tokio::time::sleep(LAG_POLL_INTERVAL).await;
+        }
+    });
+
+    metrics
+}
+
+/// Parse the command line, completing it with the options of the configuration file it does
+/// not set. Keys are the long names of the options, and their values are strings, numbers,
+/// `true` for flags, or arrays for options that can be repeated. They become the defaults of
+/// the options, so that they satisfy the required ones and never conflict with the
+/// command line.
+fn parse_args() -> Result<Args> {
+    // The configuration may set required options, so the configuration file and the
+    // (sub)command being run are first found leniently
+    let lenient = Args::command().ignore_errors(true).try_get_matches().ok();
+    let path = match lenient
+        .as_ref()
+        .and_then(|matches| matches.get_one::<PathBuf>("config"))
+    {
+        Some(path) => path.clone(),
+        None if Path::new(CONFIG_FILE).exists() => PathBuf::from(CONFIG_FILE),
+        None => return Ok(Args::parse()),
+    };
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read the configuration file {}", path.display()))?;
+    let config: toml::Table = toml::from_str(&text)
+        .with_context(|| format!("Invalid configuration file {}", path.display()))?;
+
+    let mut subcommands = Vec::new();
+    let mut matches = lenient.as_ref();
+    while let Some((name, sub_matches)) = matches.and_then(|matches| matches.subcommand()) {
+        subcommands.push(name.to_string());
+        matches = Some(sub_matches);
+    }
+
+    let root = Args::command();
+    let mut command = Args::command();
+    for (key, value) in config {
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        let values = values
+            .into_iter()
+            .map(|value| match value {
+                toml::Value::Boolean(value) => Ok(value.to_string()),
+                toml::Value::String(value) => Ok(value),
+                toml::Value::Integer(value) => Ok(value.to_string()),
+                toml::Value::Float(value) => Ok(value.to_string()),
+                _ => bail!("Unsupported value of `{key}` in {}", path.display()),
+            })
+            // Clap only takes static defaults, which live as long as the run anyway
+            .map(|value| value.map(|value| &*value.leak()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut found = false;
+        command = set_default(command, &subcommands, &key, &values, &mut found);
+        // Options of the other commands are ignored
+        if !found && !has_option(&root, &key) {
+            bail!("Unknown option `{key}` in {}", path.display());
+        }
+    }
+    Ok(Args::from_arg_matches(&command.get_matches())?)
+}
+
+/// Make `values` the default of the option `--key` of the subcommand at `path` of `command`,
+/// or of the closest of its parents having it, setting `found` if one does.
+fn set_default(
+    command: clap::Command,
+    path: &[String],
+    key: &str,
+    values: &[&'static str],
+    found: &mut bool,
+) -> clap::Command {
+    let command = match path.split_first() {
+        Some((name, path)) => {
+            command.mut_subcommand(name, |sub| set_default(sub, path, key, values, found))
+        }
+        None => command,
+    };
+    if *found {
+        return command;
+    }
+    let Some(id) = command
+        .get_arguments()
+        .find(|arg| has_long(arg, key))
+        .map(|arg| arg.get_id().clone())
+    else {
+        return command;
+    };
+    *found = true;
+    command.mut_arg(id, |arg| {
+        arg.default_values(values.iter().copied()).required(false)
+    })
+}
+
+/// Whether `--key` is the long name of `arg`, or one of its aliases.
+fn has_long(arg: &clap::Arg, key: &str) -> bool {
+    arg.get_long_and_visible_aliases()
+        .is_some_and(|names| names.contains(&key))
+}
+
+/// Whether `command` or one of its subcommands has the option `--key`.
+fn has_option(command: &clap::Command, key: &str) -> bool {
+    command.get_arguments().any(|arg| has_long(arg, key))
+        || command.get_subcommands().any(|sub| has_option(sub, key))
+}
+
+/// Extract events, forward them to `sinks` and those of `args`, and print the reports of the
+/// analyzers, for every range of `ranges` if set.
+#[allow(clippy::too_many_arguments)]
+async fn harvest_events(
+    source: &SourceArgs,
+    args: &EventArgs,
+    client: SuiClient,
+    initial: u64,
+    limit: u64,
+    ranges: Option<Vec<RangeInclusive<u64>>>,
+    progress_file: PathBuf,
+    follow: Option<&FollowArgs>,
+    mut sinks: Vec<Box<dyn EventSink>>,
+    metrics: Option<HarvestMetrics>,
+) -> Result<()> {
+    // Only keep events from the requested packages and types, if any
+    let mut packages = args.packages.clone();
+    if args.system_events {
+        packages.push(SUI_SYSTEM_PACKAGE_ID);
+    }
+    let mut excluded_packages = args.excluded_packages.clone();
+    if args.exclude_system {
+        excluded_packages.extend(FRAMEWORK_PACKAGES);
+    }
+    let filter = EventFilter {
+        packages,
+        event_types: args.event_types.clone(),
+        type_regexes: args.type_regexes.clone(),
+        type_params: args.type_params.clone(),
+        expression: args.filter.clone(),
+        address: args.watch_address,
+        excluded_packages,
+    };
+
+    // Additional destinations for the raw events
+    if let Some(path) = &args.csv {
+        sinks.push(Box::new(CsvSink::new(path)?));
+    }
+    if let Some(path) = &args.sqlite {
+        sinks.push(Box::new(SqliteSink::new(path)?));
+    }
+    if let Some(url) = &args.postgres_url {
+        sinks.push(Box::new(PostgresSink::connect(url).await?));
+    }
+    if let Some(url) = &args.clickhouse_url {
+        sinks.push(Box::new(
+            ClickHouseSink::connect(url.clone(), args.sink_retry_policy()).await?,
+        ));
+    }
+    if let Some(url) = &args.webhook_url {
+        sinks.push(Box::new(WebhookSink::new(
+            url.clone(),
+            args.webhook_batch,
+            args.sink_retry_policy(),
+        )));
+    }
+    if let Some(port) = args.ws_port {
+        sinks.push(Box::new(
+            WebSocketSink::serve(([0, 0, 0, 0], port).into()).await?,
+        ));
+    }
+    if let Some(url) = &args.notify_url {
+        sinks.push(Box::new(NotificationSink::new(
+            url.clone(),
+            args.notify_templates.clone(),
+            args.notify_rate,
+            args.sink_retry_policy(),
+        )));
+    }
+    if !args.alerts.is_empty() {
+        let notifier = args.alert_url.as_ref().map(|url| {
+            NotificationSink::new(
+                url.clone(),
+                vec![],
+                args.notify_rate,
+                args.sink_retry_policy(),
+            )
+        });
+        sinks.push(Box::new(AlertSink::new(args.alerts.clone(), notifier)));
+    }
+    if args.anomalies {
+        let options = AnomalyOptions {
+            interval: args.anomaly_interval,
+            window: args.anomaly_window,
+            threshold: args.anomaly_threshold,
+        };
+        sinks.push(Box::new(AnomalySink::new(options, metrics.clone())));
+    }
+    #[cfg(feature = "redis")]
+    if let (Some(url), Some(stream)) = (&args.redis_url, &args.redis_stream) {
+        sinks.push(Box::new(
+            harvestlib::sinks::redis::RedisSink::connect(
+                url,
+                stream.clone(),
+                args.redis_max_len,
+                args.redis_format,
+            )
+            .await?,
+        ));
+    }
+    #[cfg(feature = "nats")]
+    if let Some(url) = &args.nats_url {
+        sinks.push(Box::new(
+            harvestlib::sinks::nats::NatsSink::connect(
+                url,
+                args.nats_stream.clone(),
+                args.nats_subject_prefix.clone(),
+                args.nats_format,
+            )
+            .await?,
+        ));
+    }
+    #[cfg(feature = "kafka")]
+    if let (Some(brokers), Some(topic)) = (&args.kafka_brokers, &args.kafka_topic) {
+        sinks.push(Box::new(harvestlib::sinks::kafka::KafkaSink::new(
+            brokers,
+            topic.clone(),
+            args.kafka_format,
+        )?));
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(folder) = &args.parquet {
+        sinks.push(Box::new(harvestlib::sinks::parquet::ParquetSink::new(
+            folder.clone(),
+            args.parquet_checkpoints,
+        )?));
+    }
+    #[cfg(feature = "arrow")]
+    if let Some(path) = &args.arrow {
+        sinks.push(Box::new(harvestlib::sinks::arrow::ArrowSink::new(
+            path,
+            args.arrow_checkpoints,
+        )?));
+    }
+    #[cfg(feature = "flight")]
+    if let Some(address) = args.flight {
+        let relay = harvestlib::flight::FlightRelay::new(args.flight_batches);
+        sinks.push(Box::new(relay.sink(args.arrow_checkpoints)));
+        let server = relay.bind(address)?;
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                tracing::error!("Arrow Flight server failed: {}", e);
+            }
+        });
+        tracing::info!("Serving the events over Arrow Flight on {}", address);
+    }
+    #[cfg(feature = "bigquery")]
+    if let Some(table) = &args.bigquery_table {
+        sinks.push(Box::new(
+            harvestlib::sinks::bigquery::BigQuerySink::connect(
+                table,
+                args.bigquery_key.as_deref(),
+                args.sink_retry_policy(),
+            )
+            .await?,
+        ));
+    }
+
+    let sampling = args
+        .sample
+        .map(|fraction| {
+            let seed = args.sample_seed.unwrap_or_else(rand::random);
+            tracing::info!(
+                "Sampling {}% of the checkpoints, with seed {seed}",
+                fraction * 100.0
+            );
+            Sampling::new(fraction, seed)
+        })
+        .transpose()?;
+    if sampling.is_some() && follow.is_some() {
+        bail!("Only the checkpoints of a bounded range can be sampled");
+    }
+
+    // Run the selected analyzers, the histogram by default
+    let report_options = ReportOptions {
+        suppress: args.suppress,
+        top: args.top,
+        by_sender: args.by_sender,
+        by_call: args.by_call,
+        by_transaction: args.by_transaction,
+        time_buckets: args.time_buckets,
+        by_epoch: args.by_epoch,
+        by_bytes: args.by_bytes,
+        depth: args.depth,
+        labels: source.labels()?,
+    };
+    let analyzer_names = match args.system_events {
+        true => vec!["system".to_string()],
+        false => {
+            let mut names = args.analyzers.clone();
+            for preset in &args.presets {
+                if !names.contains(preset) {
+                    names.push(preset.clone());
+                }
+            }
+            names
+        }
+    };
+    // Every range is reported on its own, with the estimates of its events when sampled
+    let populations = match &ranges {
+        Some(ranges) => ranges
+            .iter()
+            .map(|range| range.end() - range.start() + 1)
+            .collect(),
+        None => vec![limit],
+    };
+    let mut analyzers = populations
+        .iter()
+        .map(|&population| {
+            let mut analyzers = match args.watch_address {
+                Some(address) => vec![Box::new(WatchPreset::new(address)) as Box<dyn Analyzer>],
+                None => analyzer_names
+                    .iter()
+                    .map(|name| analyzer(name, &report_options))
+                    .collect::<Result<Vec<_>>>()?,
+            };
+            if sampling.is_some() {
+                analyzers.push(Box::new(SampleEstimator::new(population)));
+            }
+            Ok(analyzers)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // The filter describes its own event cache
+    let event_cache = args
+        .cache_events
+        .then(|| EventCache::open(&source.cache_folder(), &filter.canonical()))
+        .transpose()?;
+
+    let mut fetch_options = source.fetch_options();
+    if let Some(follow) = follow {
+        follow.apply(&mut fetch_options, &client);
+    }
+    fetch_options.sampling = sampling;
+
+    // The analyzers of the transactions only see those emitting the filtered events
+    let filters_events = !filter.is_empty();
+
+    // Get a new Custom Worker, also extracting the transactions if an analyzer needs them
+    let mut builder = EventExtractWorker::builder(move |record| filter.matches(record))
+        .start(initial)
+        .limit(limit)
+        .remote_store_urls(source.checkpoints_urls()?)
+        .concurrency(source.concurrent as usize)
+        .fetch_options(fetch_options)
+        .cache_folder(source.cache_folder())
+        .channel(match args.bulk {
+            // Let the downloads run ahead of the consumer, without dropping checkpoints
+            true => ChannelConfig::bounded(
+                source.channel_capacity.unwrap_or(BULK_CHANNEL_CAPACITY),
+                OverflowPolicy::Block,
+            ),
+            false => source.channel_config(),
+        });
+    if let Some(metrics) = metrics.clone() {
+        builder = builder.metrics(metrics);
+    }
+    if let Some(cache) = event_cache {
+        builder = builder.event_cache(cache);
+    }
+    if args.index_events {
+        builder = builder.type_index(TypeIndex::open(&source.cache_folder())?);
+    }
+    if let Some(ranges) = ranges.clone() {
+        builder = builder.ranges(ranges);
+    }
+    let with_transactions = analyzers
+        .iter()
+        .flatten()
+        .any(|analyzer| analyzer.wants_transactions());
+    let (executor, mut receiver) = if with_transactions {
+        let (executor, receiver) = builder.build_with_transactions(|_| true).await?;
+        (
+            executor.boxed_local(),
+            Checkpoints::WithTransactions(receiver),
+        )
+    } else {
+        let (executor, receiver) = builder.build().await?;
+        (executor.boxed_local(), Checkpoints::Events(receiver))
+    };
+
+    // spawn a task to process the received data. The runs over several ranges cannot be
+    // resumed
+    let (output, strict) = (source.output, source.strict);
+    let saves_progress = ranges.is_none() && source.saves_progress();
+    let last = last_checkpoint(initial, limit)?;
+    let end = ranges
+        .as_ref()
+        .and_then(|ranges| ranges.last())
+        .map_or(last, |range| *range.end());
+    let html_file = args.html.clone();
+    // The script sees the decoded events
+    #[cfg(feature = "script")]
+    let mut hook = args
+        .script
+        .as_deref()
+        .map(|path| Script::load(path, args.script_output.as_deref(), output))
+        .transpose()?;
+    #[cfg(feature = "script")]
+    let decode = args.decode || hook.is_some();
+    #[cfg(not(feature = "script"))]
+    let decode = args.decode;
+    let print_decoded = args.decode;
+    let dashboard_shown = args.tui();
+    let bulk = args.bulk;
+    let mut lineage = args
+        .collapse_upgrades
+        .then(|| PackageLineage::new(client.clone()));
+    let mut decoder = decode.then(|| EventDecoder::new(client).with_object_types());
+    let mut rolling = follow
+        .filter(|follow| follow.summary_every.is_some())
+        .map(|follow| RollingSummaries::new(follow, output))
+        .transpose()?;
+    #[cfg(feature = "tui")]
+    let dashboard = metrics
+        .clone()
+        .filter(|_| args.tui)
+        .map(harvestlib::dashboard::Dashboard::new);
+    #[cfg(feature = "tui")]
+    let dashboard_handle = dashboard.as_ref().map(|dashboard| dashboard.handle());
+    let join = tokio::spawn(async move {
+        let mut progress = Progress {
+            next_checkpoint: initial,
+            end_checkpoint: last,
+        };
+        // The first checkpoint not covered by the last save of the progress
+        let mut saved = initial;
+        let mut gaps = match &ranges {
+            Some(ranges) => Gaps::over_ranges(ranges),
+            None => Gaps::new(initial),
+        };
+        if let Some(sampling) = sampling {
+            gaps = gaps.sampled(sampling);
+        }
+        // The decoded events and the dashboard take over the terminal
+        let mut bar = (!print_decoded && !dashboard_shown && !bulk)
+            .then(|| match &ranges {
+                Some(ranges) => Some(RunProgress::over_ranges(ranges.clone(), "events")),
+                None => RunProgress::new(initial, limit, "events"),
+            })
+            .flatten();
+
+        while let Some((summary, data, mut records)) = receiver.recv().await {
+            gaps.observe(summary.sequence_number);
+            if let Some(bar) = &mut bar {
+                bar.observe(summary.sequence_number, data.len());
+            }
+            if let Some(metrics) = &metrics {
+                metrics.events_processed.inc_by(data.len() as u64);
+            }
+            if let Some(records) = records.as_mut().filter(|_| filters_events) {
+                records
+                    .retain(|record| data.iter().any(|(_, id, _)| id.tx_digest == record.digest));
+            }
+
+            let mut decoded = Vec::new();
+            if let Some(decoder) = decoder.as_mut() {
+                for record in &data {
+                    match decoder.decode(record).await {
+                        Ok(event) => decoded.push(Some(event)),
+                        Err(e) => {
+                            tracing::warn!("Cannot decode {}: {}", record.2.type_, e);
+                            decoded.push(None);
+                        }
+                    }
+                }
+            }
+
+            #[cfg(feature = "script")]
+            let (data, decoded) = match hook.as_mut() {
+                Some(hook) => run_script(hook, data, decoded)?,
+                None => (data, decoded),
+            };
+
+            #[cfg(feature = "tui")]
+            if let Some(handle) = &dashboard_handle {
+                handle.add(&data);
+            }
+
+            for sink in sinks.iter_mut() {
+                sink.process(&summary, &data).await?;
+            }
+
+            // Stream the events, decoded if possible
+            if output == OutputFormat::Ndjson {
+                for (i, record) in data.iter().enumerate() {
+                    let line = match decoded.get(i).and_then(Option::as_ref) {
+                        Some(decoded) => serde_json::to_string(decoded)?,
+                        None => serde_json::to_string(&JsonEvent::from(record))?,
+                    };
+                    println!("{line}");
+                }
+            } else if print_decoded {
+                for (record, decoded) in data.iter().zip(&decoded) {
+                    let Some(decoded) = decoded else { continue };
+                    println!(
+                        "{} {} {}",
+                        decoded.event.checkpoint.to_string().blue(),
+                        type_to_short_string(&record.2.type_).green(),
+                        decoded.fields
+                    );
+                    for (id, type_) in &decoded.objects {
+                        println!("    {} : {}", id, type_.yellow());
+                    }
+                }
+            }
+
+            // Feed the analyzers, with the original IDs of the packages if collapsing upgrades
+            let data = match lineage.as_mut() {
+                Some(lineage) => {
+                    let mut collapsed = Vec::with_capacity(data.len());
+                    for record in &data {
+                        collapsed.push(lineage.collapse(record).await);
+                    }
+                    collapsed
+                }
+                None => data,
+            };
+            let range = ranges.as_ref().map_or(0, |ranges| {
+                ranges
+                    .iter()
+                    .position(|range| range.contains(&summary.sequence_number))
+                    .unwrap_or(0)
+            });
+            for analyzer in analyzers[range].iter_mut() {
+                analyzer.on_checkpoint(&summary);
+                data.iter().for_each(|record| analyzer.on_event(record));
+                if let Some(records) = records.as_ref().filter(|_| analyzer.wants_transactions()) {
+                    records
+                        .iter()
+                        .for_each(|record| analyzer.on_transaction(record));
+                }
+            }
+
+            if let Some(rolling) = rolling.as_mut().filter(|_| !dashboard_shown) {
+                rolling.observe(&summary, &data)?;
+            }
+
+            // Record the checkpoint as fully processed, only every so often in bulk runs,
+            // counting the skipped checkpoints too
+            progress.next_checkpoint = summary.sequence_number + 1;
+            if saves_progress
+                && (!bulk || progress.next_checkpoint >= saved + BULK_PROGRESS_INTERVAL)
+            {
+                progress.save(&progress_file)?;
+                saved = progress.next_checkpoint;
+            }
+        }
+        if bulk && saves_progress {
+            progress.save(&progress_file)?;
+        }
+
+        drop(bar);
+        for sink in sinks.iter_mut() {
+            sink.finish().await?;
+        }
+        #[cfg(feature = "script")]
+        if let Some(script) = hook.as_mut() {
+            script.finish()?;
+        }
+
+        // The reports of every range, named after it when there are several
+        let names = match &ranges {
+            Some(ranges) => ranges
+                .iter()
+                .map(|range| Some(format!("{}..={}", range.start(), range.end())))
+                .collect(),
+            None => vec![None],
+        };
+        let reports: Vec<(Option<String>, Vec<AnalyzerReport>)> = names
+            .into_iter()
+            .zip(analyzers)
+            .map(|(name, analyzers)| {
+                let reports = analyzers.into_iter().map(|analyzer| analyzer.finish());
+                (name, reports.collect())
+            })
+            .collect();
+        if let Some(path) = &html_file {
+            let mut sections = Vec::new();
+            for (name, reports) in &reports {
+                if let Some(name) = name {
+                    sections.push(format!("<h2>Checkpoints {name}</h2>"));
+                }
+                sections.extend(reports.iter().filter_map(|r| r.html.clone()));
+            }
+            let title = format!(
+                "Events of checkpoints {}..={}",
+                initial, progress.end_checkpoint
+            );
+            std::fs::write(path, harvestlib::html::page(&title, &sections))?;
+        }
+        match output {
+            OutputFormat::Text => {
+                for (i, (name, reports)) in reports.iter().enumerate() {
+                    if let Some(name) = name {
+                        let separator = if i > 0 { "\n" } else { "" };
+                        println!("{separator}{}", format!("Checkpoints {name}:").bold());
+                    }
+                    if let Some(sampling) = sampling {
+                        println!(
+                            "{}",
+                            format!(
+                                "Counts of the {}% sampled checkpoints only, see the `sample` report for estimates of the whole range",
+                                sampling.fraction * 100.0
+                            )
+                            .italic()
+                        );
+                    }
+                    for (j, report) in reports.iter().enumerate() {
+                        let separator = if j > 0 { "\n" } else { "" };
+                        print!("{separator}{}", report.text);
+                    }
+                }
+            }
+            // The reports of several ranges are keyed by range
+            OutputFormat::Json => {
+                let json = match ranges {
+                    Some(_) => serde_json::Value::Object(
+                        reports
+                            .into_iter()
+                            .map(|(name, reports)| {
+                                (name.unwrap_or_default(), reports_json(reports, sampling))
+                            })
+                            .collect(),
+                    ),
+                    None => reports
+                        .into_iter()
+                        .next()
+                        .map_or(serde_json::Value::Null, |(_, reports)| {
+                            reports_json(reports, sampling)
+                        }),
+                };
+                println!("{}", serde_json::to_string_pretty(&json)?)
+            }
+            // The events were already streamed
+            OutputFormat::Ndjson => (),
+        }
+        anyhow::Ok(gaps)
+    });
+
+    #[cfg(feature = "tui")]
+    if let Some(dashboard) = dashboard {
+        let completed = run_with_dashboard(executor, dashboard).await?;
+        return finish_gaps(join.await??, completed, end, strict);
+    }
+
+    let completed = run_until_shutdown(executor).await?;
+    finish_gaps(join.await??, completed, end, strict)?;
+
+    // Consumers can still pull the events once the run is over
+    #[cfg(feature = "flight")]
+    if let Some(address) = args.flight {
+        tracing::info!(
+            "Serving the events over Arrow Flight on {} until interrupted",
+            address
+        );
+        shutdown_signal().await;
+    }
+    Ok(())
+}
 
-    /// Whether to follow in real time
-    #[arg(short, long, default_value_t = false)]
-    follow: bool,
+/// The checkpoints of a run, with their transactions if an analyzer needs them.
+enum Checkpoints {
+    Events(Receiver<(CertifiedCheckpointSummary, Vec<EventRecord>)>),
+    WithTransactions(
+        Receiver<(
+            CertifiedCheckpointSummary,
+            Vec<EventRecord>,
+            Vec<TransactionRecord>,
+        )>,
+    ),
+}
 
-    /// Bottom percentage to suppress
-    #[arg(short, long, default_value_t = 0.5)]
-    suppress: f64,
+impl Checkpoints {
+    async fn recv(
+        &mut self,
+    ) -> Option<(
+        CertifiedCheckpointSummary,
+        Vec<EventRecord>,
+        Option<Vec<TransactionRecord>>,
+    )> {
+        match self {
+            Self::Events(receiver) => receiver
+                .recv()
+                .await
+                .map(|(summary, events)| (summary, events, None)),
+            Self::WithTransactions(receiver) => receiver
+                .recv()
+                .await
+                .map(|(summary, events, transactions)| (summary, events, Some(transactions))),
+        }
+    }
+}
 
-    /// URL of Sui full nodes
-    #[arg(long, default_value = "https://fullnode.mainnet.sui.io:443")]
-    full_node_url: String,
+/// The JSON of the reports of the analyzers, keyed by analyzer name, with the `sampled`
+/// fraction and seed their counts are restricted to, if any.
+fn reports_json(reports: Vec<AnalyzerReport>, sampling: Option<Sampling>) -> serde_json::Value {
+    let mut json: serde_json::Map<_, _> = reports
+        .into_iter()
+        .map(|report| (report.name.to_string(), report.json))
+        .collect();
+    if let Some(sampling) = sampling {
+        json.insert("sampled".to_string(), serde_json::json!(sampling));
+    }
+    serde_json::Value::Object(json)
+}
 
-    /// URL of Sui checkpoint nodes
-    #[arg(long, default_value = "https://checkpoints.mainnet.sui.io")]
-    checkpoints_node_url: String,
+/// The rolling summaries of a follower, with their destination.
+struct RollingSummaries {
+    window: RollingWindow,
+    /// The file of the summaries, printed with the output of the run if not set
+    file: Option<std::io::BufWriter<std::fs::File>>,
+    output: OutputFormat,
 }
 
-fn tag_to_short_string(tag_: &TypeTag) -> String {
-    match tag_ {
-        TypeTag::Struct(struct_tag) => type_to_short_string(struct_tag),
-        TypeTag::Vector(type_tag) => format!("Vector<{}>", tag_to_short_string(type_tag)),
-        _ => tag_.to_canonical_string(false),
+impl RollingSummaries {
+    fn new(follow: &FollowArgs, output: OutputFormat) -> Result<Self> {
+        let file = follow
+            .summary_file
+            .as_deref()
+            .map(|path| {
+                std::fs::File::create(path)
+                    .with_context(|| format!("Cannot create {}", path.display()))
+                    .map(std::io::BufWriter::new)
+            })
+            .transpose()?;
+        if file.is_none() && output == OutputFormat::Json {
+            bail!("The summaries need `--summary-file` with the JSON output");
+        }
+        Ok(Self {
+            window: RollingWindow::new(follow.summary_every.unwrap_or(1)),
+            file,
+            output,
+        })
+    }
+
+    /// Add the events of a checkpoint, writing the summary of the window it completes, if
+    /// any, tagged to tell it from the events in the NDJSON output.
+    fn observe(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        use std::io::Write;
+        let Some(window) = self.window.observe(summary, events) else {
+            return Ok(());
+        };
+        match &mut self.file {
+            Some(file) => {
+                writeln!(file, "{}", serde_json::to_string(&window)?)?;
+                file.flush()?;
+            }
+            None if self.output == OutputFormat::Ndjson => {
+                println!("{}", serde_json::json!({ "summary": window }))
+            }
+            None => progress_bars().suspend(|| print!("{window}")),
+        }
+        Ok(())
     }
 }
 
-fn type_to_short_string(type_: &StructTag) -> String {
-    let base = format!("{}::{}", type_.module, type_.name,);
+/// The script of a run, with the destination of its derived records.
+#[cfg(feature = "script")]
+struct Script {
+    hook: harvestlib::script::ScriptHook,
+    /// The file of the derived records, printed with the output of the run if not set
+    file: Option<std::io::BufWriter<std::fs::File>>,
+    output: OutputFormat,
+    /// Number of events the script failed on, kept as if it was not run
+    errors: u64,
+}
 
-    if type_.type_params.is_empty() {
-        base
-    } else {
-        let type_params = type_
-            .type_params
-            .iter()
-            .map(tag_to_short_string)
-            .collect::<Vec<_>>()
-            .join(", ");
-        format!("{}<{}>", base, type_params)
+#[cfg(feature = "script")]
+impl Script {
+    fn load(path: &Path, derived: Option<&Path>, output: OutputFormat) -> Result<Self> {
+        let file = derived
+            .map(|path| {
+                std::fs::File::create(path)
+                    .with_context(|| format!("Cannot create {}", path.display()))
+                    .map(std::io::BufWriter::new)
+            })
+            .transpose()?;
+        if file.is_none() && output == OutputFormat::Json {
+            bail!("The derived records of the script need `--script-output` with the JSON output");
+        }
+        Ok(Self {
+            hook: harvestlib::script::ScriptHook::load(path)?,
+            file,
+            output,
+            errors: 0,
+        })
+    }
+
+    /// Write a derived record, tagged to tell it from the events in the NDJSON output.
+    fn emit(&mut self, record: serde_json::Value) -> Result<()> {
+        use std::io::Write;
+        match &mut self.file {
+            Some(file) => writeln!(file, "{record}")?,
+            None if self.output == OutputFormat::Ndjson => {
+                println!("{}", serde_json::json!({ "derived": record }))
+            }
+            None => println!("{record}"),
+        }
+        Ok(())
+    }
+
+    /// Report the events the script failed on and flush the derived records.
+    fn finish(&mut self) -> Result<()> {
+        use std::io::Write;
+        if self.errors > 0 {
+            tracing::warn!("The script failed on {} events, kept as is", self.errors);
+        }
+        if let Some(file) = &mut self.file {
+            file.flush()?;
+        }
+        Ok(())
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::init();
+/// Let the script drop events and emit derived records. Events that could not be decoded,
+/// and those the script failed on, are kept.
+#[cfg(feature = "script")]
+fn run_script(
+    script: &mut Script,
+    data: Vec<harvestlib::EventRecord>,
+    decoded: Vec<Option<harvestlib::decode::DecodedEvent>>,
+) -> Result<(
+    Vec<harvestlib::EventRecord>,
+    Vec<Option<harvestlib::decode::DecodedEvent>>,
+)> {
+    let mut kept = Vec::with_capacity(data.len());
+    for (record, event) in data.into_iter().zip(decoded) {
+        let keep = match &event {
+            Some(event) => match script.hook.on_event(event) {
+                Ok((keep, derived)) => {
+                    for record in derived {
+                        script.emit(record)?;
+                    }
+                    keep
+                }
+                Err(e) => {
+                    tracing::warn!("The script failed on {}: {:#}", record.2.type_, e);
+                    script.errors += 1;
+                    true
+                }
+            },
+            None => true,
+        };
+        if keep {
+            kept.push((record, event));
+        }
+    }
+    Ok(kept.into_iter().unzip())
+}
 
-    let args = Args::parse();
+/// Number of checkpoints behind the latest checkpoint of the full node of that compared
+/// with the checkpoint stores by `check_chain`, as the stores may lag behind the full node.
+const CHAIN_CHECK_LAG: u64 = 1000;
 
-    let sui_mainnet = SuiClientBuilder::default()
-        .build(args.full_node_url)
-        .await?;
-    println!("Sui mainnet version: {}", sui_mainnet.api_version());
+/// The identifier of the chain of the full node `client`. The cache entries are recorded
+/// as checkpoints of that chain, so unless the cache folder already holds those of that
+/// chain, this checks that the checkpoint stores hold its checkpoints, e.g. that they are
+/// not those of another network, by comparing the summary of a recent checkpoint. The chain
+/// is then recorded in the cache folder for `harvest query`, which runs without network
+/// access.
+async fn check_chain(source: &SourceArgs, client: &SuiClient) -> Result<String> {
+    let chain = client.read_api().get_chain_identifier().await?;
+    let folder = source.cache_folder();
+    let recorded = cache::read_chain(&folder)?;
+    // The checkpoints of local stores are not cached
+    if source.checkpoints_dir.is_some() || recorded.as_deref() == Some(chain.as_str()) {
+        return Ok(chain);
+    }
 
-    // Get and print the latest checkpoint
-    let latest_checkpoint = sui_mainnet
+    let sequence_number = client
         .read_api()
         .get_latest_checkpoint_sequence_number()
-        .await?;
-
-    let limit = args.count;
+        .await?
+        .saturating_sub(CHAIN_CHECK_LAG);
+    let expected = client
+        .read_api()
+        .get_checkpoint(CheckpointId::SequenceNumber(sequence_number))
+        .await?
+        .digest;
+    for (i, url) in source.checkpoints_urls()?.into_iter().enumerate() {
+        let summary = CheckpointFetcher::new(
+            std::slice::from_ref(&url),
+            None,
+            &ReaderOptions::default(),
+            source.fetch_options(),
+            None,
+        )?
+        .fetch_summary(sequence_number)
+        .await;
+        match summary {
+            Ok(summary) if *summary.digest() == expected => (),
+            Ok(_) => bail!(
+                "The checkpoints of {url} are not those of the full node {}, of chain {chain}",
+                source.full_node_url()
+            ),
+            // The other stores only take over when the first one fails
+            Err(e) if i > 0 => tracing::warn!("Cannot check the chain of {url}: {e:#}"),
+            Err(e) => return Err(e).with_context(|| format!("Cannot check the chain of {url}")),
+        }
+    }
 
-    let initial = if args.follow {
-        println!(
-            "Following the latest checkpoint ({}) ...",
-            latest_checkpoint
+    if let Some(recorded) = recorded {
+        tracing::warn!(
+            "The cache of {} holds the checkpoints of chain {recorded}, not {chain}, which are \
+            downloaded again; `harvest cache clean` removes its cached events and event index",
+            folder.display()
         );
-        latest_checkpoint
+    }
+    if !source.dry_run {
+        cache::write_chain(&folder, &chain)?;
+    }
+    Ok(chain)
+}
+
+/// Remove cached checkpoints according to the cache policy, or all of them without one.
+fn clean_cache(source: &SourceArgs) -> Result<()> {
+    let folder = source.cache_folder();
+    let index = CacheIndex::open(&folder)?;
+    let policy = source.cache_policy();
+    let eviction = if policy.is_unbounded() {
+        event_cache::clear(&folder)?;
+        type_index::clear(&folder)?;
+        cache::clean(&folder, &index)?
     } else {
-        println!(
-            "Get events from checkpoints {} ... {}",
-            (latest_checkpoint - limit).max(0),
-            latest_checkpoint
-        );
-        (latest_checkpoint - limit).max(0)
+        cache::evict(&folder, &index, &policy, &HashSet::new())?
+    };
+    println!(
+        "Removed {} cached checkpoints ({:.2} GB)",
+        eviction.files,
+        eviction.bytes as f64 / 1e9
+    );
+    Ok(())
+}
+
+/// Number of checkpoints downloaded by `dry_run` to estimate the size and processing
+/// time of the others
+const DRY_RUN_SAMPLES: u64 = 10;
+
+/// The estimated work of processing a range of checkpoints.
+#[derive(Serialize)]
+struct DryRunReport {
+    first_checkpoint: u64,
+    /// The last checkpoint, or the tip if the range ends after it
+    last_checkpoint: u64,
+    transactions: u64,
+    cached_checkpoints: u64,
+    /// Estimated size of the checkpoints that are not cached
+    download_bytes: u64,
+    /// Estimated time of downloading and deserializing the checkpoints
+    seconds: f64,
+}
+
+/// Estimate the work of processing `limit` checkpoints from `initial`, up to the tip: the
+/// number of transactions from the summaries of the first and last checkpoints, and the
+/// download size and time from a sample of downloads, spread over the fetch and decode
+/// concurrency of the run.
+async fn dry_run(
+    source: &SourceArgs,
+    client: &SuiClient,
+    initial: u64,
+    limit: u64,
+    latest_checkpoint: u64,
+) -> Result<()> {
+    if limit == 0 || initial > latest_checkpoint {
+        bail!("No checkpoint of the range is available yet, the latest is {latest_checkpoint}");
+    }
+    let end = initial
+        .saturating_add(limit.saturating_sub(1))
+        .min(latest_checkpoint);
+    let checkpoints = end - initial + 1;
+
+    let total_transactions = |sequence_number| async move {
+        anyhow::Ok(
+            client
+                .read_api()
+                .get_checkpoint(CheckpointId::SequenceNumber(sequence_number))
+                .await?
+                .network_total_transactions,
+        )
+    };
+    let before = match initial {
+        0 => 0,
+        _ => total_transactions(initial - 1).await?,
+    };
+    let transactions = total_transactions(end).await? - before;
+
+    // A dry run does not write to the cache, nor index it
+    let (cached_checkpoints, _) = match CacheIndex::open_read_only(&source.cache_folder())? {
+        Some(index) => index.range_stats(initial, end)?,
+        None => (0, 0),
     };
 
-    // Get a new Custom Worker
-    let (executor, mut receiver) = EventExtractWorker::new(
-        initial,
-        limit,
-        |_e| true,
-        args.checkpoints_node_url.clone(),
-        args.concurrent as usize,
+    // Download evenly spread checkpoints, without caching them
+    let fetcher = CheckpointFetcher::new(
+        &source.checkpoints_urls()?,
         None,
-        Some(PathBuf::from("cache")),
-    )
-    .await?;
+        &ReaderOptions::default(),
+        source.fetch_options(),
+        None,
+    )?;
+    let samples = DRY_RUN_SAMPLES.min(checkpoints);
+    let (mut bytes, mut download, mut decode) = (0, Duration::ZERO, Duration::ZERO);
+    for i in 0..samples {
+        let sequence_number = initial + i * checkpoints / samples;
+        let started = Instant::now();
+        let file = fetcher.fetch_file(sequence_number).await?;
+        download += started.elapsed();
+        bytes += match &file {
+            CheckpointFile::Memory(contents) => contents.len() as u64,
+            CheckpointFile::Disk(path) => std::fs::metadata(path)?.len(),
+            CheckpointFile::Cached { file, .. } | CheckpointFile::Spooled(file) => {
+                file.metadata()?.len()
+            }
+        };
+        let started = Instant::now();
+        fetcher.decode(sequence_number, file)?;
+        decode += started.elapsed();
+    }
 
-    // spawn a task to process the received data
-    let join = tokio::spawn(async move {
-        // Histogram of identifiers
-        let mut histogram = HashMap::new();
-        let mut events_by_package = HashMap::new();
-
-        while let Some((_summary, data)) = receiver.recv().await {
-            // Update the histogram
-            data.iter().for_each(|(_index, _id, event)| {
-                let entry = histogram
-                    .entry(event.type_.address)
-                    .or_insert((0, HashMap::new()));
-                entry.0 += 1;
-                let entry = entry.1.entry(event.type_.clone()).or_insert(0);
-                *entry += 1;
-
-                let count = events_by_package.entry(event.package_id).or_insert(0);
-                *count += 1;
-            });
+    // Downloads and deserialization overlap, the slowest of the two sets the pace
+    let to_download = checkpoints - cached_checkpoints;
+    let decode_concurrency = source
+        .decode_concurrency
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |cpus| cpus.get()));
+    let mut download_seconds =
+        download.as_secs_f64() / samples as f64 * to_download as f64 / source.concurrent as f64;
+    if let Some(rate) = source.max_rps {
+        download_seconds = download_seconds.max(to_download as f64 / rate);
+    }
+    let decode_seconds =
+        decode.as_secs_f64() / samples as f64 * checkpoints as f64 / decode_concurrency as f64;
+    let report = DryRunReport {
+        first_checkpoint: initial,
+        last_checkpoint: end,
+        transactions,
+        cached_checkpoints,
+        download_bytes: bytes / samples * to_download,
+        seconds: download_seconds.max(decode_seconds),
+    };
+
+    match source.output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(&report)?),
+        OutputFormat::Text => {
+            println!(
+                "Checkpoints {}..={} ({checkpoints} checkpoints, {} transactions)",
+                report.first_checkpoint, report.last_checkpoint, report.transactions
+            );
+            println!(
+                "{} cached, {} to download (about {:.2} GB)",
+                report.cached_checkpoints,
+                to_download,
+                report.download_bytes as f64 / 1e9
+            );
+            println!(
+                "Estimated time: {:?} at {} concurrent downloads, from {samples} sample checkpoints",
+                Duration::from_secs(report.seconds.round() as u64),
+                source.concurrent
+            );
         }
+    }
+    Ok(())
+}
+
+/// Maximum number of ranges of checkpoints printed by `print_cache_stats` and `query_cache`
+const MAX_PRINTED_RANGES: usize = 20;
 
-        // Print all entries in the histogram, sorted in descending order of value
-        let mut histogram: Vec<_> = histogram.into_iter().collect();
-        histogram.sort_by(|a, b| b.1 .0.cmp(&a.1 .0));
+/// List the events of the types matching `pattern` in checkpoints `start..=end`. The indexed
+/// checkpoints are only read if they hold such events, and the others are read in full from
+/// the cache; the query fails if any checkpoint needed is not cached.
+fn query_cache(
+    source: &SourceArgs,
+    pattern: &EventTypePattern,
+    start: u64,
+    end: u64,
+) -> Result<()> {
+    if start > end {
+        bail!("The end checkpoint must not be before the start checkpoint");
+    }
+    let folder = &source.cache_folder();
+    let output = source.output;
+    if !folder.exists() {
+        bail!("There is no cache to query, run `harvest events --index-events` first");
+    }
+    let chain = cache::read_chain(folder)?.ok_or_else(|| {
+        anyhow!("The chain of the cache is unknown, run `harvest events --index-events` first")
+    })?;
+    let index = TypeIndex::open(folder)?;
+    let locations = index.locations(pattern, start, end)?;
+    let unindexed = index.missing(start, end)?;
 
-        // Sum all events
-        let total_events: usize = histogram.iter().map(|(_type_, value)| value.0).sum();
-        // Define the cutoff to suppress
-        let cutoff = (total_events as f64 * args.suppress / 100.0).round() as usize;
-        if cutoff > 0 {
-            println!("Suppressing packages with fewer than {} events", cutoff);
+    // Every checkpoint to read must be in the cache
+    let mut checkpoints: BTreeSet<u64> = locations.iter().map(|l| l.checkpoint).collect();
+    checkpoints.extend(unindexed.iter().flat_map(|&(first, last)| first..=last));
+    let mut uncached: Vec<(u64, u64)> = Vec::new();
+    for &sequence_number in &checkpoints {
+        let path = folder.join(format!("{sequence_number}.{CHECKPOINT_EXTENSION}"));
+        if path.exists() {
+            continue;
+        }
+        match uncached.last_mut() {
+            Some((_, last)) if *last + 1 == sequence_number => *last = sequence_number,
+            _ => uncached.push((sequence_number, sequence_number)),
+        }
+    }
+    if !uncached.is_empty() {
+        let count: u64 = uncached.iter().map(|(first, last)| last - first + 1).sum();
+        let mut ranges: Vec<_> = uncached
+            .iter()
+            .take(MAX_PRINTED_RANGES)
+            .map(|(first, last)| format!("{first}..={last}"))
+            .collect();
+        if uncached.len() > MAX_PRINTED_RANGES {
+            ranges.push(format!("and {} more", uncached.len() - MAX_PRINTED_RANGES));
         }
+        bail!(
+            "{count} checkpoints needed by the query are not cached: {}",
+            ranges.join(", ")
+        );
+    }
 
-        for (type_, value) in histogram.into_iter() {
-            if value.0 < cutoff {
-                continue;
+    // The events at the indexed locations, and those of the types in the other checkpoints
+    let located: BTreeSet<_> = locations
+        .iter()
+        .map(|l| (l.checkpoint, l.transaction, l.event))
+        .collect();
+    let read = checkpoints.len();
+    let mut events = Vec::new();
+    for sequence_number in checkpoints {
+        let data = read_cached(folder, sequence_number, &chain)?.ok_or_else(|| {
+            anyhow!(
+                "Checkpoint {sequence_number} was evicted from the cache, or is of another chain"
+            )
+        })?;
+        let indexed = !unindexed
+            .iter()
+            .any(|(first, last)| (*first..=*last).contains(&sequence_number));
+        let (_, records) = extract_events(data);
+        events.extend(
+            records
+                .into_iter()
+                .filter(|(index, id, event)| match indexed {
+                    true => located.contains(&(
+                        sequence_number,
+                        index.transaction_sequence_number,
+                        id.event_seq,
+                    )),
+                    false => pattern.matches(&event.type_),
+                }),
+        );
+    }
+
+    match output {
+        OutputFormat::Json => {
+            let events: Vec<_> = events.iter().map(JsonEvent::from).collect();
+            println!("{}", serde_json::to_string_pretty(&events)?);
+        }
+        OutputFormat::Ndjson => {
+            for record in &events {
+                println!("{}", serde_json::to_string(&JsonEvent::from(record))?);
+            }
+        }
+        OutputFormat::Text => {
+            for (index, id, event) in &events {
+                println!(
+                    "{} {}:{} {} {}",
+                    index.checkpoint_sequence_number.to_string().blue(),
+                    id.tx_digest,
+                    id.event_seq,
+                    type_to_short_string(&event.type_).green(),
+                    event.sender
+                );
             }
+            println!(
+                "{} events in checkpoints {start}..={end}, {read} checkpoints read from the cache",
+                events.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the decoded events of the transaction `digest`. Its checkpoint is looked up on the
+/// full node, or among the cached checkpoints once the full node pruned the transaction.
+async fn print_transaction_events(
+    source: &SourceArgs,
+    client: SuiClient,
+    digest: TransactionDigest,
+    checkpoint: Option<u64>,
+) -> Result<()> {
+    let fetcher = CheckpointFetcher::new(
+        &source.checkpoints_urls()?,
+        Some(source.cache_folder()),
+        &ReaderOptions::default(),
+        source.fetch_options(),
+        None,
+    )?;
+    let contains = |data: &CheckpointData| {
+        data.transactions
+            .iter()
+            .any(|tx| *tx.transaction.digest() == digest)
+    };
 
-            println!("\x1b[34m{:<5}\x1b[0m {}", value.0, type_.to_string().red());
+    let sequence_number = match checkpoint {
+        Some(checkpoint) => Some(checkpoint),
+        None => match client
+            .read_api()
+            .get_transaction_with_options(digest, SuiTransactionBlockResponseOptions::new())
+            .await
+        {
+            Ok(response) => response.checkpoint,
+            Err(e) => {
+                tracing::warn!("Cannot look up {} on the full node: {}", digest, e);
+                None
+            }
+        },
+    };
+    let data = match sequence_number {
+        Some(sequence_number) => {
+            let data = fetcher.fetch(sequence_number).await?;
+            if !contains(&data) {
+                bail!("Transaction {digest} is not in checkpoint {sequence_number}");
+            }
+            data
+        }
+        None => {
+            tracing::info!("Scanning the cached checkpoints for {} ...", digest);
+            let cache_folder = &source.cache_folder();
+            let ranges = match cache_folder.exists() {
+                true => CacheIndex::open(cache_folder)?.stats()?.ranges,
+                false => Vec::new(),
+            };
+            let mut found = None;
+            for sequence_number in ranges.into_iter().flat_map(|(first, last)| first..=last) {
+                let data = fetcher.fetch(sequence_number).await?;
+                if contains(&data) {
+                    found = Some(data);
+                    break;
+                }
+            }
+            found.ok_or_else(|| {
+                anyhow!("Transaction {digest} is in none of the cached checkpoints")
+            })?
+        }
+    };
 
-            let mut inner_histogram: Vec<_> = value.1.into_iter().collect();
-            inner_histogram.sort_by(|a, b| b.1.cmp(&a.1));
+    let (summary, records) = extract_events(data);
+    let mut decoder = EventDecoder::new(client).with_object_types();
+    let mut events = Vec::new();
+    for record in records.iter().filter(|(_, id, _)| id.tx_digest == digest) {
+        match decoder.decode(record).await {
+            Ok(event) => events.push((record, event)),
+            Err(e) => tracing::warn!("Cannot decode {}: {}", record.2.type_, e),
+        }
+    }
 
-            for (type_, value) in inner_histogram.into_iter() {
+    match source.output {
+        OutputFormat::Json => {
+            let events: Vec<_> = events.iter().map(|(_, event)| event).collect();
+            println!("{}", serde_json::to_string_pretty(&events)?);
+        }
+        OutputFormat::Ndjson => {
+            for (_, event) in &events {
+                println!("{}", serde_json::to_string(event)?);
+            }
+        }
+        OutputFormat::Text => {
+            println!(
+                "Transaction {} in checkpoint {} ({}), {} events",
+                digest,
+                summary.sequence_number,
+                summary.timestamp_ms,
+                events.len()
+            );
+            for (record, event) in &events {
                 println!(
-                    "       \x1b[34m{:5}\x1b[0m : {}",
-                    value,
-                    type_to_short_string(&type_).green()
+                    "{} {} {}",
+                    record.1.event_seq.to_string().blue(),
+                    type_to_short_string(&record.2.type_).green(),
+                    event.fields
                 );
+                for (id, type_) in &event.objects {
+                    println!("    {} : {}", id, type_.yellow());
+                }
             }
         }
+    }
+    Ok(())
+}
+
+fn print_cache_stats(folder: &Path, output: OutputFormat) -> Result<()> {
+    let stats = CacheIndex::open(folder)?.stats()?;
+    match output {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            return Ok(());
+        }
+        OutputFormat::Ndjson => {
+            println!("{}", serde_json::to_string(&stats)?);
+            return Ok(());
+        }
+        OutputFormat::Text => (),
+    }
 
-        println!("\nEvents by package:");
-        for (package, count) in &events_by_package {
-            println!("\x1b[34m{package:<5}\x1b[0m {count}");
+    println!(
+        "{} cached checkpoints ({:.2} GB)",
+        stats.checkpoints,
+        stats.bytes as f64 / 1e9
+    );
+    match stats.hit_rate() {
+        Some(rate) => println!(
+            "{} hits and {} misses ({:.1}% hit rate)",
+            stats.hits,
+            stats.misses,
+            rate * 100.0
+        ),
+        None => println!("No checkpoint fetched yet"),
+    }
+    if !stats.ranges.is_empty() {
+        println!("\nCached ranges:");
+        for (first, last) in stats.ranges.iter().take(MAX_PRINTED_RANGES) {
+            println!("\x1b[34m{:<8}\x1b[0m {first}..={last}", last - first + 1);
         }
-        let total_packages = events_by_package.len();
-        let average_events_by_package = events_by_package.values().sum::<usize>() / total_packages;
-        let stdev_events_by_package = events_by_package
-            .values()
-            .map(|&x| x as f64)
-            .collect::<Vec<_>>()
-            .std_dev();
-        println!(
-            "Summary: {total_packages} packages, 
-            with an average of {average_events_by_package} +- {stdev_events_by_package} events each"
+        if stats.ranges.len() > MAX_PRINTED_RANGES {
+            println!("... and {} more", stats.ranges.len() - MAX_PRINTED_RANGES);
+        }
+    }
+    Ok(())
+}
+
+/// Parse an inclusive range of checkpoints, `start-end`.
+fn parse_range(s: &str) -> Result<RangeInclusive<u64>> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| anyhow!("Expected a range of checkpoints `start-end`, got `{s}`"))?;
+    let (start, end): (u64, u64) = (start.parse()?, end.parse()?);
+    if start > end {
+        bail!("Start checkpoint {start} is after end checkpoint {end}");
+    }
+    Ok(start..=end)
+}
+
+/// Parse a header of the requests, `name: value`.
+fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue)> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected a header `name: value`, got `{s}`"))?;
+    Ok((name.trim().parse()?, value.trim().parse()?))
+}
+
+/// The certificate authorities of a PEM file.
+#[derive(Debug, Clone)]
+struct CaBundle(Vec<Certificate>);
+
+fn parse_ca_bundle(path: &str) -> Result<CaBundle> {
+    let pem = std::fs::read(path).with_context(|| format!("Cannot read {path}"))?;
+    let certificates = Certificate::from_pem_bundle(&pem)?;
+    if certificates.is_empty() {
+        bail!("No certificate in {path}");
+    }
+    Ok(CaBundle(certificates))
+}
+
+/// Parse the fraction of the sampled checkpoints, in (0, 1].
+fn parse_fraction(s: &str) -> Result<f64> {
+    let fraction: f64 = s.parse()?;
+    if !(fraction > 0.0 && fraction <= 1.0) {
+        bail!("Expected a fraction of the checkpoints in (0, 1], got `{s}`");
+    }
+    Ok(fraction)
+}
+
+/// Check the name of a preset when parsing the arguments.
+fn parse_preset(name: &str) -> Result<String> {
+    preset(name)?;
+    Ok(name.to_string())
+}
+
+/// Check the name of an analyzer when parsing the arguments.
+fn parse_analyzer(name: &str) -> Result<String> {
+    analyzer(name, &ReportOptions::default())?;
+    Ok(name.to_string())
+}
+
+/// The progress bars of the run, above which the logs are written, see `LogWriter`.
+fn progress_bars() -> &'static MultiProgress {
+    static BARS: OnceLock<MultiProgress> = OnceLock::new();
+    BARS.get_or_init(MultiProgress::new)
+}
+
+/// Writes the logs to stderr, hiding the progress bars meanwhile so that the lines do not
+/// tear through them.
+struct LogWriter;
+
+impl std::io::Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        progress_bars().suspend(|| std::io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+/// A progress bar on stderr of the runs with a known number of checkpoints, with the rates
+/// of checkpoints and of the extracted items, and the estimated remaining time. It is not
+/// drawn when stderr is not a terminal.
+struct RunProgress {
+    bar: ProgressBar,
+    /// The checkpoints of the run, the bar being at the number of them up to the last
+    /// received one, whether the others were skipped, sampled out or are missing
+    ranges: Vec<RangeInclusive<u64>>,
+    started: Instant,
+    /// Name of the extracted items, e.g. `events`
+    unit: &'static str,
+    items: u64,
+}
+
+impl RunProgress {
+    /// The progress bar of `limit` checkpoints from `initial`, unless the run is unbounded.
+    fn new(initial: u64, limit: u64, unit: &'static str) -> Option<Self> {
+        if limit >= u64::MAX - initial {
+            return None;
+        }
+        Some(Self::over_ranges(vec![initial..=initial + limit - 1], unit))
+    }
+
+    /// The progress bar of a run over `ranges`, sorted and not overlapping.
+    fn over_ranges(ranges: Vec<RangeInclusive<u64>>, unit: &'static str) -> Self {
+        let checkpoints = ranges.iter().map(|range| range.end() - range.start() + 1);
+        let bar = progress_bars().add(ProgressBar::new(checkpoints.sum()));
+        bar.set_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} checkpoints, {msg}, ETA {eta}")
+                .expect("Valid progress bar template"),
         );
+        Self {
+            bar,
+            ranges,
+            started: Instant::now(),
+            unit,
+            items: 0,
+        }
+    }
+
+    /// Count the checkpoints up to `sequence_number`, that was processed, and its extracted
+    /// items.
+    fn observe(&mut self, sequence_number: u64, items: usize) {
+        self.items += items as u64;
+        let position: u64 = self
+            .ranges
+            .iter()
+            .filter(|range| *range.start() <= sequence_number)
+            .map(|range| sequence_number.min(*range.end()) - range.start() + 1)
+            .sum();
+        self.bar.set_position(position.max(self.bar.position()));
+        let elapsed = self.started.elapsed().as_secs_f64().max(f64::EPSILON);
+        self.bar.set_message(format!(
+            "{:.1} checkpoints/s, {:.0} {}/s",
+            self.bar.position() as f64 / elapsed,
+            self.items as f64 / elapsed,
+            self.unit
+        ));
+    }
+}
+
+impl Drop for RunProgress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// The last checkpoint of a run of `limit` checkpoints from `initial`.
+fn last_checkpoint(initial: u64, limit: u64) -> Result<u64> {
+    limit
+        .checked_sub(1)
+        .map(|count| initial + count)
+        .context("The run has no checkpoints to process")
+}
+
+/// Check the gaps of a run ending at `end`, missing its last checkpoints if it completed
+/// without receiving them.
+fn finish_gaps(mut gaps: Gaps, completed: bool, end: u64, strict: bool) -> Result<()> {
+    if completed {
+        gaps.finish(end);
+    }
+    check_gaps(&gaps, strict)
+}
+
+/// Report the checkpoints missing from the processed range, failing in strict mode.
+fn check_gaps(gaps: &Gaps, strict: bool) -> Result<()> {
+    if gaps.is_empty() {
+        return Ok(());
+    }
+    tracing::warn!("Missing {} checkpoints: {}", gaps.count(), gaps);
+    if strict {
+        bail!("{} checkpoints are missing from the run", gaps.count());
+    }
+    Ok(())
+}
+
+/// The totals of the items extracted from the checkpoints of a run, printed as a report once
+/// the run is over.
+trait ReportHistogram<T>: Send + 'static {
+    type Report: Labeled + Serialize;
+
+    fn add_checkpoint(&mut self, items: &[T]);
+
+    fn report(self) -> Self::Report;
+
+    fn print(report: &Self::Report);
+}
+
+impl ReportHistogram<BalanceChange> for BalanceHistogram {
+    type Report = BalanceReport;
+
+    fn add_checkpoint(&mut self, changes: &[BalanceChange]) {
+        changes.iter().for_each(|change| self.add(change));
+    }
+
+    fn report(self) -> BalanceReport {
+        self.report()
+    }
+
+    fn print(report: &BalanceReport) {
+        print_balance_report(report)
+    }
+}
+
+impl ReportHistogram<BalanceChange> for CoinFlowHistogram {
+    type Report = CoinFlowReport;
+
+    fn add_checkpoint(&mut self, changes: &[BalanceChange]) {
+        self.add_checkpoint(changes)
+    }
+
+    fn report(self) -> CoinFlowReport {
+        self.report()
+    }
+
+    fn print(report: &CoinFlowReport) {
+        print_coin_flow_report(report)
+    }
+}
+
+impl ReportHistogram<TransactionRecord> for GasHistogram {
+    type Report = GasReport;
+
+    fn add_checkpoint(&mut self, transactions: &[TransactionRecord]) {
+        transactions
+            .iter()
+            .for_each(|transaction| self.add(transaction));
+    }
+
+    fn report(self) -> GasReport {
+        self.report()
+    }
+
+    fn print(report: &GasReport) {
+        print_gas_report(report)
+    }
+}
+
+/// Drive the executor of a worker over `limit` checkpoints from `initial`, adding the `unit`
+/// of every checkpoint to `histogram`, and print its report once the run is over.
+async fn run_report<T, W, E, H>(
+    source: &SourceArgs,
+    (initial, limit): (u64, u64),
+    (executor, mut receiver): (
+        impl Future<Output = Result<W, E>>,
+        Receiver<(CertifiedCheckpointSummary, Vec<T>)>,
+    ),
+    progress_file: &str,
+    unit: &'static str,
+    mut histogram: H,
+) -> Result<()>
+where
+    T: Send + 'static,
+    E: std::error::Error + Send + Sync + 'static,
+    H: ReportHistogram<T>,
+{
+    let (output, strict, labels) = (source.output, source.strict, source.labels()?);
+    let saves_progress = source.saves_progress();
+    let progress_file = source.cache_file(progress_file);
+    let end = last_checkpoint(initial, limit)?;
+    let join = tokio::spawn(async move {
+        let mut progress = Progress {
+            next_checkpoint: initial,
+            end_checkpoint: end,
+        };
+        let mut gaps = Gaps::new(initial);
+        let mut bar = RunProgress::new(initial, limit, unit);
+
+        while let Some((summary, items)) = receiver.recv().await {
+            gaps.observe(summary.sequence_number);
+            if let Some(bar) = &mut bar {
+                bar.observe(summary.sequence_number, items.len());
+            }
+            histogram.add_checkpoint(&items);
+
+            // Record the checkpoint as fully processed
+            progress.next_checkpoint = summary.sequence_number + 1;
+            if saves_progress {
+                progress.save(&progress_file)?;
+            }
+        }
+
+        let mut report = histogram.report();
+        report.label(&labels);
+        match output {
+            OutputFormat::Text => H::print(&report),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            OutputFormat::Ndjson => println!("{}", serde_json::to_string(&report)?),
+        }
+        anyhow::Ok(gaps)
+    });
+
+    let completed = run_until_shutdown(executor).await?;
+    finish_gaps(join.await??, completed, end, strict)
+}
+
+/// Extract balance changes and print their totals by coin type and owner.
+async fn harvest_balance_changes(
+    source: &SourceArgs,
+    initial: u64,
+    limit: u64,
+    metrics: Option<HarvestMetrics>,
+) -> Result<()> {
+    let mut builder = BalanceChangeExtractWorker::builder(|_change: &BalanceChange| true)
+        .start(initial)
+        .limit(limit)
+        .remote_store_urls(source.checkpoints_urls()?)
+        .concurrency(source.concurrent as usize)
+        .fetch_options(source.fetch_options())
+        .cache_folder(source.cache_folder())
+        .channel(source.channel_config());
+    if let Some(metrics) = metrics {
+        builder = builder.metrics(metrics);
+    }
+    run_report(
+        source,
+        (initial, limit),
+        builder.build().await?,
+        BALANCE_CHANGES_PROGRESS_FILE,
+        "balance changes",
+        BalanceHistogram::default(),
+    )
+    .await
+}
+
+/// Extract the balance changes of one coin type and print its mints, burns and transfers.
+async fn harvest_coin_flow(
+    source: &SourceArgs,
+    coin_type: TypeTag,
+    initial: u64,
+    limit: u64,
+    metrics: Option<HarvestMetrics>,
+) -> Result<()> {
+    let filter_type = coin_type.clone();
+    let mut builder = BalanceChangeExtractWorker::builder(move |change: &BalanceChange| {
+        change.coin_type == filter_type
+    })
+    .start(initial)
+    .limit(limit)
+    .remote_store_urls(source.checkpoints_urls()?)
+    .concurrency(source.concurrent as usize)
+    .fetch_options(source.fetch_options())
+    .cache_folder(source.cache_folder())
+    .channel(source.channel_config());
+    if let Some(metrics) = metrics {
+        builder = builder.metrics(metrics);
+    }
+    run_report(
+        source,
+        (initial, limit),
+        builder.build().await?,
+        COIN_FLOW_PROGRESS_FILE,
+        "balance changes",
+        CoinFlowHistogram::new(coin_type),
+    )
+    .await
+}
+
+/// Extract transactions and print the gas they used by package and entry function.
+async fn harvest_gas(
+    source: &SourceArgs,
+    initial: u64,
+    limit: u64,
+    metrics: Option<HarvestMetrics>,
+) -> Result<()> {
+    let mut builder = TransactionExtractWorker::builder(|_transaction: &TransactionRecord| true)
+        .start(initial)
+        .limit(limit)
+        .remote_store_urls(source.checkpoints_urls()?)
+        .concurrency(source.concurrent as usize)
+        .fetch_options(source.fetch_options())
+        .cache_folder(source.cache_folder())
+        .channel(source.channel_config());
+    if let Some(metrics) = metrics {
+        builder = builder.metrics(metrics);
+    }
+    run_report(
+        source,
+        (initial, limit),
+        builder.build().await?,
+        GAS_PROGRESS_FILE,
+        "transactions",
+        GasHistogram::default(),
+    )
+    .await
+}
+
+/// Read the summaries of the checkpoints from the full node and print their transactions, gas
+/// and timestamps.
+async fn harvest_checkpoint_summaries(
+    source: &SourceArgs,
+    client: SuiClient,
+    initial: u64,
+    limit: u64,
+    metrics: Option<HarvestMetrics>,
+) -> Result<()> {
+    let mut builder = CheckpointSummaryWorker::builder(client)
+        .start(initial)
+        .limit(limit)
+        .channel(source.channel_config())
+        .retry_policy(source.fetch_options().retry_policy);
+    if let Some(metrics) = metrics {
+        builder = builder.metrics(metrics);
+    }
+    let (executor, mut receiver) = builder.build().await?;
+
+    let (output, saves_progress) = (source.output, source.saves_progress());
+    let end = last_checkpoint(initial, limit)?;
+    let progress_file = source.cache_file(CHECKPOINTS_PROGRESS_FILE);
+    let join = tokio::spawn(async move {
+        let mut histogram = CheckpointSummaryHistogram::default();
+        let mut progress = Progress {
+            next_checkpoint: initial,
+            end_checkpoint: end,
+        };
+        let mut bar = RunProgress::new(initial, limit, "transactions");
+
+        while let Some(summary) = receiver.recv().await {
+            if let Some(bar) = &mut bar {
+                bar.observe(summary.sequence_number, summary.transactions as usize);
+            }
+            progress.next_checkpoint = summary.sequence_number + 1;
+            if saves_progress {
+                progress.save(&progress_file)?;
+            }
+            histogram.add(summary);
+        }
+
+        let mut report = histogram.report();
+        match output {
+            OutputFormat::Text => print_checkpoint_summary_report(&report),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            OutputFormat::Ndjson => {
+                for summary in std::mem::take(&mut report.summaries) {
+                    println!("{}", serde_json::to_string(&summary)?);
+                }
+                println!("{}", serde_json::to_string(&report)?);
+            }
+        }
+        anyhow::Ok(())
     });
 
-    executor.await?;
-    join.await?;
+    run_until_shutdown(executor).await?;
+    join.await??;
     Ok(())
 }
+
+/// Extract the events of two ranges of checkpoints and print the change of the number of
+/// events of every type from the first to the second.
+async fn harvest_diff(
+    source: &SourceArgs,
+    (range_a, range_b): (RangeInclusive<u64>, RangeInclusive<u64>),
+    filter: EventFilter,
+    top: Option<usize>,
+    metrics: Option<HarvestMetrics>,
+) -> Result<()> {
+    // Both ranges are downloaded by a single worker, in checkpoint order
+    let mut ranges = vec![range_a.clone(), range_b.clone()];
+    ranges.sort_by_key(|range| *range.start());
+    let mut builder = EventExtractWorker::builder(move |record| filter.matches(record))
+        .ranges(ranges.clone())
+        .remote_store_urls(source.checkpoints_urls()?)
+        .concurrency(source.concurrent as usize)
+        .fetch_options(source.fetch_options())
+        .cache_folder(source.cache_folder())
+        .channel(source.channel_config());
+    if let Some(metrics) = metrics {
+        builder = builder.metrics(metrics);
+    }
+    let (executor, mut receiver) = builder.build().await?;
+
+    let (output, strict, labels) = (source.output, source.strict, source.labels()?);
+    let end = *ranges[1].end();
+    let join = tokio::spawn(async move {
+        let mut diff = EventDiff::new(range_a, range_b);
+        let mut gaps = Gaps::over_ranges(&ranges);
+        let mut bar = Some(RunProgress::over_ranges(ranges.clone(), "events"));
+
+        while let Some((summary, events)) = receiver.recv().await {
+            gaps.observe(summary.sequence_number);
+            if let Some(bar) = &mut bar {
+                bar.observe(summary.sequence_number, events.len());
+            }
+            events.iter().for_each(|record| diff.add(record));
+        }
+        drop(bar);
+
+        let mut report = diff.report(top);
+        report.label(&labels);
+        match output {
+            OutputFormat::Text => print!("{report}"),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+            OutputFormat::Ndjson => println!("{}", serde_json::to_string(&report)?),
+        }
+        anyhow::Ok(gaps)
+    });
+
+    let completed = run_until_shutdown(executor).await?;
+    finish_gaps(join.await??, completed, end, strict)
+}
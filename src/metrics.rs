@@ -0,0 +1,160 @@
+#[cfg(feature = "metrics")]
+use prometheus::{
+    register_gauge_vec_with_registry, register_histogram_with_registry,
+    register_int_counter_vec_with_registry, register_int_counter_with_registry,
+    register_int_gauge_with_registry, GaugeVec, Histogram, HistogramTimer, IntCounter,
+    IntCounterVec, IntGauge, Registry,
+};
+#[cfg(feature = "metrics-server")]
+use {
+    anyhow::Result,
+    axum::{routing::get, Router},
+    prometheus::TextEncoder,
+    std::net::SocketAddr,
+};
+
+/// Metrics of a harvester run, exposed in the Prometheus format.
+#[cfg(feature = "metrics")]
+#[derive(Clone)]
+pub struct HarvestMetrics {
+    pub checkpoints_processed: IntCounter,
+    pub checkpoints_skipped: IntCounter,
+    pub events_processed: IntCounter,
+    pub latest_checkpoint: IntGauge,
+    pub checkpoint_lag: IntGauge,
+    pub fetch_latency: Histogram,
+    pub cache_hits: IntCounter,
+    pub cache_misses: IntCounter,
+    /// Only the types flagged at least once have a series
+    pub anomalies: IntCounterVec,
+    pub anomaly_score: GaugeVec,
+}
+
+/// Without the `metrics` feature there are no metrics: this type has no values, so the
+/// metrics of the workers are always `None`.
+#[cfg(not(feature = "metrics"))]
+#[derive(Clone)]
+pub enum HarvestMetrics {}
+
+#[cfg(feature = "metrics")]
+impl HarvestMetrics {
+    pub fn new(registry: &Registry) -> Self {
+        Self {
+            checkpoints_processed: register_int_counter_with_registry!(
+                "harvest_checkpoints_processed",
+                "Number of checkpoints processed",
+                registry
+            )
+            .unwrap(),
+            checkpoints_skipped: register_int_counter_with_registry!(
+                "harvest_checkpoints_skipped",
+                "Number of checkpoints that could not be fetched or decoded",
+                registry
+            )
+            .unwrap(),
+            events_processed: register_int_counter_with_registry!(
+                "harvest_events_processed",
+                "Number of events delivered after filtering",
+                registry
+            )
+            .unwrap(),
+            latest_checkpoint: register_int_gauge_with_registry!(
+                "harvest_latest_checkpoint",
+                "Sequence number of the latest processed checkpoint",
+                registry
+            )
+            .unwrap(),
+            checkpoint_lag: register_int_gauge_with_registry!(
+                "harvest_checkpoint_lag",
+                "Number of checkpoints between the latest processed one and the tip",
+                registry
+            )
+            .unwrap(),
+            fetch_latency: register_histogram_with_registry!(
+                "harvest_fetch_latency_seconds",
+                "Time to download a checkpoint from the remote store",
+                registry
+            )
+            .unwrap(),
+            cache_hits: register_int_counter_with_registry!(
+                "harvest_cache_hits",
+                "Number of checkpoints read from the local cache",
+                registry
+            )
+            .unwrap(),
+            cache_misses: register_int_counter_with_registry!(
+                "harvest_cache_misses",
+                "Number of checkpoints downloaded from the remote store",
+                registry
+            )
+            .unwrap(),
+            anomalies: register_int_counter_vec_with_registry!(
+                "harvest_event_rate_anomalies",
+                "Number of spikes and droughts of the rate of an event type",
+                &["type", "kind"],
+                registry
+            )
+            .unwrap(),
+            anomaly_score: register_gauge_vec_with_registry!(
+                "harvest_event_rate_anomaly_score",
+                "Standard deviations of the rate of an event type from its mean, 0 when normal",
+                &["type"],
+                registry
+            )
+            .unwrap(),
+        }
+    }
+}
+
+/// Times a download for the `fetch_latency` of the metrics, if any. Dropping it records the
+/// duration like `observe`.
+pub(crate) struct FetchTimer {
+    #[cfg(feature = "metrics")]
+    timer: Option<HistogramTimer>,
+}
+
+impl FetchTimer {
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn start(metrics: Option<&HarvestMetrics>) -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            timer: metrics.map(|metrics| metrics.fetch_latency.start_timer()),
+        }
+    }
+
+    /// Record the duration of a completed download.
+    pub(crate) fn observe(self) {
+        #[cfg(feature = "metrics")]
+        if let Some(timer) = self.timer {
+            timer.observe_duration();
+        }
+    }
+
+    /// Forget a download that failed or found nothing.
+    pub(crate) fn discard(self) {
+        #[cfg(feature = "metrics")]
+        if let Some(timer) = self.timer {
+            timer.stop_and_discard();
+        }
+    }
+}
+
+#[cfg(feature = "metrics-server")]
+/// Serve the metrics of the registry on `/metrics` until the process exits.
+pub async fn serve_metrics(registry: Registry, address: SocketAddr) -> Result<()> {
+    let app = Router::new().route(
+        "/metrics",
+        get(move || {
+            let registry = registry.clone();
+            async move {
+                TextEncoder::new()
+                    .encode_to_string(&registry.gather())
+                    .unwrap_or_else(|e| format!("# Cannot encode metrics: {}", e))
+            }
+        }),
+    );
+
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
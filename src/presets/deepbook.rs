@@ -0,0 +1,179 @@
+use std::{collections::HashMap, fmt::Write};
+
+use move_core_types::language_storage::StructTag;
+use serde::{Deserialize, Serialize};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    DEEPBOOK_ADDRESS,
+};
+
+use crate::{
+    analyzer::{Analyzer, AnalyzerReport},
+    report::tag_to_short_string,
+    EventRecord,
+};
+
+/// DeepBook prices are fixed point numbers with 9 decimals
+const FLOAT_SCALING: f64 = 1e9;
+
+/// `0xdee9::clob_v2::OrderFilled<BaseAsset, QuoteAsset>`
+#[derive(Debug, Deserialize)]
+struct OrderFilled {
+    pool_id: ObjectID,
+    _order_id: u64,
+    _taker_client_order_id: u64,
+    _maker_client_order_id: u64,
+    _original_quantity: u64,
+    base_asset_quantity_filled: u64,
+    _base_asset_quantity_remaining: u64,
+    _is_bid: bool,
+    _maker_address: SuiAddress,
+    _taker_address: SuiAddress,
+    price: u64,
+    _taker_commission: u64,
+    _maker_rebates: u64,
+}
+
+/// `0xdee9::clob_v2::OrderPlaced<BaseAsset, QuoteAsset>`
+#[derive(Debug, Deserialize)]
+struct OrderPlaced {
+    pool_id: ObjectID,
+    _order_id: u64,
+    _client_order_id: u64,
+    is_bid: bool,
+    _owner: SuiAddress,
+    _original_quantity: u64,
+    _base_asset_quantity_placed: u64,
+    _price: u64,
+    _expire_timestamp: u64,
+}
+
+/// Activity of a DeepBook pool. Quantities are in the smallest units of the assets.
+#[derive(Debug, Default, Serialize)]
+struct PoolStats {
+    pool_id: String,
+    /// `Base/Quote`
+    pair: String,
+    fills: u64,
+    base_volume: u128,
+    quote_volume: u128,
+    min_price: Option<f64>,
+    max_price: Option<f64>,
+    last_price: Option<f64>,
+    /// Volume weighted average price
+    average_price: Option<f64>,
+    bids_placed: u64,
+    asks_placed: u64,
+}
+
+impl PoolStats {
+    fn fill(&mut self, quantity: u64, price: u64) {
+        let scaled = price as f64 / FLOAT_SCALING;
+        self.fills += 1;
+        self.base_volume += quantity as u128;
+        self.quote_volume += quantity as u128 * price as u128 / FLOAT_SCALING as u128;
+        self.min_price = Some(self.min_price.map_or(scaled, |min| min.min(scaled)));
+        self.max_price = Some(self.max_price.map_or(scaled, |max| max.max(scaled)));
+        self.last_price = Some(scaled);
+        self.average_price = Some(self.quote_volume as f64 / self.base_volume.max(1) as f64);
+    }
+}
+
+/// Traded volume and price statistics of the DeepBook v2 pools, from their `OrderFilled`
+/// and `OrderPlaced` events.
+#[derive(Debug, Default)]
+pub struct DeepBookPreset {
+    pools: HashMap<ObjectID, PoolStats>,
+    /// Events of the known types that could not be decoded
+    errors: u64,
+}
+
+impl DeepBookPreset {
+    fn pool(&mut self, pool_id: ObjectID, type_: &StructTag) -> &mut PoolStats {
+        self.pools.entry(pool_id).or_insert_with(|| PoolStats {
+            pool_id: pool_id.to_string(),
+            pair: type_
+                .type_params
+                .iter()
+                .map(tag_to_short_string)
+                .collect::<Vec<_>>()
+                .join("/"),
+            ..PoolStats::default()
+        })
+    }
+
+    /// Pools by decreasing number of fills.
+    fn sorted_pools(&self) -> Vec<&PoolStats> {
+        let mut pools: Vec<_> = self.pools.values().collect();
+        pools.sort_by(|a, b| b.fills.cmp(&a.fills).then(a.pool_id.cmp(&b.pool_id)));
+        pools
+    }
+}
+
+impl Analyzer for DeepBookPreset {
+    fn name(&self) -> &'static str {
+        "deepbook"
+    }
+
+    fn on_event(&mut self, (_index, _id, event): &EventRecord) {
+        let type_ = &event.type_;
+        if type_.address != DEEPBOOK_ADDRESS || type_.module.as_str() != "clob_v2" {
+            return;
+        }
+
+        let decoded = match type_.name.as_str() {
+            "OrderFilled" => bcs::from_bytes::<OrderFilled>(&event.contents).map(|fill| {
+                self.pool(fill.pool_id, type_)
+                    .fill(fill.base_asset_quantity_filled, fill.price)
+            }),
+            "OrderPlaced" => bcs::from_bytes::<OrderPlaced>(&event.contents).map(|order| {
+                let pool = self.pool(order.pool_id, type_);
+                match order.is_bid {
+                    true => pool.bids_placed += 1,
+                    false => pool.asks_placed += 1,
+                }
+            }),
+            _ => return,
+        };
+        if decoded.is_err() {
+            self.errors += 1;
+        }
+    }
+
+    fn finish(self: Box<Self>) -> AnalyzerReport {
+        let pools = self.sorted_pools();
+        let json = serde_json::json!({
+            "pools": pools,
+            "errors": self.errors,
+        });
+
+        let mut text = String::from("DeepBook pools:\n");
+        for pool in pools {
+            let price = |price: Option<f64>| price.map_or("-".to_string(), |p| format!("{p:.6}"));
+            let _ = writeln!(
+                text,
+                "{:<5} {} {}: base volume {}, quote volume {}, price {} (min {}, max {}, last {}), placed {} bids and {} asks",
+                pool.fills,
+                pool.pool_id,
+                pool.pair,
+                pool.base_volume,
+                pool.quote_volume,
+                price(pool.average_price),
+                price(pool.min_price),
+                price(pool.max_price),
+                price(pool.last_price),
+                pool.bids_placed,
+                pool.asks_placed,
+            );
+        }
+        if self.errors > 0 {
+            let _ = writeln!(text, "{} events could not be decoded", self.errors);
+        }
+        AnalyzerReport {
+            name: "deepbook",
+            json,
+            text: Box::new(text),
+            html: None,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+pub mod deepbook;
+pub mod system;
+pub mod watch;
+
+use anyhow::{bail, Result};
+
+use crate::analyzer::Analyzer;
+
+/// Names of the bundled presets, accepted by `preset`.
+pub const PRESETS: &[&str] = &["deepbook", "system"];
+
+/// The bundled preset called `name`: a built-in analysis of the events of a known protocol,
+/// which decodes the event types it knows about, ignoring all others.
+pub fn preset(name: &str) -> Result<Box<dyn Analyzer>> {
+    Ok(match name {
+        "deepbook" => Box::new(deepbook::DeepBookPreset::default()),
+        "system" => Box::new(system::SystemPreset::default()),
+        _ => bail!(
+            "Unknown preset `{name}`, expected one of: {}",
+            PRESETS.join(", ")
+        ),
+    })
+}
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, fmt::Write};
+
+use serde::{Deserialize, Serialize};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    SUI_SYSTEM_ADDRESS,
+};
+
+use crate::{
+    analyzer::{Analyzer, AnalyzerReport},
+    EventRecord,
+};
+
+/// Number of validators listed by staking activity
+const TOP_VALIDATORS: usize = 10;
+
+/// `0x3::sui_system_state_inner::SystemEpochInfoEvent`, emitted at every epoch change
+#[derive(Debug, Deserialize, Serialize)]
+struct SystemEpochInfoEvent {
+    epoch: u64,
+    protocol_version: u64,
+    reference_gas_price: u64,
+    total_stake: u64,
+    storage_fund_reinvestment: u64,
+    storage_charge: u64,
+    storage_rebate: u64,
+    storage_fund_balance: u64,
+    stake_subsidy_amount: u64,
+    total_gas_fees: u64,
+    total_stake_rewards_distributed: u64,
+    leftover_storage_fund_inflow: u64,
+}
+
+/// `0x3::validator_set::ValidatorJoinEvent`
+#[derive(Debug, Deserialize)]
+struct ValidatorJoinEvent {
+    epoch: u64,
+    validator_address: SuiAddress,
+    _staking_pool_id: ObjectID,
+}
+
+/// `0x3::validator_set::ValidatorLeaveEvent`
+#[derive(Debug, Deserialize)]
+struct ValidatorLeaveEvent {
+    epoch: u64,
+    validator_address: SuiAddress,
+    _staking_pool_id: ObjectID,
+    is_voluntary: bool,
+}
+
+/// `0x3::validator::StakingRequestEvent`
+#[derive(Debug, Deserialize)]
+struct StakingRequestEvent {
+    _pool_id: ObjectID,
+    validator_address: SuiAddress,
+    _staker_address: SuiAddress,
+    _epoch: u64,
+    amount: u64,
+}
+
+/// `0x3::validator::UnstakingRequestEvent`
+#[derive(Debug, Deserialize)]
+struct UnstakingRequestEvent {
+    _pool_id: ObjectID,
+    validator_address: SuiAddress,
+    _staker_address: SuiAddress,
+    _stake_activation_epoch: u64,
+    _unstaking_epoch: u64,
+    principal_amount: u64,
+    reward_amount: u64,
+}
+
+/// A validator joining or leaving the active set.
+#[derive(Debug, Serialize)]
+struct ValidatorChange {
+    epoch: u64,
+    validator: String,
+    /// `joined`, `left` or `removed`, when it left involuntarily
+    change: &'static str,
+}
+
+/// Staking requests of the delegators of a validator. Amounts are in MIST.
+#[derive(Debug, Default, Serialize)]
+struct ValidatorStaking {
+    validator: String,
+    stake_requests: u64,
+    staked: u128,
+    unstake_requests: u64,
+    unstaked: u128,
+    rewards_withdrawn: u128,
+}
+
+/// Epoch changes, validator set changes and staking requests, from the events of the Sui
+/// system package. The other events of the package are only counted.
+#[derive(Debug, Default)]
+pub struct SystemPreset {
+    epochs: Vec<SystemEpochInfoEvent>,
+    validator_changes: Vec<ValidatorChange>,
+    staking: HashMap<SuiAddress, ValidatorStaking>,
+    /// Other events of the system package, by `module::name`
+    others: HashMap<String, u64>,
+    /// Events of the known types that could not be decoded
+    errors: u64,
+}
+
+impl SystemPreset {
+    fn staking(&mut self, validator: SuiAddress) -> &mut ValidatorStaking {
+        self.staking
+            .entry(validator)
+            .or_insert_with(|| ValidatorStaking {
+                validator: validator.to_string(),
+                ..ValidatorStaking::default()
+            })
+    }
+
+    /// Validators by decreasing number of staking requests.
+    fn sorted_staking(&self) -> Vec<&ValidatorStaking> {
+        let mut validators: Vec<_> = self.staking.values().collect();
+        validators.sort_by(|a, b| {
+            (b.stake_requests + b.unstake_requests)
+                .cmp(&(a.stake_requests + a.unstake_requests))
+                .then(a.validator.cmp(&b.validator))
+        });
+        validators.truncate(TOP_VALIDATORS);
+        validators
+    }
+}
+
+impl Analyzer for SystemPreset {
+    fn name(&self) -> &'static str {
+        "system"
+    }
+
+    fn on_event(&mut self, (_index, _id, event): &EventRecord) {
+        let type_ = &event.type_;
+        if type_.address != SUI_SYSTEM_ADDRESS {
+            return;
+        }
+
+        let contents = &event.contents;
+        let decoded = match (type_.module.as_str(), type_.name.as_str()) {
+            ("sui_system_state_inner", "SystemEpochInfoEvent") => {
+                bcs::from_bytes::<SystemEpochInfoEvent>(contents).map(|info| self.epochs.push(info))
+            }
+            ("validator_set", "ValidatorJoinEvent") => {
+                bcs::from_bytes::<ValidatorJoinEvent>(contents).map(|join| {
+                    self.validator_changes.push(ValidatorChange {
+                        epoch: join.epoch,
+                        validator: join.validator_address.to_string(),
+                        change: "joined",
+                    })
+                })
+            }
+            ("validator_set", "ValidatorLeaveEvent") => {
+                bcs::from_bytes::<ValidatorLeaveEvent>(contents).map(|leave| {
+                    self.validator_changes.push(ValidatorChange {
+                        epoch: leave.epoch,
+                        validator: leave.validator_address.to_string(),
+                        change: if leave.is_voluntary {
+                            "left"
+                        } else {
+                            "removed"
+                        },
+                    })
+                })
+            }
+            ("validator", "StakingRequestEvent") => {
+                bcs::from_bytes::<StakingRequestEvent>(contents).map(|request| {
+                    let staking = self.staking(request.validator_address);
+                    staking.stake_requests += 1;
+                    staking.staked += request.amount as u128;
+                })
+            }
+            ("validator", "UnstakingRequestEvent") => {
+                bcs::from_bytes::<UnstakingRequestEvent>(contents).map(|request| {
+                    let staking = self.staking(request.validator_address);
+                    staking.unstake_requests += 1;
+                    staking.unstaked += request.principal_amount as u128;
+                    staking.rewards_withdrawn += request.reward_amount as u128;
+                })
+            }
+            (module, name) => {
+                *self.others.entry(format!("{module}::{name}")).or_default() += 1;
+                return;
+            }
+        };
+        if decoded.is_err() {
+            self.errors += 1;
+        }
+    }
+
+    fn finish(self: Box<Self>) -> AnalyzerReport {
+        let staking = self.sorted_staking();
+        let mut others: Vec<_> = self.others.iter().collect();
+        others.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let json = serde_json::json!({
+            "epochs": self.epochs,
+            "validator_changes": self.validator_changes,
+            "staking": staking,
+            "others": self.others,
+            "errors": self.errors,
+        });
+
+        let mut text = String::from("Epoch changes:\n");
+        for info in &self.epochs {
+            let _ = writeln!(
+                text,
+                "{:<6} protocol {}, gas price {}, total stake {}, gas fees {}, stake subsidy {}, rewards {}, storage fund {}",
+                info.epoch,
+                info.protocol_version,
+                info.reference_gas_price,
+                info.total_stake,
+                info.total_gas_fees,
+                info.stake_subsidy_amount,
+                info.total_stake_rewards_distributed,
+                info.storage_fund_balance,
+            );
+        }
+        if !self.validator_changes.is_empty() {
+            text.push_str("Validator set changes:\n");
+            for change in &self.validator_changes {
+                let _ = writeln!(
+                    text,
+                    "{:<6} {} {}",
+                    change.epoch, change.validator, change.change
+                );
+            }
+        }
+        if !staking.is_empty() {
+            text.push_str("Staking requests by validator:\n");
+            for validator in staking {
+                let _ = writeln!(
+                    text,
+                    "{}: staked {} in {} requests, unstaked {} and {} of rewards in {} requests",
+                    validator.validator,
+                    validator.staked,
+                    validator.stake_requests,
+                    validator.unstaked,
+                    validator.rewards_withdrawn,
+                    validator.unstake_requests,
+                );
+            }
+        }
+        if !others.is_empty() {
+            text.push_str("Other system events:\n");
+            for (type_, count) in others {
+                let _ = writeln!(text, "{count:<8} {type_}");
+            }
+        }
+        if self.errors > 0 {
+            let _ = writeln!(text, "{} events could not be decoded", self.errors);
+        }
+        AnalyzerReport {
+            name: "system",
+            json,
+            text: Box::new(text),
+            html: None,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{collections::HashMap, fmt::Write};
+
+use serde::Serialize;
+use sui_types::base_types::SuiAddress;
+
+use crate::{
+    analyzer::{Analyzer, AnalyzerReport},
+    filter::references,
+    report::type_to_short_string,
+    EventRecord,
+};
+
+/// An event of the watched address.
+#[derive(Debug, Serialize)]
+struct WatchedEvent {
+    checkpoint: u64,
+    timestamp: u64,
+    transaction: String,
+    event_sequence: u64,
+    type_: String,
+    short_type: String,
+    /// `sender`, `referenced` when the address only appears in its contents, or `both`
+    role: &'static str,
+}
+
+/// The events sent by an address or referencing it in their contents, in the order they were
+/// emitted, to trace the footprint of an account.
+#[derive(Debug)]
+pub struct WatchPreset {
+    address: SuiAddress,
+    events: Vec<WatchedEvent>,
+    /// Number of events by short type
+    by_type: HashMap<String, u64>,
+}
+
+impl WatchPreset {
+    pub fn new(address: SuiAddress) -> Self {
+        Self {
+            address,
+            events: Vec::new(),
+            by_type: HashMap::new(),
+        }
+    }
+}
+
+impl Analyzer for WatchPreset {
+    fn name(&self) -> &'static str {
+        "watch"
+    }
+
+    fn on_event(&mut self, (index, id, event): &EventRecord) {
+        let role = match (
+            event.sender == self.address,
+            references(event, self.address),
+        ) {
+            (true, true) => "both",
+            (true, false) => "sender",
+            (false, true) => "referenced",
+            (false, false) => return,
+        };
+
+        let short_type = type_to_short_string(&event.type_);
+        *self.by_type.entry(short_type.clone()).or_default() += 1;
+        self.events.push(WatchedEvent {
+            checkpoint: index.checkpoint_sequence_number,
+            timestamp: index.timestamp,
+            transaction: id.tx_digest.to_string(),
+            event_sequence: id.event_seq,
+            type_: event.type_.to_canonical_string(true),
+            short_type,
+            role,
+        });
+    }
+
+    fn finish(self: Box<Self>) -> AnalyzerReport {
+        let sent = self
+            .events
+            .iter()
+            .filter(|e| e.role != "referenced")
+            .count();
+        let mut by_type: Vec<_> = self.by_type.iter().collect();
+        by_type.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let json = serde_json::json!({
+            "address": self.address.to_string(),
+            "events": self.events,
+            "sent": sent,
+            "referenced": self.events.len() - sent,
+            "by_type": self.by_type,
+        });
+
+        let mut text = format!(
+            "Events of {}: {} sent, {} referencing it\n",
+            self.address,
+            sent,
+            self.events.len() - sent
+        );
+        for event in &self.events {
+            let _ = writeln!(
+                text,
+                "{:<10} {} {}:{} {:<10} {}",
+                event.checkpoint,
+                event.timestamp,
+                event.transaction,
+                event.event_sequence,
+                event.role,
+                event.short_type,
+            );
+        }
+        if !by_type.is_empty() {
+            text.push_str("Events by type:\n");
+            for (type_, count) in by_type {
+                let _ = writeln!(text, "{count:<8} {type_}");
+            }
+        }
+        AnalyzerReport {
+            name: "watch",
+            json,
+            text: Box::new(text),
+            html: None,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::{fmt, ops::RangeInclusive, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::sampling::Sampling;
+
+/// Watermark of a bounded run, persisted after every processed checkpoint so that
+/// an interrupted run can be resumed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Progress {
+    /// The next checkpoint to process
+    pub next_checkpoint: u64,
+    /// The last checkpoint of the run (inclusive)
+    pub end_checkpoint: u64,
+}
+
+impl Progress {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read progress file {}", path.display()))?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file and rename it, so a crash never leaves a torn file
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_checkpoint > self.end_checkpoint
+    }
+}
+
+/// Checkpoints missing from the ordered sequence received by a consumer, because the worker
+/// skipped them or the channel dropped them.
+#[derive(Debug, Clone, Default)]
+pub struct Gaps {
+    next: u64,
+    /// Inclusive ranges of missing checkpoints
+    ranges: Vec<(u64, u64)>,
+    /// Inclusive ranges of checkpoints between the requested ranges, never missing
+    between: Vec<(u64, u64)>,
+    /// The checkpoints of the run, if only a sample of them is processed
+    sampling: Option<Sampling>,
+}
+
+impl Gaps {
+    pub fn new(initial: u64) -> Self {
+        Self {
+            next: initial,
+            ranges: Vec::new(),
+            between: Vec::new(),
+            sampling: None,
+        }
+    }
+
+    /// Only count the checkpoints of `sampling` as missing.
+    pub fn sampled(mut self, sampling: Sampling) -> Self {
+        self.sampling = Some(sampling);
+        self
+    }
+
+    /// Gaps of a run over several ranges of checkpoints, sorted and not overlapping.
+    pub fn over_ranges(ranges: &[RangeInclusive<u64>]) -> Self {
+        Self {
+            between: ranges
+                .windows(2)
+                .filter(|pair| pair[0].end() + 1 < *pair[1].start())
+                .map(|pair| (pair[0].end() + 1, pair[1].start() - 1))
+                .collect(),
+            ..Self::new(ranges.first().map_or(0, |range| *range.start()))
+        }
+    }
+
+    /// Record the next received checkpoint.
+    pub fn observe(&mut self, sequence_number: u64) {
+        self.skip_to(sequence_number);
+        self.next = self.next.max(sequence_number + 1);
+    }
+
+    /// Record the end of a run that completed at `end`, the checkpoints after the last one
+    /// received being missing.
+    pub fn finish(&mut self, end: u64) {
+        self.skip_to(end.saturating_add(1));
+        self.next = self.next.max(end.saturating_add(1));
+    }
+
+    /// Record the checkpoints from the next expected one up to `sequence_number` excluded
+    /// as missing.
+    fn skip_to(&mut self, sequence_number: u64) {
+        if sequence_number > self.next {
+            // Only the checkpoints of the requested ranges can be missing
+            let mut start = self.next;
+            let mut missing = Vec::new();
+            for &(first, last) in &self.between {
+                if last < start || first >= sequence_number {
+                    continue;
+                }
+                if first > start {
+                    missing.push((start, first - 1));
+                }
+                start = last + 1;
+            }
+            for (first, last) in missing {
+                self.push_missing(first, last);
+            }
+            if start < sequence_number {
+                self.push_missing(start, sequence_number - 1);
+            }
+        }
+    }
+
+    /// Record the checkpoints `first..=last` as missing, those of the sample only if any.
+    fn push_missing(&mut self, first: u64, last: u64) {
+        let Some(sampling) = self.sampling else {
+            self.ranges.push((first, last));
+            return;
+        };
+        for sequence_number in (first..=last).filter(|&n| sampling.contains(n)) {
+            match self.ranges.last_mut() {
+                Some((_, end)) if *end + 1 == sequence_number => *end = sequence_number,
+                _ => self.ranges.push((sequence_number, sequence_number)),
+            }
+        }
+    }
+
+    pub fn ranges(&self) -> &[(u64, u64)] {
+        &self.ranges
+    }
+
+    /// Total number of missing checkpoints.
+    pub fn count(&self) -> u64 {
+        self.ranges.iter().map(|(start, end)| end - start + 1).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+impl fmt::Display for Gaps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (start, end)) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match start == end {
+                true => write!(f, "{start}")?,
+                false => write!(f, "{start}..={end}")?,
+            }
+        }
+        Ok(())
+    }
+}
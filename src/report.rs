@@ -0,0 +1,1522 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    ops::RangeInclusive,
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use clap::ValueEnum;
+use colored::Colorize;
+
+use move_core_types::{account_address::AccountAddress, language_storage::StructTag};
+use serde::Serialize;
+use statrs::statistics::{Data, Max, Median, Min, OrderStatistics, Statistics};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress, TransactionDigest},
+    event::Event,
+    messages_checkpoint::CertifiedCheckpointSummary,
+    TypeTag,
+};
+
+use crate::{
+    balances::BalanceChange,
+    labels::{named, Labels},
+    summaries::CheckpointSummaryRecord,
+    transactions::{MoveCallTarget, TransactionRecord},
+    EventRecord,
+};
+
+pub fn tag_to_short_string(tag_: &TypeTag) -> String {
+    match tag_ {
+        TypeTag::Struct(struct_tag) => type_to_short_string(struct_tag),
+        TypeTag::Vector(type_tag) => format!("Vector<{}>", tag_to_short_string(type_tag)),
+        _ => tag_.to_canonical_string(false),
+    }
+}
+
+pub fn type_to_short_string(type_: &StructTag) -> String {
+    let base = format!("{}::{}", type_.module, type_.name,);
+
+    if type_.type_params.is_empty() {
+        base
+    } else {
+        let type_params = type_
+            .type_params
+            .iter()
+            .map(tag_to_short_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}<{}>", base, type_params)
+    }
+}
+
+/// Number of transactions listed in the events by transaction without `top`
+const TOP_TRANSACTIONS: usize = 10;
+
+/// Options controlling what the event report contains.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Suppress addresses that account for less than this percentage of all events
+    pub suppress: f64,
+    /// Only keep the most frequent addresses, packages and types, instead of suppressing
+    pub top: Option<usize>,
+    /// Also report the number of events by sender
+    pub by_sender: bool,
+    /// Also report the number of calls of every Move function, see `EventHistogram::add_calls`
+    pub by_call: bool,
+    /// Also report the transactions that emitted the most events, see
+    /// `EventHistogram::with_transactions`
+    pub by_transaction: bool,
+    /// Also report the number of events by package in time buckets of this width
+    pub time_buckets: Option<BucketWidth>,
+    /// Also report the number of events by epoch, see `EventHistogram::add_checkpoint`
+    pub by_epoch: bool,
+    /// Sort the addresses, types and packages by the size of the contents of their events
+    /// rather than by their number of events
+    pub by_bytes: bool,
+    /// Group the types of every address by module, or only report the addresses, instead of
+    /// listing their types
+    pub depth: Option<Depth>,
+    /// Names replacing the addresses in the report
+    pub labels: Labels,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            suppress: 0.5,
+            top: None,
+            by_sender: false,
+            by_call: false,
+            by_transaction: false,
+            time_buckets: None,
+            by_epoch: false,
+            by_bytes: false,
+            depth: None,
+            labels: Labels::default(),
+        }
+    }
+}
+
+/// The granularity of the events by address: `package`, `module` or `type`. Without a
+/// depth, the types of every address are listed without grouping them by module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Depth {
+    /// Only the number of events of every address
+    Package,
+    /// The number of events of every module of the addresses
+    Module,
+    /// The number of events of every type, under its module
+    Type,
+}
+
+/// The width of the time buckets of a time series, written as a number followed by a
+/// unit, e.g. `30s`, `1m`, `1h` or `1d`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketWidth(pub Duration);
+
+impl FromStr for BucketWidth {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let width = parse_duration(s)?;
+        if width.subsec_nanos() != 0 {
+            bail!("The width of the time buckets must be a whole number of seconds");
+        }
+        Ok(Self(width))
+    }
+}
+
+/// Parse a positive duration made of a number and a unit, e.g. `500ms`, `30s`, `5m`, `1h` or `7d`.
+pub fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Missing unit in duration `{s}`"))?;
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value.parse()?;
+    let millis = match unit {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        _ => bail!("Unknown unit `{unit}`, expected `ms`, `s`, `m`, `h` or `d`"),
+    };
+    // Counted in milliseconds, like the timestamps of the checkpoints
+    let duration = value
+        .checked_mul(millis)
+        .map(Duration::from_millis)
+        .ok_or_else(|| anyhow!("The duration `{s}` is too long"))?;
+    if duration.is_zero() {
+        bail!("The duration must be positive");
+    }
+    Ok(duration)
+}
+
+/// Event counts by package, in consecutive time buckets.
+#[derive(Debug)]
+struct TimeBuckets {
+    width_ms: u64,
+    counts: BTreeMap<u64, HashMap<ObjectID, usize>>,
+}
+
+/// The sender and number of events by type of a transaction.
+type TransactionCounts = (SuiAddress, HashMap<StructTag, usize>);
+
+/// The transactions that emitted the most events. The events of a transaction are all in
+/// its checkpoint, so only the transactions of the current checkpoint are counted in full,
+/// and the others are merged into the `limit` first when the next checkpoint starts.
+#[derive(Debug)]
+struct TopTransactions {
+    limit: usize,
+    checkpoint: Option<u64>,
+    current: HashMap<TransactionDigest, TransactionCounts>,
+    /// Number of events, digest, sender and number of events by type, most events first
+    top: Vec<(usize, TransactionDigest, TransactionCounts)>,
+}
+
+impl TopTransactions {
+    fn add(&mut self, checkpoint: u64, digest: TransactionDigest, event: &Event) {
+        if self.checkpoint != Some(checkpoint) {
+            self.merge();
+            self.checkpoint = Some(checkpoint);
+        }
+        let (_, types) = self
+            .current
+            .entry(digest)
+            .or_insert_with(|| (event.sender, HashMap::new()));
+        *types.entry(event.type_.clone()).or_insert(0) += 1;
+    }
+
+    /// Merge the transactions of the current checkpoint into the top ones.
+    fn merge(&mut self) {
+        self.top.extend(
+            self.current
+                .drain()
+                .map(|(digest, counts)| (counts.1.values().sum(), digest, counts)),
+        );
+        self.top.sort_by(|a, b| b.0.cmp(&a.0));
+        self.top.truncate(self.limit);
+    }
+}
+
+/// Checkpoints and event counts by package of an epoch.
+#[derive(Debug)]
+struct EpochCounts {
+    first_checkpoint: u64,
+    last_checkpoint: u64,
+    checkpoints: u64,
+    counts: HashMap<ObjectID, usize>,
+}
+
+/// Histogram of events by the address defining their type, by emitting package and
+/// by sender.
+#[derive(Debug, Default)]
+pub struct EventHistogram {
+    by_address: HashMap<AccountAddress, (usize, HashMap<StructTag, usize>)>,
+    by_package: HashMap<ObjectID, usize>,
+    /// Size of the BCS contents of the events of every type and package
+    bytes_by_type: HashMap<StructTag, usize>,
+    bytes_by_package: HashMap<ObjectID, usize>,
+    by_sender: HashMap<SuiAddress, usize>,
+    by_call: HashMap<MoveCallTarget, usize>,
+    /// The transactions with the most events, if counted
+    by_transaction: Option<TopTransactions>,
+    time_buckets: Option<TimeBuckets>,
+    by_epoch: BTreeMap<u64, EpochCounts>,
+    /// Number of events of every checkpoint, including those without events once added
+    by_checkpoint: HashMap<u64, usize>,
+    /// The epoch of the last checkpoint, to which the added events belong
+    epoch: Option<u64>,
+}
+
+impl EventHistogram {
+    /// A histogram that also counts events by package in time buckets of the given
+    /// width, based on the timestamp of their checkpoint.
+    pub fn with_time_buckets(width: BucketWidth) -> Self {
+        Self {
+            time_buckets: Some(TimeBuckets {
+                width_ms: width.0.as_millis() as u64,
+                counts: BTreeMap::new(),
+            }),
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, (index, id, event): &EventRecord) {
+        let entry = self
+            .by_address
+            .entry(event.type_.address)
+            .or_insert((0, HashMap::new()));
+        entry.0 += 1;
+        let entry = entry.1.entry(event.type_.clone()).or_insert(0);
+        *entry += 1;
+
+        let count = self.by_package.entry(event.package_id).or_insert(0);
+        *count += 1;
+
+        let size = event.contents.len();
+        *self.bytes_by_type.entry(event.type_.clone()).or_insert(0) += size;
+        *self.bytes_by_package.entry(event.package_id).or_insert(0) += size;
+
+        *self.by_sender.entry(event.sender).or_insert(0) += 1;
+
+        if let Some(by_transaction) = &mut self.by_transaction {
+            by_transaction.add(index.checkpoint_sequence_number, id.tx_digest, event);
+        }
+
+        *self
+            .by_checkpoint
+            .entry(index.checkpoint_sequence_number)
+            .or_insert(0) += 1;
+
+        if let Some(epoch) = self.epoch.and_then(|epoch| self.by_epoch.get_mut(&epoch)) {
+            *epoch.counts.entry(event.package_id).or_insert(0) += 1;
+        }
+
+        if let Some(buckets) = &mut self.time_buckets {
+            let start = index.timestamp - index.timestamp % buckets.width_ms;
+            *buckets
+                .counts
+                .entry(start)
+                .or_default()
+                .entry(event.package_id)
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// Also count the events of every transaction, keeping the `top` transactions with the
+    /// most events, or the 10 first, for the events by transaction.
+    pub fn with_transactions(self, top: Option<usize>) -> Self {
+        Self {
+            by_transaction: Some(TopTransactions {
+                limit: top.unwrap_or(TOP_TRANSACTIONS),
+                checkpoint: None,
+                current: HashMap::new(),
+                top: Vec::new(),
+            }),
+            ..self
+        }
+    }
+
+    /// Start a new checkpoint, whose events are then counted in its epoch. Events added before
+    /// the first checkpoint are not counted by epoch.
+    pub fn add_checkpoint(&mut self, summary: &CertifiedCheckpointSummary) {
+        let sequence_number = summary.sequence_number;
+        let epoch = self
+            .by_epoch
+            .entry(summary.epoch)
+            .or_insert_with(|| EpochCounts {
+                first_checkpoint: sequence_number,
+                last_checkpoint: sequence_number,
+                checkpoints: 0,
+                counts: HashMap::new(),
+            });
+        epoch.first_checkpoint = epoch.first_checkpoint.min(sequence_number);
+        epoch.last_checkpoint = epoch.last_checkpoint.max(sequence_number);
+        epoch.checkpoints += 1;
+        self.epoch = Some(summary.epoch);
+        self.by_checkpoint.entry(sequence_number).or_insert(0);
+    }
+
+    /// Count the Move functions called by a transaction, whether or not it emitted events.
+    pub fn add_calls(&mut self, transaction: &TransactionRecord) {
+        for call in &transaction.calls {
+            *self.by_call.entry(call.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// Build the final report, suppressing addresses that account for less than
+    /// `suppress` percent of all events. If `top` is set, only the `top` most frequent
+    /// addresses, packages and senders, and the `top` most frequent types of each
+    /// address, are kept instead.
+    pub fn report(self, options: &ReportOptions) -> Report {
+        let ReportOptions {
+            suppress,
+            top,
+            by_sender,
+            by_call,
+            by_epoch,
+            by_bytes,
+            depth,
+            ..
+        } = *options;
+        let limit = top.unwrap_or(usize::MAX);
+
+        // Sort all entries in the histogram in descending order of value, or of size
+        let bytes_by_type = &self.bytes_by_type;
+        let type_bytes = |type_: &StructTag| bytes_by_type.get(type_).copied().unwrap_or(0);
+        let address_bytes =
+            |types: &HashMap<StructTag, usize>| -> usize { types.keys().map(type_bytes).sum() };
+        let mut histogram: Vec<_> = self.by_address.into_iter().collect();
+        if by_bytes {
+            histogram.sort_by_cached_key(|(_, (_, types))| std::cmp::Reverse(address_bytes(types)));
+        } else {
+            histogram.sort_by(|a, b| b.1 .0.cmp(&a.1 .0));
+        }
+        let total_bytes = self.bytes_by_type.values().sum();
+
+        // Sum all events
+        let total_events: usize = histogram.iter().map(|(_type_, value)| value.0).sum();
+        // Define the cutoff to suppress
+        let cutoff = match top {
+            Some(_) => 0,
+            None => (total_events as f64 * suppress / 100.0).round() as usize,
+        };
+
+        let addresses = histogram
+            .into_iter()
+            .filter(|(_address, value)| value.0 >= cutoff)
+            .take(limit)
+            .map(|(address, (count, types))| {
+                let bytes = address_bytes(&types);
+                let type_report = |(type_, count): (StructTag, usize)| TypeReport {
+                    type_: type_.to_canonical_string(true),
+                    short_type: type_to_short_string(&type_),
+                    count,
+                    bytes: Some(type_bytes(&type_)),
+                };
+                let sort_types = |types: &mut Vec<TypeReport>| {
+                    if by_bytes {
+                        types.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+                    } else {
+                        types.sort_by(|a, b| b.count.cmp(&a.count));
+                    }
+                    types.truncate(limit);
+                };
+
+                // The types are listed under their module when grouping by module
+                let (types, modules) = match depth {
+                    None => {
+                        let mut types: Vec<_> = types.into_iter().map(type_report).collect();
+                        sort_types(&mut types);
+                        (types, None)
+                    }
+                    Some(Depth::Package) => (Vec::new(), None),
+                    Some(depth) => {
+                        let mut by_module: BTreeMap<String, Vec<TypeReport>> = BTreeMap::new();
+                        for (type_, count) in types {
+                            let module = type_.module.to_string();
+                            by_module
+                                .entry(module)
+                                .or_default()
+                                .push(type_report((type_, count)));
+                        }
+                        let mut modules: Vec<_> = by_module
+                            .into_iter()
+                            .map(|(module, mut types)| {
+                                let count = types.iter().map(|type_| type_.count).sum();
+                                let bytes = types.iter().filter_map(|type_| type_.bytes).sum();
+                                sort_types(&mut types);
+                                if depth == Depth::Module {
+                                    types.clear();
+                                }
+                                ModuleReport {
+                                    module,
+                                    count,
+                                    bytes,
+                                    types,
+                                }
+                            })
+                            .collect();
+                        if by_bytes {
+                            modules.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+                        } else {
+                            modules.sort_by(|a, b| b.count.cmp(&a.count));
+                        }
+                        modules.truncate(limit);
+                        (Vec::new(), Some(modules))
+                    }
+                };
+
+                AddressReport {
+                    address: address.to_canonical_string(true),
+                    label: None,
+                    count,
+                    bytes,
+                    types,
+                    modules,
+                }
+            })
+            .collect();
+
+        let mut packages: Vec<_> = self
+            .by_package
+            .iter()
+            .map(|(package, count)| PackageReport {
+                package: package.to_string(),
+                label: None,
+                count: *count,
+                bytes: self.bytes_by_package.get(package).copied(),
+            })
+            .collect();
+        if by_bytes {
+            packages.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        } else {
+            packages.sort_by(|a, b| b.count.cmp(&a.count));
+        }
+        packages.truncate(limit);
+
+        let time_series = self.time_buckets.map(|buckets| TimeSeriesReport {
+            width_ms: buckets.width_ms,
+            buckets: buckets
+                .counts
+                .into_iter()
+                .map(|(start_ms, counts)| {
+                    let mut packages: Vec<_> = counts
+                        .into_iter()
+                        .map(|(package, count)| PackageReport {
+                            package: package.to_string(),
+                            label: None,
+                            count,
+                            bytes: None,
+                        })
+                        .collect();
+                    packages.sort_by(|a, b| b.count.cmp(&a.count));
+                    let total = packages.iter().map(|p| p.count).sum();
+                    packages.truncate(limit);
+
+                    TimeBucketReport {
+                        start_ms,
+                        total,
+                        packages,
+                    }
+                })
+                .collect(),
+        });
+
+        // The trend compares the event rate of each epoch to the previous one, since the
+        // first and last epochs of the range may be partial
+        let epochs = by_epoch.then(|| {
+            let mut previous_rate = None;
+            self.by_epoch
+                .into_iter()
+                .map(|(epoch, counts)| {
+                    let mut packages: Vec<_> = counts
+                        .counts
+                        .into_iter()
+                        .map(|(package, count)| PackageReport {
+                            package: package.to_string(),
+                            label: None,
+                            count,
+                            bytes: None,
+                        })
+                        .collect();
+                    packages.sort_by(|a, b| b.count.cmp(&a.count));
+                    let total = packages.iter().map(|p| p.count).sum::<usize>();
+                    packages.truncate(limit);
+
+                    let events_per_checkpoint = total as f64 / counts.checkpoints.max(1) as f64;
+                    let change_pct = previous_rate
+                        .filter(|&rate| rate > 0.0)
+                        .map(|rate| (events_per_checkpoint - rate) / rate * 100.0);
+                    previous_rate = Some(events_per_checkpoint);
+
+                    EpochReport {
+                        epoch,
+                        first_checkpoint: counts.first_checkpoint,
+                        last_checkpoint: counts.last_checkpoint,
+                        checkpoints: counts.checkpoints,
+                        total,
+                        events_per_checkpoint,
+                        change_pct,
+                        packages,
+                    }
+                })
+                .collect()
+        });
+
+        let senders = by_sender.then(|| {
+            let mut senders: Vec<_> = self
+                .by_sender
+                .iter()
+                .map(|(sender, count)| SenderReport {
+                    sender: sender.to_string(),
+                    label: None,
+                    count: *count,
+                })
+                .collect();
+            senders.sort_by(|a, b| b.count.cmp(&a.count));
+            senders.truncate(limit);
+            senders
+        });
+
+        let calls = by_call.then(|| {
+            let mut calls: Vec<_> = self
+                .by_call
+                .into_iter()
+                .map(|(target, count)| CallReport {
+                    package: target.package.to_string(),
+                    label: None,
+                    function: format!("{}::{}", target.module, target.function),
+                    count,
+                })
+                .collect();
+            calls.sort_by(|a, b| b.count.cmp(&a.count));
+            calls.truncate(limit);
+            calls
+        });
+
+        let transactions = self.by_transaction.map(|mut by_transaction| {
+            by_transaction.merge();
+            by_transaction
+                .top
+                .into_iter()
+                .map(|(count, digest, (sender, types))| {
+                    let mut types: Vec<_> = types
+                        .into_iter()
+                        .map(|(type_, count)| TypeReport {
+                            type_: type_.to_canonical_string(true),
+                            short_type: type_to_short_string(&type_),
+                            count,
+                            bytes: None,
+                        })
+                        .collect();
+                    types.sort_by(|a, b| b.count.cmp(&a.count));
+                    TransactionReport {
+                        digest: digest.to_string(),
+                        sender: sender.to_string(),
+                        label: None,
+                        count,
+                        types,
+                    }
+                })
+                .collect()
+        });
+
+        let total_packages = self.by_package.len();
+        let average_events = if total_packages == 0 {
+            0
+        } else {
+            self.by_package.values().sum::<usize>() / total_packages
+        };
+        let stdev_events = self
+            .by_package
+            .values()
+            .map(|&x| x as f64)
+            .collect::<Vec<_>>()
+            .std_dev();
+
+        Report {
+            total_events,
+            total_bytes,
+            by_bytes,
+            cutoff,
+            addresses,
+            packages,
+            senders,
+            calls,
+            transactions,
+            time_series,
+            epochs,
+            summary: PackageSummary {
+                total_packages,
+                average_events,
+                stdev_events,
+                per_package: CountDistribution::new(self.by_package.into_values()),
+                per_checkpoint: CountDistribution::new(self.by_checkpoint.into_values()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub total_events: usize,
+    /// Size of the BCS contents of all events
+    pub total_bytes: usize,
+    /// Whether the addresses, types and packages are sorted by size, which is then printed
+    #[serde(skip)]
+    pub by_bytes: bool,
+    pub cutoff: usize,
+    pub addresses: Vec<AddressReport>,
+    pub packages: Vec<PackageReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub senders: Option<Vec<SenderReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calls: Option<Vec<CallReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<Vec<TransactionReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_series: Option<TimeSeriesReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epochs: Option<Vec<EpochReport>>,
+    pub summary: PackageSummary,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.cutoff > 0 {
+            writeln!(
+                f,
+                "Suppressing packages with fewer than {} events",
+                self.cutoff
+            )?;
+        }
+
+        // The sizes are only printed when sorting by them
+        let size = |bytes: Option<usize>| match (self.by_bytes, bytes) {
+            (true, Some(bytes)) => format!(" ({bytes} bytes)"),
+            _ => String::new(),
+        };
+        for address in &self.addresses {
+            writeln!(
+                f,
+                "{:<5} {}{}",
+                address.count.to_string().blue(),
+                named(&address.address, &address.label).red(),
+                size(Some(address.bytes))
+            )?;
+
+            for type_ in &address.types {
+                writeln!(
+                    f,
+                    "       {:>5} : {}{}",
+                    type_.count.to_string().blue(),
+                    type_.short_type.green(),
+                    size(type_.bytes)
+                )?;
+            }
+            for module in address.modules.iter().flatten() {
+                writeln!(
+                    f,
+                    "       {:>5} : {}{}",
+                    module.count.to_string().blue(),
+                    module.module.yellow(),
+                    size(Some(module.bytes))
+                )?;
+                for type_ in &module.types {
+                    writeln!(
+                        f,
+                        "               {:>5} : {}{}",
+                        type_.count.to_string().blue(),
+                        type_.short_type.green(),
+                        size(type_.bytes)
+                    )?;
+                }
+            }
+        }
+
+        writeln!(f, "\nEvents by package:")?;
+        for package in &self.packages {
+            writeln!(
+                f,
+                "{:<5} {}{}",
+                named(&package.package, &package.label).blue(),
+                package.count,
+                size(package.bytes)
+            )?;
+        }
+        if let Some(senders) = &self.senders {
+            writeln!(f, "\nEvents by sender:")?;
+            for sender in senders {
+                writeln!(
+                    f,
+                    "{:<5} {}",
+                    sender.count.to_string().blue(),
+                    named(&sender.sender, &sender.label)
+                )?;
+            }
+        }
+
+        if let Some(calls) = &self.calls {
+            writeln!(f, "\nCalls by function:")?;
+            for call in calls {
+                writeln!(
+                    f,
+                    "{:<5} {}::{}",
+                    call.count.to_string().blue(),
+                    named(&call.package, &call.label),
+                    call.function
+                )?;
+            }
+        }
+
+        if let Some(transactions) = &self.transactions {
+            writeln!(f, "\nEvents by transaction:")?;
+            for transaction in transactions {
+                writeln!(
+                    f,
+                    "{:<5} {} by {}",
+                    transaction.count.to_string().blue(),
+                    transaction.digest,
+                    named(&transaction.sender, &transaction.label)
+                )?;
+                for type_ in &transaction.types {
+                    writeln!(
+                        f,
+                        "       {:>5} : {}",
+                        type_.count.to_string().blue(),
+                        type_.short_type.green()
+                    )?;
+                }
+            }
+        }
+
+        if let Some(time_series) = &self.time_series {
+            writeln!(f, "\nEvents per {}s:", time_series.width_ms / 1000)?;
+            for bucket in &time_series.buckets {
+                writeln!(
+                    f,
+                    "{:<5} {}",
+                    bucket.total.to_string().blue(),
+                    bucket.start_ms
+                )?;
+                for package in &bucket.packages {
+                    writeln!(
+                        f,
+                        "       {:>5} : {}",
+                        package.count.to_string().blue(),
+                        named(&package.package, &package.label)
+                    )?;
+                }
+            }
+        }
+
+        if let Some(epochs) = &self.epochs {
+            writeln!(f, "\nEvents by epoch:")?;
+            for epoch in epochs {
+                let change = epoch
+                    .change_pct
+                    .map(|change| format!(" ({change:+.1}%)"))
+                    .unwrap_or_default();
+                writeln!(
+                    f,
+                    "{:<5} epoch {} (checkpoints {}..={}), {:.2} per checkpoint{}",
+                    epoch.total.to_string().blue(),
+                    epoch.epoch,
+                    epoch.first_checkpoint,
+                    epoch.last_checkpoint,
+                    epoch.events_per_checkpoint,
+                    change
+                )?;
+                for package in &epoch.packages {
+                    writeln!(
+                        f,
+                        "       {:>5} : {}",
+                        package.count.to_string().blue(),
+                        named(&package.package, &package.label)
+                    )?;
+                }
+            }
+        }
+
+        let summary = &self.summary;
+        writeln!(
+            f,
+            "Summary: {} packages, with an average of {} +- {} events each",
+            summary.total_packages, summary.average_events, summary.stdev_events
+        )?;
+        if let Some(distribution) = &summary.per_package {
+            writeln!(f, "Events per package: {distribution}")?;
+        }
+        if let Some(distribution) = &summary.per_checkpoint {
+            writeln!(f, "Events per checkpoint: {distribution}")?;
+        }
+        writeln!(f, "Size of the event contents: {} bytes", self.total_bytes)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressReport {
+    pub address: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub count: usize,
+    pub bytes: usize,
+    /// Empty when grouping by module, the types being listed under their module
+    pub types: Vec<TypeReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modules: Option<Vec<ModuleReport>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleReport {
+    pub module: String,
+    pub count: usize,
+    pub bytes: usize,
+    /// Empty at the `module` depth
+    pub types: Vec<TypeReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TypeReport {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub short_type: String,
+    pub count: usize,
+    /// Size of the contents of the events, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageReport {
+    pub package: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub count: usize,
+    /// Size of the contents of the events, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SenderReport {
+    pub sender: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CallReport {
+    pub package: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// `module::function`
+    pub function: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionReport {
+    pub digest: String,
+    pub sender: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub count: usize,
+    pub types: Vec<TypeReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeSeriesReport {
+    pub width_ms: u64,
+    pub buckets: Vec<TimeBucketReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeBucketReport {
+    /// Timestamp of the start of the bucket, in milliseconds since the UNIX epoch
+    pub start_ms: u64,
+    pub total: usize,
+    pub packages: Vec<PackageReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EpochReport {
+    pub epoch: u64,
+    pub first_checkpoint: u64,
+    pub last_checkpoint: u64,
+    /// Number of processed checkpoints of the epoch
+    pub checkpoints: u64,
+    pub total: usize,
+    pub events_per_checkpoint: f64,
+    /// Change of `events_per_checkpoint` from the previous epoch, in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change_pct: Option<f64>,
+    pub packages: Vec<PackageReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageSummary {
+    pub total_packages: usize,
+    pub average_events: usize,
+    pub stdev_events: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_package: Option<CountDistribution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_checkpoint: Option<CountDistribution>,
+}
+
+/// The distribution of a number of events, e.g. per package, whose median and upper
+/// percentiles are more telling than the mean when a few values dominate.
+#[derive(Debug, Serialize)]
+pub struct CountDistribution {
+    pub count: usize,
+    pub min: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub stdev: f64,
+}
+
+impl CountDistribution {
+    /// The distribution of the values, if any.
+    pub fn new(values: impl IntoIterator<Item = usize>) -> Option<Self> {
+        let values: Vec<_> = values.into_iter().map(|x| x as f64).collect();
+        if values.is_empty() {
+            return None;
+        }
+        let (mean, stdev) = (values.iter().mean(), values.iter().std_dev());
+        let mut data = Data::new(values);
+        Some(Self {
+            count: data.len(),
+            min: data.min(),
+            median: data.median(),
+            p90: data.percentile(90),
+            p99: data.percentile(99),
+            max: data.max(),
+            mean,
+            // Undefined for a single value
+            stdev: if stdev.is_nan() { 0.0 } else { stdev },
+        })
+    }
+}
+
+impl fmt::Display for CountDistribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {}, median {}, p90 {}, p99 {}, max {}, mean {:.1} +- {:.1} (over {})",
+            self.min, self.median, self.p90, self.p99, self.max, self.mean, self.stdev, self.count
+        )
+    }
+}
+
+/// Number of owners listed for each coin type in the balance change report
+const TOP_OWNERS: usize = 10;
+
+/// Net balance changes by coin type and owner.
+#[derive(Debug, Default)]
+pub struct BalanceHistogram {
+    by_coin: HashMap<TypeTag, (usize, HashMap<SuiAddress, i128>)>,
+}
+
+impl BalanceHistogram {
+    pub fn add(&mut self, change: &BalanceChange) {
+        let entry = self
+            .by_coin
+            .entry(change.coin_type.clone())
+            .or_insert((0, HashMap::new()));
+        entry.0 += 1;
+        *entry.1.entry(change.owner).or_insert(0) += change.amount;
+    }
+
+    /// Build the final report, listing for each coin type the owners with the largest
+    /// absolute net change.
+    pub fn report(self) -> BalanceReport {
+        let mut coins: Vec<_> = self.by_coin.into_iter().collect();
+        coins.sort_by(|a, b| b.1 .0.cmp(&a.1 .0));
+
+        let coins = coins
+            .into_iter()
+            .map(|(coin_type, (changes, owners))| {
+                let inflow = owners.values().filter(|a| **a > 0).sum();
+                let outflow = owners.values().filter(|a| **a < 0).sum();
+
+                let mut owners: Vec<_> = owners.into_iter().collect();
+                owners.sort_by(|a, b| b.1.unsigned_abs().cmp(&a.1.unsigned_abs()));
+                owners.truncate(TOP_OWNERS);
+
+                CoinReport {
+                    coin_type: coin_type.to_canonical_string(true),
+                    short_type: tag_to_short_string(&coin_type),
+                    changes,
+                    inflow,
+                    outflow,
+                    owners: owners
+                        .into_iter()
+                        .map(|(owner, amount)| OwnerReport {
+                            owner: owner.to_string(),
+                            label: None,
+                            amount,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        BalanceReport { coins }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalanceReport {
+    pub coins: Vec<CoinReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoinReport {
+    pub coin_type: String,
+    pub short_type: String,
+    pub changes: usize,
+    pub inflow: i128,
+    pub outflow: i128,
+    pub owners: Vec<OwnerReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OwnerReport {
+    pub owner: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub amount: i128,
+}
+
+/// Flows of a single coin type: within each transaction, the balance increases not matched
+/// by decreases are minted, the decreases not matched by increases are burned, and the rest
+/// is transferred. Burns of SUI include the gas fees.
+#[derive(Debug)]
+pub struct CoinFlowHistogram {
+    coin_type: TypeTag,
+    transactions: usize,
+    minted: u128,
+    burned: u128,
+    transferred: u128,
+    sent: HashMap<SuiAddress, u128>,
+    received: HashMap<SuiAddress, u128>,
+}
+
+impl CoinFlowHistogram {
+    pub fn new(coin_type: TypeTag) -> Self {
+        Self {
+            coin_type,
+            transactions: 0,
+            minted: 0,
+            burned: 0,
+            transferred: 0,
+            sent: HashMap::new(),
+            received: HashMap::new(),
+        }
+    }
+
+    /// Add the balance changes of a checkpoint; changes of other coin types are ignored.
+    pub fn add_checkpoint(&mut self, changes: &[BalanceChange]) {
+        let mut transactions: HashMap<_, Vec<_>> = HashMap::new();
+        for change in changes.iter().filter(|c| c.coin_type == self.coin_type) {
+            transactions.entry(change.digest).or_default().push(change);
+        }
+
+        for changes in transactions.values() {
+            let (mut outflow, mut inflow) = (0, 0);
+            for change in changes {
+                let amount = change.amount.unsigned_abs();
+                if change.amount < 0 {
+                    outflow += amount;
+                    *self.sent.entry(change.owner).or_insert(0) += amount;
+                } else {
+                    inflow += amount;
+                    *self.received.entry(change.owner).or_insert(0) += amount;
+                }
+            }
+            self.transactions += 1;
+            self.transferred += inflow.min(outflow);
+            self.minted += inflow.saturating_sub(outflow);
+            self.burned += outflow.saturating_sub(inflow);
+        }
+    }
+
+    pub fn report(self) -> CoinFlowReport {
+        let top = |owners: HashMap<SuiAddress, u128>| {
+            let mut owners: Vec<_> = owners.into_iter().collect();
+            owners.sort_by(|a, b| b.1.cmp(&a.1));
+            owners.truncate(TOP_OWNERS);
+            owners
+                .into_iter()
+                .map(|(owner, amount)| FlowReport {
+                    owner: owner.to_string(),
+                    label: None,
+                    amount,
+                })
+                .collect()
+        };
+
+        CoinFlowReport {
+            coin_type: self.coin_type.to_canonical_string(true),
+            short_type: tag_to_short_string(&self.coin_type),
+            transactions: self.transactions,
+            minted: self.minted,
+            burned: self.burned,
+            transferred: self.transferred,
+            senders: top(self.sent),
+            recipients: top(self.received),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoinFlowReport {
+    pub coin_type: String,
+    pub short_type: String,
+    pub transactions: usize,
+    pub minted: u128,
+    pub burned: u128,
+    pub transferred: u128,
+    /// Owners whose balance decreased the most, summed over transactions
+    pub senders: Vec<FlowReport>,
+    /// Owners whose balance increased the most, summed over transactions
+    pub recipients: Vec<FlowReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlowReport {
+    pub owner: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub amount: u128,
+}
+
+/// Number of packages, and of functions of each package, listed in the gas report
+const TOP_GAS_PACKAGES: usize = 20;
+const TOP_GAS_FUNCTIONS: usize = 10;
+
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct GasCost {
+    pub computation_cost: u64,
+    pub storage_cost: u64,
+    pub storage_rebate: u64,
+}
+
+impl GasCost {
+    fn add(&mut self, other: &GasCost) {
+        self.computation_cost += other.computation_cost;
+        self.storage_cost += other.storage_cost;
+        self.storage_rebate += other.storage_rebate;
+    }
+
+    /// Split the cost in `n` shares, the first one taking the remainder.
+    fn split(&self, n: u64) -> (GasCost, GasCost) {
+        let share = GasCost {
+            computation_cost: self.computation_cost / n,
+            storage_cost: self.storage_cost / n,
+            storage_rebate: self.storage_rebate / n,
+        };
+        let first = GasCost {
+            computation_cost: share.computation_cost + self.computation_cost % n,
+            storage_cost: share.storage_cost + self.storage_cost % n,
+            storage_rebate: share.storage_rebate + self.storage_rebate % n,
+        };
+        (first, share)
+    }
+}
+
+/// Gas used by package and by entry function. The gas of a transaction calling several
+/// functions is split evenly between them; transactions without Move calls are counted
+/// apart.
+#[derive(Debug, Default)]
+pub struct GasHistogram {
+    total: GasCost,
+    transactions: usize,
+    other: GasCost,
+    by_package: HashMap<ObjectID, (usize, GasCost, HashMap<String, (usize, GasCost)>)>,
+}
+
+impl GasHistogram {
+    pub fn add(&mut self, transaction: &TransactionRecord) {
+        let gas = GasCost {
+            computation_cost: transaction.gas.computation_cost,
+            storage_cost: transaction.gas.storage_cost,
+            storage_rebate: transaction.gas.storage_rebate,
+        };
+        self.total.add(&gas);
+        self.transactions += 1;
+
+        if transaction.calls.is_empty() {
+            self.other.add(&gas);
+            return;
+        }
+
+        let (first, share) = gas.split(transaction.calls.len() as u64);
+        let mut packages = Vec::new();
+        for (i, call) in transaction.calls.iter().enumerate() {
+            let entry = self.by_package.entry(call.package).or_default();
+            // Count the transaction once per package it calls
+            if !packages.contains(&call.package) {
+                packages.push(call.package);
+                entry.0 += 1;
+            }
+            let cost = if i == 0 { &first } else { &share };
+            entry.1.add(cost);
+
+            let function = entry
+                .2
+                .entry(format!("{}::{}", call.module, call.function))
+                .or_default();
+            function.0 += 1;
+            function.1.add(cost);
+        }
+    }
+
+    /// Build the final report, listing the packages using the most computation gas.
+    pub fn report(self) -> GasReport {
+        let mut packages: Vec<_> = self.by_package.into_iter().collect();
+        packages.sort_by(|a, b| b.1 .1.computation_cost.cmp(&a.1 .1.computation_cost));
+        packages.truncate(TOP_GAS_PACKAGES);
+
+        let packages = packages
+            .into_iter()
+            .map(|(package, (transactions, gas, functions))| {
+                let mut functions: Vec<_> = functions.into_iter().collect();
+                functions.sort_by(|a, b| b.1 .1.computation_cost.cmp(&a.1 .1.computation_cost));
+                functions.truncate(TOP_GAS_FUNCTIONS);
+
+                GasPackageReport {
+                    package: package.to_string(),
+                    label: None,
+                    transactions,
+                    gas,
+                    functions: functions
+                        .into_iter()
+                        .map(|(function, (calls, gas))| GasFunctionReport {
+                            function,
+                            calls,
+                            gas,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        GasReport {
+            transactions: self.transactions,
+            total: self.total,
+            other: self.other,
+            packages,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GasReport {
+    pub transactions: usize,
+    pub total: GasCost,
+    /// Gas of the transactions without Move calls (transfers, system transactions, ...)
+    pub other: GasCost,
+    pub packages: Vec<GasPackageReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GasPackageReport {
+    pub package: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub transactions: usize,
+    pub gas: GasCost,
+    pub functions: Vec<GasFunctionReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GasFunctionReport {
+    /// `module::function`
+    pub function: String,
+    pub calls: usize,
+    pub gas: GasCost,
+}
+
+/// Totals of the summaries of a range of checkpoints.
+#[derive(Debug, Default)]
+pub struct CheckpointSummaryHistogram {
+    summaries: Vec<CheckpointSummaryRecord>,
+}
+
+impl CheckpointSummaryHistogram {
+    pub fn add(&mut self, summary: CheckpointSummaryRecord) {
+        self.summaries.push(summary);
+    }
+
+    pub fn report(mut self) -> CheckpointSummaryReport {
+        self.summaries
+            .sort_by_key(|summary| summary.sequence_number);
+        let mut gas = GasCost::default();
+        for summary in &self.summaries {
+            gas.add(&GasCost {
+                computation_cost: summary.gas.computation_cost,
+                storage_cost: summary.gas.storage_cost,
+                storage_rebate: summary.gas.storage_rebate,
+            });
+        }
+        let transactions = self.summaries.iter().map(|s| s.transactions).sum();
+        let mut epochs: Vec<_> = self.summaries.iter().map(|s| s.epoch).collect();
+        epochs.dedup();
+
+        let (first, last) = (self.summaries.first(), self.summaries.last());
+        let duration_ms = first
+            .zip(last)
+            .map_or(0, |(first, last)| last.timestamp - first.timestamp);
+        CheckpointSummaryReport {
+            checkpoints: self.summaries.len(),
+            epochs: epochs.len(),
+            transactions,
+            gas,
+            first_timestamp: first.map(|s| s.timestamp),
+            last_timestamp: last.map(|s| s.timestamp),
+            transactions_per_second: (duration_ms > 0)
+                .then(|| transactions as f64 * 1000.0 / duration_ms as f64),
+            busiest: self
+                .summaries
+                .iter()
+                .max_by_key(|s| s.transactions)
+                .map(|s| s.sequence_number),
+            summaries: self.summaries,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckpointSummaryReport {
+    pub checkpoints: usize,
+    pub epochs: usize,
+    pub transactions: u64,
+    pub gas: GasCost,
+    pub first_timestamp: Option<u64>,
+    pub last_timestamp: Option<u64>,
+    /// Between the timestamps of the first and last checkpoints
+    pub transactions_per_second: Option<f64>,
+    /// The checkpoint with the most transactions
+    pub busiest: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub summaries: Vec<CheckpointSummaryRecord>,
+}
+
+/// Event counts by type in two ranges of checkpoints, e.g. before and after an upgrade.
+#[derive(Debug)]
+pub struct EventDiff {
+    range_a: RangeInclusive<u64>,
+    range_b: RangeInclusive<u64>,
+    counts: HashMap<StructTag, (usize, usize)>,
+}
+
+impl EventDiff {
+    pub fn new(range_a: RangeInclusive<u64>, range_b: RangeInclusive<u64>) -> Self {
+        Self {
+            range_a,
+            range_b,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Count an event in the range of its checkpoint, if any.
+    pub fn add(&mut self, (index, _id, event): &EventRecord) {
+        let checkpoint = index.checkpoint_sequence_number;
+        let in_a = self.range_a.contains(&checkpoint);
+        if !in_a && !self.range_b.contains(&checkpoint) {
+            return;
+        }
+        let counts = self.counts.entry(event.type_.clone()).or_insert((0, 0));
+        match in_a {
+            true => counts.0 += 1,
+            false => counts.1 += 1,
+        }
+    }
+
+    /// Build the report, listing the types by decreasing absolute change, only the `top`
+    /// first if set.
+    pub fn report(self, top: Option<usize>) -> DiffReport {
+        let total_a = self.counts.values().map(|counts| counts.0).sum();
+        let total_b = self.counts.values().map(|counts| counts.1).sum();
+        let mut types: Vec<_> = self
+            .counts
+            .into_iter()
+            .map(|(type_, (count_a, count_b))| TypeDiff {
+                address: type_.address.to_canonical_string(true),
+                label: None,
+                type_: type_.to_canonical_string(true),
+                short_type: type_to_short_string(&type_),
+                count_a,
+                count_b,
+                change: count_b as i64 - count_a as i64,
+                change_pct: change_pct(count_a, count_b),
+            })
+            .collect();
+        types.sort_by(|a, b| {
+            b.change
+                .unsigned_abs()
+                .cmp(&a.change.unsigned_abs())
+                .then(a.type_.cmp(&b.type_))
+        });
+        types.truncate(top.unwrap_or(usize::MAX));
+
+        let range = |range: RangeInclusive<u64>| (*range.start(), *range.end());
+        DiffReport {
+            range_a: range(self.range_a),
+            range_b: range(self.range_b),
+            total_a,
+            total_b,
+            change_pct: change_pct(total_a, total_b),
+            types,
+        }
+    }
+}
+
+/// The relative change from `a` to `b`, unless `a` is zero.
+fn change_pct(a: usize, b: usize) -> Option<f64> {
+    (a > 0).then(|| (b as f64 - a as f64) / a as f64 * 100.0)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffReport {
+    /// First and last checkpoints of the ranges
+    pub range_a: (u64, u64),
+    pub range_b: (u64, u64),
+    pub total_a: usize,
+    pub total_b: usize,
+    pub change_pct: Option<f64>,
+    pub types: Vec<TypeDiff>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TypeDiff {
+    /// The address defining the type
+    pub address: String,
+    /// The name of the address, from the labels of the run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub short_type: String,
+    pub count_a: usize,
+    pub count_b: usize,
+    pub change: i64,
+    /// `None` for the types without events in the first range
+    pub change_pct: Option<f64>,
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pct = |change: Option<f64>| change.map_or("new".to_string(), |c| format!("{c:+.1}%"));
+        writeln!(
+            f,
+            "{} events in checkpoints {}..={}, {} in checkpoints {}..={} ({})",
+            self.total_a,
+            self.range_a.0,
+            self.range_a.1,
+            self.total_b,
+            self.range_b.0,
+            self.range_b.1,
+            pct(self.change_pct)
+        )?;
+        for type_ in &self.types {
+            let change = format!("{:<8}", format!("{:+}", type_.change));
+            let change = match type_.change {
+                0 => change.normal(),
+                c if c > 0 => change.green(),
+                _ => change.red(),
+            };
+            writeln!(
+                f,
+                "{} {:>8} -> {:<8} {:>8} {} ({})",
+                change,
+                type_.count_a,
+                type_.count_b,
+                pct(type_.change_pct),
+                type_.short_type,
+                named(&type_.address, &type_.label)
+            )?;
+        }
+        Ok(())
+    }
+}
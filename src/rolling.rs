@@ -0,0 +1,140 @@
+use std::{collections::HashMap, fmt};
+
+use colored::Colorize;
+use serde::Serialize;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{report::type_to_short_string, EventRecord};
+
+/// Number of types listed as the top movers of a window.
+const TOP_MOVERS: usize = 5;
+
+/// Summarizes the events of every `size` consecutive checkpoints, for followers running for
+/// days to report as they go rather than only when they stop.
+#[derive(Debug)]
+pub struct RollingWindow {
+    size: u64,
+    /// The first checkpoint of the current window
+    first: Option<u64>,
+    /// The timestamp the current window starts at, that of the last checkpoint of the
+    /// previous window, or of the first checkpoint of the run
+    start_timestamp: Option<u64>,
+    events: u64,
+    by_type: HashMap<String, u64>,
+    /// Number of events by type of the previous window
+    previous: HashMap<String, u64>,
+}
+
+/// The events of a window of checkpoints.
+#[derive(Debug, Serialize)]
+pub struct WindowSummary {
+    pub first_checkpoint: u64,
+    pub last_checkpoint: u64,
+    pub events: u64,
+    /// Events per second of checkpoint time
+    pub events_per_second: f64,
+    /// The types whose number of events changed the most since the previous window
+    pub movers: Vec<Mover>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Mover {
+    pub type_: String,
+    pub count: u64,
+    pub previous: u64,
+}
+
+impl RollingWindow {
+    pub fn new(size: u64) -> Self {
+        assert!(size > 0, "The window must hold at least one checkpoint");
+        Self {
+            size,
+            first: None,
+            start_timestamp: None,
+            events: 0,
+            by_type: HashMap::new(),
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Add the events of the next checkpoint, returning the summary of the window it
+    /// completes, if any.
+    pub fn observe(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Option<WindowSummary> {
+        let first = *self.first.get_or_insert(summary.sequence_number);
+        let start_timestamp = *self.start_timestamp.get_or_insert(summary.timestamp_ms);
+        self.events += events.len() as u64;
+        for (_, _, event) in events {
+            *self
+                .by_type
+                .entry(type_to_short_string(&event.type_))
+                .or_default() += 1;
+        }
+        if summary.sequence_number + 1 - first < self.size {
+            return None;
+        }
+
+        let seconds = summary.timestamp_ms.saturating_sub(start_timestamp) as f64 / 1000.0;
+        let by_type = std::mem::take(&mut self.by_type);
+        let mut movers: Vec<_> = by_type
+            .keys()
+            .chain(self.previous.keys().filter(|t| !by_type.contains_key(*t)))
+            .map(|type_| Mover {
+                type_: type_.clone(),
+                count: by_type.get(type_).copied().unwrap_or_default(),
+                previous: self.previous.get(type_).copied().unwrap_or_default(),
+            })
+            .filter(|mover| mover.count != mover.previous)
+            .collect();
+        movers.sort_by(|a, b| {
+            b.count
+                .abs_diff(b.previous)
+                .cmp(&a.count.abs_diff(a.previous))
+                .then(a.type_.cmp(&b.type_))
+        });
+        movers.truncate(TOP_MOVERS);
+
+        let window = WindowSummary {
+            first_checkpoint: first,
+            last_checkpoint: summary.sequence_number,
+            events: self.events,
+            events_per_second: match seconds > 0.0 {
+                true => self.events as f64 / seconds,
+                false => 0.0,
+            },
+            movers,
+        };
+        self.first = None;
+        self.start_timestamp = Some(summary.timestamp_ms);
+        self.events = 0;
+        self.previous = by_type;
+        Some(window)
+    }
+}
+
+impl fmt::Display for WindowSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} events, {:.1} events/s",
+            format!(
+                "Checkpoints {}..={}:",
+                self.first_checkpoint, self.last_checkpoint
+            )
+            .bold(),
+            self.events,
+            self.events_per_second
+        )?;
+        for mover in &self.movers {
+            let change = match mover.count >= mover.previous {
+                true => format!("+{}", mover.count - mover.previous).green(),
+                false => format!("-{}", mover.previous - mover.count).red(),
+            };
+            writeln!(f, "    {:<8} {:<8} {}", change, mover.count, mover.type_)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::HashMap, fmt::Write};
+
+use anyhow::{ensure, Result};
+use serde::Serialize;
+use statrs::distribution::{ContinuousCDF, StudentsT};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    analyzer::{Analyzer, AnalyzerReport},
+    report::type_to_short_string,
+    EventRecord,
+};
+
+/// Confidence level of the intervals of the estimates.
+const CONFIDENCE: f64 = 0.95;
+
+/// A random subset of the checkpoints, each kept with probability `fraction`. The choice is
+/// a hash of the sequence number and the `seed`, so that runs with the same seed process the
+/// same checkpoints, e.g. from the cache.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Sampling {
+    pub fraction: f64,
+    pub seed: u64,
+}
+
+impl Sampling {
+    pub fn new(fraction: f64, seed: u64) -> Result<Self> {
+        ensure!(
+            fraction > 0.0 && fraction <= 1.0,
+            "The fraction must be in (0, 1], got {fraction}"
+        );
+        Ok(Self { fraction, seed })
+    }
+
+    /// Whether checkpoint `sequence_number` is in the sample.
+    pub fn contains(&self, sequence_number: u64) -> bool {
+        // SplitMix64, whose 53 high bits are uniform in [0, 1)
+        let mut z = sequence_number ^ self.seed;
+        z = z.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        ((z >> 11) as f64 / (1u64 << 53) as f64) < self.fraction
+    }
+}
+
+/// The sums over the sampled checkpoints of the number of events of a type per checkpoint,
+/// and of its square.
+#[derive(Debug, Default, Clone, Copy)]
+struct Sums {
+    sum: f64,
+    squares: f64,
+}
+
+impl Sums {
+    fn add(&mut self, count: u64) {
+        self.sum += count as f64;
+        self.squares += (count * count) as f64;
+    }
+}
+
+/// The estimated number of events of a range of checkpoints, with its confidence interval.
+#[derive(Debug, Serialize)]
+struct Estimate {
+    /// Number of events of the sampled checkpoints
+    observed: u64,
+    estimate: f64,
+    low: f64,
+    high: f64,
+}
+
+/// Estimates the number of events of every type in the `population` checkpoints of a run
+/// from those of its sampled checkpoints. The events of every checkpoint being counted
+/// together, the estimate of a type is `population` times its mean count per sampled
+/// checkpoint, and its interval that of Student's t distribution with the finite population
+/// correction.
+#[derive(Debug)]
+pub struct SampleEstimator {
+    population: u64,
+    /// Number of sampled checkpoints processed
+    sampled: u64,
+    /// Number of events by type of the current checkpoint
+    current: HashMap<String, u64>,
+    by_type: HashMap<String, Sums>,
+    total: Sums,
+}
+
+impl SampleEstimator {
+    pub fn new(population: u64) -> Self {
+        Self {
+            population,
+            sampled: 0,
+            current: HashMap::new(),
+            by_type: HashMap::new(),
+            total: Sums::default(),
+        }
+    }
+
+    /// Add the counts of the current checkpoint to the sums.
+    fn flush(&mut self) {
+        let mut total = 0;
+        for (type_, count) in self.current.drain() {
+            self.by_type.entry(type_).or_default().add(count);
+            total += count;
+        }
+        self.total.add(total);
+    }
+
+    fn estimate(&self, sums: Sums, quantile: f64) -> Estimate {
+        let (n, population) = (self.sampled as f64, self.population as f64);
+        let mean = sums.sum / n;
+        let estimate = population * mean;
+        let error = match self.sampled {
+            0 | 1 => f64::NAN,
+            _ => {
+                let variance = ((sums.squares - n * mean * mean) / (n - 1.0)).max(0.0);
+                let correction = (1.0 - n / population).max(0.0);
+                quantile * population * (correction * variance / n).sqrt()
+            }
+        };
+        Estimate {
+            observed: sums.sum as u64,
+            estimate,
+            // There are at least as many events as observed
+            low: (estimate - error).max(sums.sum),
+            high: estimate + error,
+        }
+    }
+}
+
+impl Analyzer for SampleEstimator {
+    fn name(&self) -> &'static str {
+        "sample"
+    }
+
+    fn on_checkpoint(&mut self, _summary: &CertifiedCheckpointSummary) {
+        if self.sampled > 0 {
+            self.flush();
+        }
+        self.sampled += 1;
+    }
+
+    fn on_event(&mut self, (_, _, event): &EventRecord) {
+        *self
+            .current
+            .entry(type_to_short_string(&event.type_))
+            .or_default() += 1;
+    }
+
+    fn finish(mut self: Box<Self>) -> AnalyzerReport {
+        if self.sampled > 0 {
+            self.flush();
+        }
+        let quantile = match self.sampled {
+            0 | 1 => f64::NAN,
+            sampled => StudentsT::new(0.0, 1.0, (sampled - 1) as f64)
+                .map_or(f64::NAN, |t| t.inverse_cdf(0.5 + CONFIDENCE / 2.0)),
+        };
+        let total = self.estimate(self.total, quantile);
+        let mut by_type: Vec<_> = self
+            .by_type
+            .iter()
+            .map(|(type_, sums)| (type_.clone(), self.estimate(*sums, quantile)))
+            .collect();
+        by_type.sort_by(|a, b| b.1.estimate.total_cmp(&a.1.estimate).then(a.0.cmp(&b.0)));
+
+        let json = serde_json::json!({
+            "population": self.population,
+            "sampled": self.sampled,
+            "confidence": CONFIDENCE,
+            "events": total,
+            "by_type": by_type.iter().map(|(type_, estimate)| {
+                serde_json::json!({ "type": type_, "estimate": estimate })
+            }).collect::<Vec<_>>(),
+        });
+
+        let mut text = format!(
+            "Estimated from {} of {} checkpoints, with {:.0}% confidence intervals\n",
+            self.sampled,
+            self.population,
+            CONFIDENCE * 100.0
+        );
+        let _ = writeln!(text, "{} events", format_estimate(&total));
+        for (type_, estimate) in &by_type {
+            let _ = writeln!(text, "{:<40} {type_}", format_estimate(estimate));
+        }
+        AnalyzerReport {
+            name: "sample",
+            json,
+            text: Box::new(text),
+            html: None,
+        }
+    }
+}
+
+fn format_estimate(estimate: &Estimate) -> String {
+    match estimate.high.is_nan() {
+        true => format!("~{:.0}", estimate.estimate),
+        false => format!(
+            "~{:.0} [{:.0}, {:.0}]",
+            estimate.estimate, estimate.low, estimate.high
+        ),
+    }
+}
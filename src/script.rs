@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use wasmtime::{Caller, Config, Engine, Extern, Linker, Memory, Module, Store, TypedFunc};
+
+use crate::decode::DecodedEvent;
+
+/// Fuel given to the script for every event, and to instantiate it, roughly the number of
+/// WASM instructions it may run before it is stopped.
+const FUEL_PER_CALL: u64 = 100_000_000;
+
+/// A WASM module customizing the processing of the extracted events, without recompiling.
+///
+/// The module exports its `memory`, `alloc(len: i32) -> i32` returning the address of a
+/// buffer of `len` bytes, only used until the next call, and `on_event(ptr: i32, len: i32) -> i32`,
+/// called with every `DecodedEvent` as JSON and returning 0 to drop the event. While handling
+/// an event, it may call the imported `harvest.emit(ptr: i32, len: i32)` with a JSON value to
+/// emit it as a derived record. A call running out of fuel, e.g. looping, fails with a trap.
+pub struct ScriptHook {
+    /// Holds the records emitted by the current call
+    store: Store<Vec<Value>>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    on_event: TypedFunc<(i32, i32), i32>,
+}
+
+impl ScriptHook {
+    /// Compile and instantiate the module at `path`, in binary or text format.
+    pub fn load(path: &Path) -> Result<Self> {
+        let engine = Engine::new(Config::new().consume_fuel(true))?;
+        let module = Module::from_file(&engine, path)
+            .with_context(|| format!("Cannot load the script {}", path.display()))?;
+
+        let mut linker = Linker::new(&engine);
+        linker.func_wrap(
+            "harvest",
+            "emit",
+            |mut caller: Caller<'_, Vec<Value>>, ptr: i32, len: i32| -> Result<()> {
+                let memory = caller
+                    .get_export("memory")
+                    .and_then(Extern::into_memory)
+                    .ok_or_else(|| anyhow!("The script does not export its memory"))?;
+                let bytes = memory
+                    .data(&caller)
+                    .get(ptr as usize..)
+                    .and_then(|data| data.get(..len as usize))
+                    .ok_or_else(|| anyhow!("The script emitted a record out of its memory"))?;
+                let record = serde_json::from_slice(bytes)
+                    .context("The script emitted a record that is not valid JSON")?;
+                caller.data_mut().push(record);
+                Ok(())
+            },
+        )?;
+
+        let mut store = Store::new(&engine, Vec::new());
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = linker.instantiate(&mut store, &module)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("The script does not export its memory"))?;
+        let alloc = instance.get_typed_func(&mut store, "alloc")?;
+        let on_event = instance.get_typed_func(&mut store, "on_event")?;
+        Ok(Self {
+            store,
+            memory,
+            alloc,
+            on_event,
+        })
+    }
+
+    /// Run the script on `event`, returning whether to keep it and the records it emitted.
+    /// The records emitted before a failure are discarded.
+    pub fn on_event(&mut self, event: &DecodedEvent) -> Result<(bool, Vec<Value>)> {
+        self.store.set_fuel(FUEL_PER_CALL)?;
+        let result = self.call(event);
+        let derived = std::mem::take(self.store.data_mut());
+        Ok((result?, derived))
+    }
+
+    fn call(&mut self, event: &DecodedEvent) -> Result<bool> {
+        let input = serde_json::to_vec(event)?;
+        let len = i32::try_from(input.len())?;
+        let ptr = self.alloc.call(&mut self.store, len)?;
+        self.memory.write(&mut self.store, ptr as usize, &input)?;
+        let keep = self.on_event.call(&mut self.store, (ptr, len))?;
+        Ok(keep != 0)
+    }
+}
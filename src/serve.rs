@@ -0,0 +1,137 @@
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{stream, Future, Stream, StreamExt};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tonic::{
+    transport::{server::TcpIncoming, Server},
+    Request, Response, Status,
+};
+
+use crate::{filter::EventFilter, sinks::EventSink, EventRecord};
+
+pub mod proto {
+    tonic::include_proto!("harvest");
+}
+
+use proto::harvest_server::{Harvest, HarvestServer};
+
+impl From<&EventRecord> for proto::Event {
+    fn from((index, id, event): &EventRecord) -> Self {
+        Self {
+            checkpoint: index.checkpoint_sequence_number,
+            timestamp_ms: index.timestamp,
+            tx_digest: id.tx_digest.to_string(),
+            event_index: id.event_seq,
+            package: event.package_id.to_string(),
+            module: event.transaction_module.to_string(),
+            r#type: event.type_.to_canonical_string(true),
+            sender: event.sender.to_string(),
+            bcs: event.contents.clone(),
+        }
+    }
+}
+
+impl TryFrom<proto::EventFilter> for EventFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(filter: proto::EventFilter) -> Result<Self> {
+        Self::parse(&filter.packages, &filter.event_types, &filter.expression)
+    }
+}
+
+/// Relays the events of every processed checkpoint to the gRPC subscribers. Each subscriber
+/// sees the checkpoints processed after it subscribed, filtered by its own `EventFilter`.
+#[derive(Clone)]
+pub struct EventRelay {
+    sender: broadcast::Sender<Arc<Vec<EventRecord>>>,
+}
+
+impl EventRelay {
+    /// A subscriber falling behind by more than `capacity` checkpoints is disconnected.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// The sink publishing the extracted events to the subscribers.
+    pub fn sink(&self) -> RelaySink {
+        RelaySink {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Listen on `address`, returning the future serving the `Harvest` gRPC service until
+    /// the process exits.
+    pub fn bind(self, address: SocketAddr) -> Result<impl Future<Output = Result<()>>> {
+        let incoming = TcpIncoming::new(address, true, None)
+            .map_err(|e| anyhow!("Cannot listen on {address}: {e}"))?;
+        Ok(async move {
+            Server::builder()
+                .add_service(HarvestServer::new(self))
+                .serve_with_incoming(incoming)
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<proto::Event, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Harvest for EventRelay {
+    type SubscribeEventsStream = EventStream;
+
+    async fn subscribe_events(
+        &self,
+        request: Request<proto::EventFilter>,
+    ) -> Result<Response<EventStream>, Status> {
+        let filter = EventFilter::try_from(request.into_inner())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let checkpoints = stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            let item = match receiver.recv().await {
+                Ok(records) => Ok(records),
+                Err(RecvError::Lagged(missed)) => Err(Status::data_loss(format!(
+                    "The subscriber fell behind and missed {missed} checkpoints"
+                ))),
+                Err(RecvError::Closed) => return None,
+            };
+            Some((item, receiver))
+        });
+        let events = checkpoints.flat_map(move |item| {
+            let events: Vec<_> = match item {
+                Ok(records) => records
+                    .iter()
+                    .filter(|record| filter.matches(record))
+                    .map(|record| Ok(proto::Event::from(record)))
+                    .collect(),
+                Err(status) => vec![Err(status)],
+            };
+            stream::iter(events)
+        });
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+/// Publishes the events of every checkpoint to the subscribers of an `EventRelay`.
+pub struct RelaySink {
+    sender: broadcast::Sender<Arc<Vec<EventRecord>>>,
+}
+
+#[async_trait]
+impl EventSink for RelaySink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        // Sending only fails when nobody is subscribed, in which case the events are dropped
+        if !events.is_empty() {
+            let _ = self.sender.send(Arc::new(events.to_vec()));
+        }
+        Ok(())
+    }
+}
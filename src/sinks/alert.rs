@@ -0,0 +1,178 @@
+use std::{collections::VecDeque, fmt, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    filter::EventTypePattern,
+    sinks::{notify::NotificationSink, EventSink},
+    EventRecord,
+};
+
+/// A threshold on the rate of the events of the matching types, written e.g.
+/// `type=0xdee9::clob_v2::OrderFilled rate>100/min`. The rate is counted over a sliding
+/// window of its unit, `s`, `min` or `h`, in the time of the checkpoints.
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    /// The rule as written
+    pub text: String,
+    pub pattern: EventTypePattern,
+    /// Whether the rule fires above the threshold, or below it
+    pub above: bool,
+    /// Number of events in the window
+    pub threshold: u64,
+    pub window: Duration,
+}
+
+impl AlertRule {
+    fn is_crossed(&self, count: u64) -> bool {
+        match self.above {
+            true => count > self.threshold,
+            false => count < self.threshold,
+        }
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (mut pattern, mut rate) = (None, None);
+        for part in s.split_whitespace() {
+            if let Some(type_) = part.strip_prefix("type=") {
+                pattern = Some(type_.parse()?);
+            } else if let Some(threshold) = part.strip_prefix("rate") {
+                rate = Some(parse_rate(threshold)?);
+            } else {
+                bail!("Unknown alert condition `{part}`, expected `type=...` or `rate>N/unit`");
+            }
+        }
+        let (Some(pattern), Some((above, threshold, window))) = (pattern, rate) else {
+            bail!(
+                "Expected an alert of the form `type=address::module::name rate>N/unit`, got `{s}`"
+            );
+        };
+        Ok(Self {
+            text: s.to_string(),
+            pattern,
+            above,
+            threshold,
+            window,
+        })
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Parse `>N/unit` or `<N/unit` into the direction, the threshold and the window.
+fn parse_rate(s: &str) -> Result<(bool, u64, Duration)> {
+    let (above, rate) = match s.split_at_checked(1) {
+        Some((">", rate)) => (true, rate),
+        Some(("<", rate)) => (false, rate),
+        _ => bail!("Expected a rate of the form `>N/unit` or `<N/unit`, got `rate{s}`"),
+    };
+    let (threshold, unit) = rate
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Missing unit in rate `{rate}`, e.g. `100/min`"))?;
+    let window = match unit {
+        "s" => Duration::from_secs(1),
+        "min" => Duration::from_secs(60),
+        "h" => Duration::from_secs(60 * 60),
+        _ => bail!("Unknown rate unit `{unit}`, expected `s`, `min` or `h`"),
+    };
+    Ok((above, threshold.parse()?, window))
+}
+
+/// The events of a rule in its current window.
+struct RuleState {
+    rule: AlertRule,
+    /// Timestamp and number of matching events of the checkpoints in the window
+    checkpoints: VecDeque<(u64, u64)>,
+    count: u64,
+    /// Timestamp of the first checkpoint, the rule being evaluated once a whole window passed
+    start_ms: Option<u64>,
+    firing: bool,
+}
+
+/// Logs an alert when the rate of the events of a rule crosses its threshold, and again
+/// when it is back, optionally posting them to a Slack or Discord webhook, whose failures
+/// are logged once retried. Only the events kept by the filters of the run are counted.
+pub struct AlertSink {
+    rules: Vec<RuleState>,
+    notifier: Option<NotificationSink>,
+}
+
+impl AlertSink {
+    pub fn new(rules: Vec<AlertRule>, notifier: Option<NotificationSink>) -> Self {
+        let rules = rules
+            .into_iter()
+            .map(|rule| RuleState {
+                rule,
+                checkpoints: VecDeque::new(),
+                count: 0,
+                start_ms: None,
+                firing: false,
+            })
+            .collect();
+        Self { rules, notifier }
+    }
+}
+
+#[async_trait]
+impl EventSink for AlertSink {
+    async fn process(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let now_ms = summary.timestamp_ms;
+        let mut messages = Vec::new();
+        for state in &mut self.rules {
+            let matching = events
+                .iter()
+                .filter(|(_, _, event)| state.rule.pattern.matches(&event.type_))
+                .count() as u64;
+            state.checkpoints.push_back((now_ms, matching));
+            state.count += matching;
+
+            let window_ms = state.rule.window.as_millis() as u64;
+            while let Some(&(timestamp_ms, count)) = state.checkpoints.front() {
+                if timestamp_ms + window_ms > now_ms {
+                    break;
+                }
+                state.checkpoints.pop_front();
+                state.count -= count;
+            }
+            let start_ms = *state.start_ms.get_or_insert(now_ms);
+            if now_ms < start_ms + window_ms {
+                continue;
+            }
+
+            let crossed = state.rule.is_crossed(state.count);
+            if crossed != state.firing {
+                state.firing = crossed;
+                let status = if crossed { "ALERT" } else { "RESOLVED" };
+                messages.push(format!(
+                    "{status} `{}`: {} events in the last {:?} up to checkpoint {}",
+                    state.rule, state.count, state.rule.window, summary.sequence_number
+                ));
+            }
+        }
+
+        for message in messages {
+            tracing::warn!("{message}");
+            if let Some(notifier) = &self.notifier {
+                // An outage of the webhook does not stop the run
+                if let Err(e) = notifier.send(&message).await {
+                    tracing::error!("Cannot post the alert: {e:#}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
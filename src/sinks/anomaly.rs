@@ -0,0 +1,147 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use statrs::{
+    distribution::{ContinuousCDF, Normal},
+    statistics::Statistics,
+};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{metrics::HarvestMetrics, report::type_to_short_string, sinks::EventSink, EventRecord};
+
+/// Minimum number of past intervals of a type before its rate is checked.
+const MIN_HISTORY: usize = 5;
+
+/// How rates are compared with their recent past.
+#[derive(Debug, Clone, Copy)]
+pub struct AnomalyOptions {
+    /// Width of the intervals whose event counts are compared
+    pub interval: Duration,
+    /// Number of past intervals the mean and standard deviation are computed over
+    pub window: usize,
+    /// Number of standard deviations away from the mean flagged as an anomaly
+    pub threshold: f64,
+}
+
+/// The past event counts of a type.
+#[derive(Default)]
+struct TypeHistory {
+    counts: VecDeque<f64>,
+    /// Whether the type is currently flagged
+    anomalous: bool,
+}
+
+/// Flags the spikes and droughts of the rate of every event type, comparing its number of
+/// events in each interval, in the time of the checkpoints, with the mean and standard
+/// deviation of the previous intervals. The deviation is at least that of a Poisson process
+/// of the same mean, so that rare types are not flagged for a single event.
+pub struct AnomalySink {
+    options: AnomalyOptions,
+    histories: HashMap<String, TypeHistory>,
+    /// The current interval, and the event counts of its types so far
+    current: Option<(u64, HashMap<String, u64>)>,
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    metrics: Option<HarvestMetrics>,
+}
+
+impl AnomalySink {
+    pub fn new(options: AnomalyOptions, metrics: Option<HarvestMetrics>) -> Self {
+        Self {
+            options,
+            histories: HashMap::new(),
+            current: None,
+            metrics,
+        }
+    }
+
+    /// Compare the counts of a complete interval with the histories of their types.
+    fn close_interval(&mut self, counts: &HashMap<String, u64>, checkpoint: u64) {
+        let normal = Normal::new(0.0, 1.0).expect("Valid standard normal distribution");
+        for type_ in counts.keys() {
+            self.histories.entry(type_.clone()).or_default();
+        }
+        for (type_, history) in &mut self.histories {
+            let count = counts.get(type_).copied().unwrap_or(0) as f64;
+            if history.counts.len() >= MIN_HISTORY {
+                let mean = history.counts.iter().mean();
+                let deviation = history.counts.iter().std_dev().max(mean.sqrt());
+                let score = if deviation > 0.0 {
+                    (count - mean) / deviation
+                } else {
+                    0.0
+                };
+
+                let anomalous = score.abs() > self.options.threshold;
+                if anomalous && !history.anomalous {
+                    let kind = if score > 0.0 { "spike" } else { "drought" };
+                    let p_value = 2.0 * (1.0 - normal.cdf(score.abs()));
+                    tracing::warn!(
+                        "Anomaly: {kind} of `{type_}`, {count} events in the {:?} up to \
+                        checkpoint {checkpoint}, expected {mean:.1} ± {deviation:.1} \
+                        (z = {score:.1}, p = {p_value:.1e})",
+                        self.options.interval
+                    );
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = &self.metrics {
+                        metrics
+                            .anomalies
+                            .with_label_values(&[type_.as_str(), kind])
+                            .inc();
+                    }
+                } else if !anomalous && history.anomalous {
+                    tracing::info!("Anomaly over: `{type_}` is back to {count} events");
+                }
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    if anomalous || history.anomalous {
+                        metrics
+                            .anomaly_score
+                            .with_label_values(&[type_.as_str()])
+                            .set(if anomalous { score } else { 0.0 });
+                    }
+                }
+                history.anomalous = anomalous;
+            }
+
+            history.counts.push_back(count);
+            if history.counts.len() > self.options.window {
+                history.counts.pop_front();
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for AnomalySink {
+    async fn process(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let interval_ms = self.options.interval.as_millis().max(1) as u64;
+        let interval = summary.timestamp_ms / interval_ms;
+
+        // Close the finished interval, and the empty ones since, up to a whole window
+        if let Some((start, counts)) = self.current.take_if(|(start, _)| *start < interval) {
+            self.close_interval(&counts, summary.sequence_number);
+            let empty = (interval - start - 1).min(self.options.window as u64);
+            for _ in 0..empty {
+                self.close_interval(&HashMap::new(), summary.sequence_number);
+            }
+        }
+
+        let (_, counts) = self
+            .current
+            .get_or_insert_with(|| (interval, HashMap::new()));
+        for (_, _, event) in events {
+            *counts
+                .entry(type_to_short_string(&event.type_))
+                .or_default() += 1;
+        }
+        Ok(())
+    }
+}
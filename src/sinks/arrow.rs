@@ -0,0 +1,137 @@
+use std::{fs::File, io::BufWriter, path::Path, sync::Arc};
+
+use anyhow::Result;
+use arrow_array::{ArrayRef, BinaryArray, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{sinks::EventSink, EventRecord};
+
+/// The schema of the event batches, shared by the Arrow and Parquet outputs and kept stable
+/// across versions.
+pub fn event_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("checkpoint", DataType::UInt64, false),
+        Field::new("timestamp_ms", DataType::UInt64, false),
+        Field::new("tx_digest", DataType::Utf8, false),
+        Field::new("event_index", DataType::UInt64, false),
+        Field::new("package", DataType::Utf8, false),
+        Field::new("module", DataType::Utf8, false),
+        Field::new("type", DataType::Utf8, false),
+        Field::new("sender", DataType::Utf8, false),
+        Field::new("bcs", DataType::Binary, false),
+    ]))
+}
+
+/// The events as a single `RecordBatch` of `event_schema`.
+pub fn event_batch(events: &[EventRecord]) -> Result<RecordBatch> {
+    let mut columns = EventColumns::default();
+    events.iter().for_each(|record| columns.push(record));
+    columns.take_batch()
+}
+
+/// Columns of the events, buffered until the next batch is built.
+#[derive(Default)]
+pub struct EventColumns {
+    checkpoint: Vec<u64>,
+    timestamp_ms: Vec<u64>,
+    tx_digest: Vec<String>,
+    event_index: Vec<u64>,
+    package: Vec<String>,
+    module: Vec<String>,
+    type_: Vec<String>,
+    sender: Vec<String>,
+    bcs: Vec<Vec<u8>>,
+}
+
+impl EventColumns {
+    pub fn push(&mut self, (index, id, event): &EventRecord) {
+        self.checkpoint.push(index.checkpoint_sequence_number);
+        self.timestamp_ms.push(index.timestamp);
+        self.tx_digest.push(id.tx_digest.to_string());
+        self.event_index.push(id.event_seq);
+        self.package.push(event.package_id.to_string());
+        self.module.push(event.transaction_module.to_string());
+        self.type_.push(event.type_.to_canonical_string(true));
+        self.sender.push(event.sender.to_string());
+        self.bcs.push(event.contents.clone());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checkpoint.is_empty()
+    }
+
+    /// Build a batch of the buffered events, leaving the columns empty.
+    pub fn take_batch(&mut self) -> Result<RecordBatch> {
+        let columns = std::mem::take(self);
+        let arrays: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(columns.checkpoint)),
+            Arc::new(UInt64Array::from(columns.timestamp_ms)),
+            Arc::new(StringArray::from(columns.tx_digest)),
+            Arc::new(UInt64Array::from(columns.event_index)),
+            Arc::new(StringArray::from(columns.package)),
+            Arc::new(StringArray::from(columns.module)),
+            Arc::new(StringArray::from(columns.type_)),
+            Arc::new(StringArray::from(columns.sender)),
+            Arc::new(BinaryArray::from_iter_values(columns.bcs.iter())),
+        ];
+        Ok(RecordBatch::try_new(event_schema(), arrays)?)
+    }
+}
+
+/// Writes events to a file in the Arrow IPC file format, a batch for every
+/// `checkpoints_per_batch` consecutive checkpoints. Readers such as Polars or PyArrow can map
+/// the file in memory instead of parsing it.
+pub struct ArrowSink {
+    writer: FileWriter<BufWriter<File>>,
+    checkpoints_per_batch: u64,
+    first_checkpoint: Option<u64>,
+    columns: EventColumns,
+}
+
+impl ArrowSink {
+    pub fn new(path: &Path, checkpoints_per_batch: u64) -> Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Self {
+            writer: FileWriter::try_new(file, &event_schema())?,
+            checkpoints_per_batch,
+            first_checkpoint: None,
+            columns: EventColumns::default(),
+        })
+    }
+
+    fn write_batch(&mut self) -> Result<()> {
+        self.first_checkpoint = None;
+        if self.columns.is_empty() {
+            return Ok(());
+        }
+        let batch = self.columns.take_batch()?;
+        self.writer.write(&batch)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for ArrowSink {
+    async fn process(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let first = *self.first_checkpoint.get_or_insert(summary.sequence_number);
+        events.iter().for_each(|record| self.columns.push(record));
+
+        if summary.sequence_number + 1 - first >= self.checkpoints_per_batch {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.write_batch()?;
+        self.writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use gcp_bigquery_client::{
+    error::BQError,
+    google::cloud::bigquery::storage::v1::append_rows_response::Response,
+    model::{
+        clustering::Clustering, table::Table, table_field_schema::TableFieldSchema,
+        table_schema::TableSchema,
+    },
+    storage::{ColumnType, FieldDescriptor, StreamName, TableDescriptor},
+    Client,
+};
+use prost::Message;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    fetcher::RetryPolicy,
+    sinks::{event_key, EventSink},
+    EventRecord,
+};
+
+/// Number of checkpoints buffered before their events are appended
+const BATCH_SIZE: usize = 1000;
+
+/// Largest size of the rows appended in a request, below its 10 MB limit
+const MAX_BATCH_BYTES: usize = 5_000_000;
+
+/// The columns of the events table, in the order of the fields of `EventRow`.
+const COLUMNS: [(&str, ColumnType); 10] = [
+    ("id", ColumnType::String),
+    ("checkpoint", ColumnType::Int64),
+    ("timestamp_ms", ColumnType::Int64),
+    ("tx_digest", ColumnType::String),
+    ("event_index", ColumnType::Int64),
+    ("package", ColumnType::String),
+    ("module", ColumnType::String),
+    ("type", ColumnType::String),
+    ("sender", ColumnType::String),
+    ("bcs", ColumnType::String),
+];
+
+/// An event, encoded as the protocol buffer message of a row of the events table.
+#[derive(Clone, PartialEq, Message)]
+struct EventRow {
+    /// The `event_key` of the event, for deduplicating the rows of overlapping runs
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(int64, tag = "2")]
+    checkpoint: i64,
+    #[prost(int64, tag = "3")]
+    timestamp_ms: i64,
+    #[prost(string, tag = "4")]
+    tx_digest: String,
+    #[prost(int64, tag = "5")]
+    event_index: i64,
+    #[prost(string, tag = "6")]
+    package: String,
+    #[prost(string, tag = "7")]
+    module: String,
+    #[prost(string, tag = "8")]
+    type_: String,
+    #[prost(string, tag = "9")]
+    sender: String,
+    /// Hex encoded BCS contents of the event
+    #[prost(string, tag = "10")]
+    bcs: String,
+}
+
+impl From<&EventRecord> for EventRow {
+    fn from(record: &EventRecord) -> Self {
+        let (index, id, event) = record;
+        Self {
+            id: event_key(record),
+            checkpoint: index.checkpoint_sequence_number as i64,
+            timestamp_ms: index.timestamp as i64,
+            tx_digest: id.tx_digest.to_string(),
+            event_index: id.event_seq as i64,
+            package: event.package_id.to_string(),
+            module: event.transaction_module.to_string(),
+            type_: event.type_.to_canonical_string(true),
+            sender: event.sender.to_string(),
+            bcs: hex::encode(&event.contents),
+        }
+    }
+}
+
+/// Streams the events into a BigQuery table through the default stream of the Storage Write
+/// API, creating the table, clustered by package and type, if it does not exist. The default
+/// stream is at least once, so a batch retried after a timeout, or written again by an
+/// overlapping run, is duplicated; deduplicate the rows on `id` when querying them.
+pub struct BigQuerySink {
+    client: Client,
+    stream: StreamName,
+    descriptor: TableDescriptor,
+    retry_policy: RetryPolicy,
+    checkpoints: usize,
+    events: Vec<EventRow>,
+    bytes: usize,
+}
+
+impl BigQuerySink {
+    /// Connect to the table `project.dataset.table`, authenticating with the service account
+    /// key file if given, and with the application default credentials otherwise.
+    pub async fn connect(
+        table: &str,
+        key_file: Option<&str>,
+        retry_policy: RetryPolicy,
+    ) -> Result<Self> {
+        let [project, dataset, table] = table.split('.').collect::<Vec<_>>()[..] else {
+            bail!("Expected a BigQuery table of the form `project.dataset.table`, got `{table}`");
+        };
+        let client = match key_file {
+            Some(path) => Client::from_service_account_key_file(path).await?,
+            None => Client::from_application_default_credentials().await?,
+        };
+
+        match client.table().get(project, dataset, table, None).await {
+            Ok(_) => (),
+            Err(BQError::ResponseError { error }) if error.error.code == 404 => {
+                let schema = TableSchema::new(
+                    COLUMNS
+                        .iter()
+                        .map(|(name, typ)| match typ {
+                            ColumnType::Int64 => TableFieldSchema::integer(name),
+                            _ => TableFieldSchema::string(name),
+                        })
+                        .collect(),
+                );
+                let clustering = Clustering {
+                    fields: Some(vec!["package".to_string(), "type".to_string()]),
+                };
+                let definition = Table::new(project, dataset, table, schema).clustering(clustering);
+                client.table().create(definition).await?;
+                tracing::info!("Created the BigQuery table {project}.{dataset}.{table}");
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        let descriptor = TableDescriptor {
+            field_descriptors: COLUMNS
+                .iter()
+                .zip(1..)
+                .map(|((name, typ), number)| FieldDescriptor {
+                    number,
+                    name: name.to_string(),
+                    typ: *typ,
+                })
+                .collect(),
+        };
+        Ok(Self {
+            client,
+            stream: StreamName::new_default(project.into(), dataset.into(), table.into()),
+            descriptor,
+            retry_policy,
+            checkpoints: 0,
+            events: Vec::new(),
+            bytes: 0,
+        })
+    }
+
+    /// Append the rows in a single request.
+    async fn append(&self) -> Result<()> {
+        // The clients share their connection, the storage API only needs one of its own
+        let mut client = self.client.clone();
+        let mut responses = client
+            .storage_mut()
+            .append_rows(
+                &self.stream,
+                &self.descriptor,
+                &self.events,
+                "sui-harvest".to_string(),
+            )
+            .await?;
+        while let Some(response) = responses.next().await {
+            let response = response?;
+            if let Some(Response::Error(status)) = response.response {
+                bail!("{} (code {})", status.message, status.code);
+            }
+            if let Some(error) = response.row_errors.first() {
+                bail!("Row {} rejected: {}", error.index, error.message);
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the buffered rows, retrying with backoff.
+    async fn flush(&mut self) -> Result<()> {
+        if self.events.is_empty() {
+            return Ok(());
+        }
+        self.retry_policy
+            .retry_with_backoff("append the rows to BigQuery", || self.append())
+            .await
+            .with_context(|| {
+                format!(
+                    "BigQuery append failed after {} retries",
+                    self.retry_policy.max_retries
+                )
+            })?;
+        self.events.clear();
+        self.checkpoints = 0;
+        self.bytes = 0;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for BigQuerySink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        for row in events.iter().map(EventRow::from) {
+            // Append the buffered rows before they would exceed the size of a request
+            let size = row.encoded_len();
+            if self.bytes + size > MAX_BATCH_BYTES {
+                self.flush().await?;
+            }
+            self.bytes += size;
+            self.events.push(row);
+        }
+        self.checkpoints += 1;
+
+        if self.checkpoints >= BATCH_SIZE {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.flush().await
+    }
+}
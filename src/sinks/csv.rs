@@ -0,0 +1,55 @@
+use std::{fs::File, path::Path};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{sinks::EventSink, EventRecord};
+
+/// Writes every event as a row of a CSV file.
+pub struct CsvSink {
+    writer: ::csv::Writer<File>,
+}
+
+impl CsvSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut writer = ::csv::Writer::from_path(path)?;
+        writer.write_record([
+            "checkpoint",
+            "tx_digest",
+            "event_index",
+            "package",
+            "type",
+            "sender",
+            "bcs",
+        ])?;
+        Ok(Self { writer })
+    }
+}
+
+#[async_trait]
+impl EventSink for CsvSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        for (index, id, event) in events {
+            self.writer.write_record([
+                index.checkpoint_sequence_number.to_string(),
+                id.tx_digest.to_string(),
+                id.event_seq.to_string(),
+                event.package_id.to_string(),
+                event.type_.to_canonical_string(true),
+                event.sender.to_string(),
+                hex::encode(&event.contents),
+            ])?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
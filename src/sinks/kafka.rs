@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rdkafka::{
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    sinks::{event_key, EventSink, MessageFormat},
+    EventRecord,
+};
+
+/// How long a message may wait for room in the producer queue
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Publishes every event to a Kafka (or Redpanda) topic, keyed by package ID. The producer is
+/// idempotent, and every message has an `event-key` header with the `event_key` of its event
+/// for consumers to drop those published again by an overlapping run.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    format: MessageFormat,
+}
+
+impl KafkaSink {
+    pub fn new(brokers: &str, topic: String, format: MessageFormat) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", "30000")
+            .set("enable.idempotence", "true")
+            .create()?;
+        Ok(Self {
+            producer,
+            topic,
+            format,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let messages = events
+            .iter()
+            .map(|record| {
+                let headers = OwnedHeaders::new().insert(Header {
+                    key: "event-key",
+                    value: Some(&event_key(record)),
+                });
+                let key = record.2.package_id.to_string();
+                Ok((key, self.format.encode(record)?, headers))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Enqueue all events of the checkpoint, then wait for their delivery
+        let deliveries = messages.iter().map(|(key, payload, headers)| {
+            self.producer.send(
+                FutureRecord::to(&self.topic)
+                    .key(key)
+                    .payload(payload)
+                    .headers(headers.clone()),
+                QUEUE_TIMEOUT,
+            )
+        });
+        for delivery in futures::future::join_all(deliveries).await {
+            delivery.map_err(|(e, _message)| e)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+#[cfg(feature = "sinks")]
+pub mod alert;
+#[cfg(feature = "sinks")]
+pub mod anomaly;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "bigquery")]
+pub mod bigquery;
+#[cfg(feature = "sinks")]
+pub mod clickhouse;
+#[cfg(feature = "sinks")]
+pub mod csv;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "nats")]
+pub mod nats;
+#[cfg(feature = "sinks")]
+pub mod notify;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "sinks")]
+pub mod postgres;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "sinks")]
+pub mod sqlite;
+#[cfg(feature = "sinks")]
+pub mod webhook;
+#[cfg(feature = "sinks")]
+pub mod websocket;
+
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::EventRecord;
+
+/// A destination for the events extracted by the worker, fed one checkpoint at a time.
+#[async_trait]
+pub trait EventSink: Send {
+    /// Consume the (filtered) events of a single checkpoint.
+    async fn process(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()>;
+
+    /// Flush any buffered data. Called once after the last checkpoint.
+    async fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The deterministic identifier of an event, `checkpoint:tx_digest:event_index`, for the
+/// destinations to recognize the events already written by an overlapping run.
+pub fn event_key((index, id, _event): &EventRecord) -> String {
+    format!(
+        "{}:{}:{}",
+        index.checkpoint_sequence_number, id.tx_digest, id.event_seq
+    )
+}
+
+/// A flat, self-describing view of an event, used by sinks that emit JSON.
+#[derive(Debug, Clone, Serialize)]
+pub struct JsonEvent {
+    pub checkpoint: u64,
+    pub timestamp_ms: u64,
+    pub tx_digest: String,
+    pub event_index: u64,
+    pub package: String,
+    pub module: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub sender: String,
+    /// Hex encoded BCS contents of the event
+    pub bcs: String,
+}
+
+impl From<&EventRecord> for JsonEvent {
+    fn from((index, id, event): &EventRecord) -> Self {
+        Self {
+            checkpoint: index.checkpoint_sequence_number,
+            timestamp_ms: index.timestamp,
+            tx_digest: id.tx_digest.to_string(),
+            event_index: id.event_seq,
+            package: event.package_id.to_string(),
+            module: event.transaction_module.to_string(),
+            type_: event.type_.to_canonical_string(true),
+            sender: event.sender.to_string(),
+            bcs: hex::encode(&event.contents),
+        }
+    }
+}
+
+/// Encoding of the messages published by sinks that forward individual events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// A `JsonEvent`
+    Json,
+    /// The BCS encoded `EventRecord`
+    Bcs,
+}
+
+impl MessageFormat {
+    pub fn encode(&self, record: &EventRecord) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Json => serde_json::to_vec(&JsonEvent::from(record))?,
+            Self::Bcs => bcs::to_bytes(record)?,
+        })
+    }
+}
+
+impl FromStr for MessageFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "bcs" => Ok(Self::Bcs),
+            _ => bail!("Unknown message format `{s}`, expected `json` or `bcs`"),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use async_nats::{
+    jetstream::{self, context::Context, stream},
+    HeaderMap,
+};
+use async_trait::async_trait;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    sinks::{event_key, EventSink, MessageFormat},
+    EventRecord,
+};
+
+/// Publishes every event to a NATS JetStream subject derived from its type,
+/// `<prefix>.<package>.<module>.<name>`, for consumers to subscribe by wildcard, e.g. to
+/// `sui.events.0xdee9.>`. Every message has a `Nats-Msg-Id` header with the `event_key` of
+/// its event, so that JetStream drops those published again within its duplicate window.
+pub struct NatsSink {
+    jetstream: Context,
+    prefix: String,
+    format: MessageFormat,
+}
+
+impl NatsSink {
+    /// Connect to the server at `url`, e.g. `nats://localhost:4222`, and create the stream
+    /// capturing the subjects of `prefix` if it does not exist.
+    pub async fn connect(
+        url: &str,
+        stream: String,
+        prefix: String,
+        format: MessageFormat,
+    ) -> Result<Self> {
+        let jetstream = jetstream::new(async_nats::connect(url).await?);
+        jetstream
+            .get_or_create_stream(stream::Config {
+                name: stream,
+                subjects: vec![format!("{prefix}.>")],
+                ..Default::default()
+            })
+            .await?;
+        Ok(Self {
+            jetstream,
+            prefix,
+            format,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        // Publish all events of the checkpoint, then wait for their acknowledgements
+        let mut acks = Vec::with_capacity(events.len());
+        for record in events {
+            let type_ = &record.2.type_;
+            let subject = format!(
+                "{}.{}.{}.{}",
+                self.prefix,
+                type_.address.to_hex_literal(),
+                type_.module,
+                type_.name
+            );
+            let mut headers = HeaderMap::new();
+            headers.insert("Nats-Msg-Id", event_key(record).as_str());
+            let payload = self.format.encode(record)?;
+            acks.push(
+                self.jetstream
+                    .publish_with_headers(subject, headers, payload.into())
+                    .await?,
+            );
+        }
+        for ack in acks {
+            ack.await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    fetcher::{RateLimiter, RetryPolicy},
+    filter::EventTypePattern,
+    report::type_to_short_string,
+    sinks::EventSink,
+    EventRecord,
+};
+
+/// Template of the events that match no other template.
+pub const DEFAULT_TEMPLATE: &str =
+    "`{short_type}` from {sender} in checkpoint {checkpoint} (tx {tx_digest})";
+
+/// The chat service receiving the notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyService {
+    Slack,
+    Discord,
+}
+
+impl NotifyService {
+    /// Guess the service from the URL of its incoming webhook.
+    pub fn from_url(url: &str) -> Self {
+        if url.contains("discord.com") || url.contains("discordapp.com") {
+            Self::Discord
+        } else {
+            Self::Slack
+        }
+    }
+
+    /// Maximum length of a single message.
+    fn max_length(&self) -> usize {
+        match self {
+            Self::Slack => 3000,
+            Self::Discord => 2000,
+        }
+    }
+
+    fn payload(&self, text: &str) -> serde_json::Value {
+        match self {
+            Self::Slack => json!({ "text": text }),
+            Self::Discord => json!({ "content": text }),
+        }
+    }
+}
+
+/// A message template for the events of the matching types, written `pattern=template`.
+/// The template may refer to `{type}`, `{short_type}`, `{package}`, `{module}`, `{sender}`,
+/// `{checkpoint}`, `{timestamp_ms}` and `{tx_digest}`.
+#[derive(Debug, Clone)]
+pub struct NotifyTemplate {
+    pub pattern: EventTypePattern,
+    pub template: String,
+}
+
+impl FromStr for NotifyTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((pattern, template)) = s.split_once('=') else {
+            bail!("Expected a template of the form `address::module::name=template`, got `{s}`");
+        };
+        Ok(Self {
+            pattern: pattern.trim().parse()?,
+            template: template.to_string(),
+        })
+    }
+}
+
+fn render(template: &str, (index, id, event): &EventRecord) -> String {
+    template
+        .replace("{type}", &event.type_.to_canonical_string(true))
+        .replace("{short_type}", &type_to_short_string(&event.type_))
+        .replace("{package}", &event.package_id.to_string())
+        .replace("{module}", event.transaction_module.as_str())
+        .replace("{sender}", &event.sender.to_string())
+        .replace(
+            "{checkpoint}",
+            &index.checkpoint_sequence_number.to_string(),
+        )
+        .replace("{timestamp_ms}", &index.timestamp.to_string())
+        .replace("{tx_digest}", &id.tx_digest.to_string())
+}
+
+/// Posts a human readable line per event to a Slack or Discord webhook. The lines of a
+/// checkpoint are packed into as few messages as possible, and messages are rate limited.
+pub struct NotificationSink {
+    client: reqwest::Client,
+    url: String,
+    service: NotifyService,
+    templates: Vec<NotifyTemplate>,
+    limiter: RateLimiter,
+    retry_policy: RetryPolicy,
+}
+
+impl NotificationSink {
+    /// Notify the webhook at `url`, sending at most `max_rate` messages per second.
+    pub fn new(
+        url: String,
+        templates: Vec<NotifyTemplate>,
+        max_rate: f64,
+        retry_policy: RetryPolicy,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            service: NotifyService::from_url(&url),
+            url,
+            templates,
+            limiter: RateLimiter::new(max_rate),
+            retry_policy,
+        }
+    }
+
+    fn format(&self, record: &EventRecord) -> String {
+        let template = self
+            .templates
+            .iter()
+            .find(|t| t.pattern.matches(&record.2.type_))
+            .map_or(DEFAULT_TEMPLATE, |t| t.template.as_str());
+        render(template, record)
+    }
+
+    pub(crate) async fn send(&self, text: &str) -> Result<()> {
+        let payload = &self.service.payload(text);
+        self.retry_policy
+            .retry_with_backoff("send the notification", || async move {
+                self.limiter.acquire().await;
+                self.client
+                    .post(&self.url)
+                    .json(payload)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+            })
+            .await
+            .with_context(|| {
+                format!(
+                    "Notification failed after {} retries",
+                    self.retry_policy.max_retries
+                )
+            })?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for NotificationSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let max_length = self.service.max_length();
+        let mut message = String::new();
+        for record in events {
+            let mut line = self.format(record);
+            line.truncate(line.floor_char_boundary(max_length));
+            if !message.is_empty() && message.len() + 1 + line.len() > max_length {
+                self.send(&message).await?;
+                message.clear();
+            }
+            if !message.is_empty() {
+                message.push('\n');
+            }
+            message.push_str(&line);
+        }
+        if !message.is_empty() {
+            self.send(&message).await?;
+        }
+        Ok(())
+    }
+}
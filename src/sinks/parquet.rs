@@ -0,0 +1,74 @@
+use std::{fs::File, path::PathBuf};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use parquet::arrow::ArrowWriter;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    sinks::{
+        arrow::{event_schema, EventColumns},
+        EventSink,
+    },
+    EventRecord,
+};
+
+/// Writes events into a folder of Parquet files, each covering `checkpoints_per_file`
+/// consecutive checkpoints and named after the first and last of them.
+pub struct ParquetSink {
+    folder: PathBuf,
+    checkpoints_per_file: u64,
+    first_checkpoint: Option<u64>,
+    last_checkpoint: u64,
+    columns: EventColumns,
+}
+
+impl ParquetSink {
+    pub fn new(folder: PathBuf, checkpoints_per_file: u64) -> Result<Self> {
+        std::fs::create_dir_all(&folder)?;
+        Ok(Self {
+            folder,
+            checkpoints_per_file,
+            first_checkpoint: None,
+            last_checkpoint: 0,
+            columns: EventColumns::default(),
+        })
+    }
+
+    fn write_file(&mut self) -> Result<()> {
+        let Some(first) = self.first_checkpoint.take() else {
+            return Ok(());
+        };
+        let batch = self.columns.take_batch()?;
+
+        let filename = format!("{}_{}.events.parquet", first, self.last_checkpoint);
+        let file = File::create(self.folder.join(filename))?;
+        let mut writer = ArrowWriter::try_new(file, event_schema(), None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for ParquetSink {
+    async fn process(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let first = *self.first_checkpoint.get_or_insert(summary.sequence_number);
+        self.last_checkpoint = summary.sequence_number;
+
+        events.iter().for_each(|record| self.columns.push(record));
+
+        if summary.sequence_number + 1 - first >= self.checkpoints_per_file {
+            self.write_file()?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.write_file()
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+use tokio_postgres::{Client, NoTls};
+
+use crate::{sinks::EventSink, EventRecord};
+
+/// Number of checkpoints buffered before they are written in a single transaction
+const BATCH_SIZE: usize = 100;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS checkpoints (
+        sequence_number BIGINT PRIMARY KEY,
+        epoch BIGINT NOT NULL,
+        timestamp_ms BIGINT NOT NULL,
+        digest TEXT NOT NULL,
+        network_total_transactions BIGINT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        checkpoint BIGINT NOT NULL,
+        timestamp_ms BIGINT NOT NULL,
+        tx_digest TEXT NOT NULL,
+        event_index BIGINT NOT NULL,
+        package TEXT NOT NULL,
+        module TEXT NOT NULL,
+        type TEXT NOT NULL,
+        sender TEXT NOT NULL,
+        bcs BYTEA NOT NULL
+    );
+    -- The databases of earlier versions may hold duplicates, which the unique key rejects
+    DO $$ BEGIN
+        IF to_regclass('events_key') IS NULL THEN
+            DELETE FROM events a USING events b
+                WHERE a.ctid > b.ctid
+                AND (a.checkpoint, a.tx_digest, a.event_index)
+                    = (b.checkpoint, b.tx_digest, b.event_index);
+            CREATE UNIQUE INDEX events_key ON events (checkpoint, tx_digest, event_index);
+        END IF;
+    END $$;
+    CREATE INDEX IF NOT EXISTS events_package ON events (package);
+    CREATE INDEX IF NOT EXISTS events_type ON events (type);
+";
+
+/// Columns of the `checkpoints` table, buffered until the next flush.
+#[derive(Default)]
+struct CheckpointRows {
+    sequence_number: Vec<i64>,
+    epoch: Vec<i64>,
+    timestamp_ms: Vec<i64>,
+    digest: Vec<String>,
+    network_total_transactions: Vec<i64>,
+}
+
+/// Columns of the `events` table, buffered until the next flush.
+#[derive(Default)]
+struct EventRows {
+    checkpoint: Vec<i64>,
+    timestamp_ms: Vec<i64>,
+    tx_digest: Vec<String>,
+    event_index: Vec<i64>,
+    package: Vec<String>,
+    module: Vec<String>,
+    type_: Vec<String>,
+    sender: Vec<String>,
+    bcs: Vec<Vec<u8>>,
+}
+
+/// Writes checkpoints and events into PostgreSQL, in batches of `BATCH_SIZE` checkpoints.
+/// Rows already written by an overlapping run are skipped, so ranges can be processed again.
+pub struct PostgresSink {
+    client: Client,
+    checkpoints: CheckpointRows,
+    events: EventRows,
+}
+
+impl PostgresSink {
+    /// Connect to the database at `url` and create the tables if they do not exist.
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+
+        // The connection object performs the actual communication with the database
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::error!("Postgres connection error: {}", e);
+            }
+        });
+
+        client.batch_execute(SCHEMA).await?;
+
+        Ok(Self {
+            client,
+            checkpoints: CheckpointRows::default(),
+            events: EventRows::default(),
+        })
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.checkpoints.sequence_number.is_empty() {
+            return Ok(());
+        }
+
+        let checkpoints = std::mem::take(&mut self.checkpoints);
+        let events = std::mem::take(&mut self.events);
+
+        let transaction = self.client.transaction().await?;
+        transaction
+            .execute(
+                "INSERT INTO checkpoints
+                    SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::BIGINT[], $4::TEXT[], $5::BIGINT[])
+                    ON CONFLICT (sequence_number) DO NOTHING",
+                &[
+                    &checkpoints.sequence_number,
+                    &checkpoints.epoch,
+                    &checkpoints.timestamp_ms,
+                    &checkpoints.digest,
+                    &checkpoints.network_total_transactions,
+                ],
+            )
+            .await?;
+        transaction
+            .execute(
+                "INSERT INTO events
+                    SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::TEXT[], $4::BIGINT[],
+                        $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[], $9::BYTEA[])
+                    ON CONFLICT (checkpoint, tx_digest, event_index) DO NOTHING",
+                &[
+                    &events.checkpoint,
+                    &events.timestamp_ms,
+                    &events.tx_digest,
+                    &events.event_index,
+                    &events.package,
+                    &events.module,
+                    &events.type_,
+                    &events.sender,
+                    &events.bcs,
+                ],
+            )
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for PostgresSink {
+    async fn process(
+        &mut self,
+        summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        let checkpoints = &mut self.checkpoints;
+        checkpoints
+            .sequence_number
+            .push(summary.sequence_number as i64);
+        checkpoints.epoch.push(summary.epoch as i64);
+        checkpoints.timestamp_ms.push(summary.timestamp_ms as i64);
+        checkpoints.digest.push(summary.digest().to_string());
+        checkpoints
+            .network_total_transactions
+            .push(summary.network_total_transactions as i64);
+
+        let rows = &mut self.events;
+        for (index, id, event) in events {
+            rows.checkpoint
+                .push(index.checkpoint_sequence_number as i64);
+            rows.timestamp_ms.push(index.timestamp as i64);
+            rows.tx_digest.push(id.tx_digest.to_string());
+            rows.event_index.push(id.event_seq as i64);
+            rows.package.push(event.package_id.to_string());
+            rows.module.push(event.transaction_module.to_string());
+            rows.type_.push(event.type_.to_canonical_string(true));
+            rows.sender.push(event.sender.to_string());
+            rows.bcs.push(event.contents.clone());
+        }
+
+        if self.checkpoints.sequence_number.len() >= BATCH_SIZE {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        self.flush().await
+    }
+}
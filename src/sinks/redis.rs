@@ -0,0 +1,74 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    sinks::{event_key, EventSink, MessageFormat},
+    EventRecord,
+};
+
+/// Appends every event to a Redis stream, trimmed to about `max_len` entries so that it only
+/// holds the recent events. Each entry has the `event_key` of its event under `key`, its
+/// package and type, and the encoded event under `event`. The connection is re-established
+/// when it drops.
+pub struct RedisSink {
+    connection: ConnectionManager,
+    stream: String,
+    max_len: usize,
+    format: MessageFormat,
+}
+
+impl RedisSink {
+    /// Connect to the server at `url`, e.g. `redis://localhost:6379`.
+    pub async fn connect(
+        url: &str,
+        stream: String,
+        max_len: usize,
+        format: MessageFormat,
+    ) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            connection: ConnectionManager::new(client).await?,
+            stream,
+            max_len,
+            format,
+        })
+    }
+}
+
+#[async_trait]
+impl EventSink for RedisSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        // Append all events of the checkpoint in a single round trip
+        let mut pipeline = redis::pipe();
+        for record in events {
+            pipeline
+                .cmd("XADD")
+                .arg(&self.stream)
+                .arg("MAXLEN")
+                .arg("~")
+                .arg(self.max_len)
+                .arg("*")
+                .arg("key")
+                .arg(event_key(record))
+                .arg("package")
+                .arg(record.2.package_id.to_string())
+                .arg("type")
+                .arg(record.2.type_.to_canonical_string(true))
+                .arg("event")
+                .arg(self.format.encode(record)?)
+                .ignore();
+        }
+        pipeline.query_async::<()>(&mut self.connection).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{sinks::EventSink, EventRecord};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS events (
+        checkpoint INTEGER NOT NULL,
+        timestamp_ms INTEGER NOT NULL,
+        tx_digest TEXT NOT NULL,
+        event_index INTEGER NOT NULL,
+        package TEXT NOT NULL,
+        module TEXT NOT NULL,
+        type TEXT NOT NULL,
+        sender TEXT NOT NULL,
+        bcs BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS events_package ON events (package);
+    CREATE INDEX IF NOT EXISTS events_type ON events (type);
+";
+
+/// The unique key of the events, created after removing the duplicates that the databases of
+/// earlier versions may hold.
+const KEY_INDEX: &str = "
+    DELETE FROM events WHERE rowid NOT IN (
+        SELECT MIN(rowid) FROM events GROUP BY checkpoint, tx_digest, event_index
+    );
+    CREATE UNIQUE INDEX events_key ON events (checkpoint, tx_digest, event_index);
+";
+
+/// Inserts every event into the `events` table of a SQLite database. Events are unique by
+/// checkpoint, transaction and index, so processing a range again does not duplicate them.
+pub struct SqliteSink {
+    connection: Connection,
+}
+
+impl SqliteSink {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        let indexed: bool = connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'events_key')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !indexed {
+            connection.execute_batch(&format!("BEGIN; {KEY_INDEX} COMMIT;"))?;
+        }
+        Ok(Self { connection })
+    }
+}
+
+#[async_trait]
+impl EventSink for SqliteSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        // Insert the events of each checkpoint in a single transaction
+        let transaction = self.connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO events
+                    (checkpoint, timestamp_ms, tx_digest, event_index, package, module, type, sender, bcs)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                    ON CONFLICT (checkpoint, tx_digest, event_index) DO NOTHING",
+            )?;
+            for (index, id, event) in events {
+                statement.execute(params![
+                    index.checkpoint_sequence_number as i64,
+                    index.timestamp as i64,
+                    id.tx_digest.to_string(),
+                    id.event_seq as i64,
+                    event.package_id.to_string(),
+                    event.transaction_module.to_string(),
+                    event.type_.to_canonical_string(true),
+                    event.sender.to_string(),
+                    event.contents,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
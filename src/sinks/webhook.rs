@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{
+    fetcher::RetryPolicy,
+    sinks::{event_key, EventSink, JsonEvent},
+    EventRecord,
+};
+
+/// POSTs the events of every checkpoint as JSON arrays of `JsonEvent` to an HTTP endpoint,
+/// in batches of at most `batch_size` events. Failed requests are retried with backoff. Every
+/// request has an `Idempotency-Key` header, the same when the batch is sent again, e.g. by an
+/// overlapping run.
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    batch_size: usize,
+    retry_policy: RetryPolicy,
+}
+
+impl WebhookSink {
+    pub fn new(url: String, batch_size: usize, retry_policy: RetryPolicy) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            batch_size: batch_size.max(1),
+            retry_policy,
+        }
+    }
+
+    async fn post(&self, key: &str, batch: &[JsonEvent]) -> Result<()> {
+        self.retry_policy
+            .retry_with_backoff("send the webhook request", || async move {
+                self.client
+                    .post(&self.url)
+                    .header("Idempotency-Key", key)
+                    .json(batch)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+            })
+            .await
+            .with_context(|| {
+                format!(
+                    "Webhook request failed after {} retries",
+                    self.retry_policy.max_retries
+                )
+            })?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        for batch in events.chunks(self.batch_size) {
+            // A batch is identified by its first and last events, whatever the batch size
+            let key = format!(
+                "{}..{}",
+                event_key(&batch[0]),
+                event_key(&batch[batch.len() - 1])
+            );
+            let batch: Vec<_> = batch.iter().map(JsonEvent::from).collect();
+            self.post(&key, &batch).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    filter::EventFilter,
+    sinks::{EventSink, JsonEvent},
+    EventRecord,
+};
+
+/// Number of checkpoints buffered for the clients falling behind
+const BUFFER: usize = 1000;
+
+/// A text frame sent by a client to replace its filter. Each non-empty criterion must match
+/// for an event to be pushed, e.g. `{"event_types": ["0xdee9::clob_v2::*"]}`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Subscription {
+    packages: Vec<String>,
+    event_types: Vec<String>,
+    filter: String,
+}
+
+/// Pushes every extracted event as a `JsonEvent` text frame to the WebSocket clients.
+/// Clients receive all events until they send a `Subscription`, which is acknowledged
+/// with `{"subscribed": true}` or rejected with `{"error": "..."}`.
+pub struct WebSocketSink {
+    sender: broadcast::Sender<Arc<Vec<EventRecord>>>,
+}
+
+impl WebSocketSink {
+    /// Accept WebSocket connections on `address`.
+    pub async fn serve(address: SocketAddr) -> Result<Self> {
+        let (sender, _) = broadcast::channel(BUFFER);
+        let subscriptions = sender.clone();
+        let app = Router::new().route(
+            "/",
+            get(move |upgrade: WebSocketUpgrade| {
+                let receiver = subscriptions.subscribe();
+                async move { upgrade.on_upgrade(move |socket| push_events(socket, receiver)) }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("WebSocket server failed: {}", e);
+            }
+        });
+        Ok(Self { sender })
+    }
+}
+
+/// Serve a single client until it disconnects.
+async fn push_events(
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<Arc<Vec<EventRecord>>>,
+) {
+    let mut filter = EventFilter::default();
+    loop {
+        let frames = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let subscription = serde_json::from_str::<Subscription>(&text)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|s| EventFilter::parse(&s.packages, &s.event_types, &s.filter));
+                    let reply = match subscription {
+                        Ok(subscription) => {
+                            filter = subscription;
+                            json!({ "subscribed": true })
+                        }
+                        Err(e) => json!({ "error": e.to_string() }),
+                    };
+                    vec![reply.to_string()]
+                }
+                // Pings are answered by axum
+                Some(Ok(Message::Binary(_) | Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+            },
+            records = receiver.recv() => match records {
+                Ok(records) => records
+                    .iter()
+                    .filter(|record| filter.matches(record))
+                    .filter_map(|record| serde_json::to_string(&JsonEvent::from(record)).ok())
+                    .collect(),
+                Err(RecvError::Lagged(missed)) => {
+                    vec![json!({ "error": format!("Missed {missed} checkpoints") }).to_string()]
+                }
+                Err(RecvError::Closed) => return,
+            },
+        };
+
+        for frame in frames {
+            if socket.send(Message::Text(frame)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for WebSocketSink {
+    async fn process(
+        &mut self,
+        _summary: &CertifiedCheckpointSummary,
+        events: &[EventRecord],
+    ) -> Result<()> {
+        // Sending only fails when no client is connected, in which case the events are dropped
+        if !events.is_empty() {
+            let _ = self.sender.send(Arc::new(events.to_vec()));
+        }
+        Ok(())
+    }
+}
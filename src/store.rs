@@ -0,0 +1,163 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use object_store::{http::HttpBuilder, ObjectStore, RetryConfig};
+
+use crate::{
+    cache::CHECKPOINT_EXTENSION,
+    error::{HarvestError, HarvestResult},
+    fetcher::{client_options, is_transient, CheckpointFetcher, CheckpointFile, FetchOptions},
+};
+
+/// Where the worker reads the checkpoints from, set with
+/// `EventExtractWorkerBuilder::checkpoint_store` for archives laid out differently from the
+/// checkpoint stores of Sui. Checkpoints past the tip are polled when following the chain,
+/// and failed reads retried when the error is transient.
+#[async_trait]
+pub trait CheckpointStore: Send + Sync + std::fmt::Debug {
+    /// The file of checkpoint `sequence_number`, or `None` if it is not available. The
+    /// worker deserializes it on the blocking thread pool.
+    async fn get_contents(&self, sequence_number: u64) -> HarvestResult<Option<CheckpointFile>>;
+
+    /// The latest checkpoint of the store, or `None` if it cannot tell.
+    async fn latest(&self) -> HarvestResult<Option<u64>>;
+}
+
+/// The checkpoint files of an object store, e.g. a bucket, named by their sequence number.
+#[derive(Debug)]
+pub struct ObjectCheckpointStore {
+    store: Box<dyn ObjectStore>,
+}
+
+impl ObjectCheckpointStore {
+    pub fn new(store: Box<dyn ObjectStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for ObjectCheckpointStore {
+    async fn get_contents(&self, sequence_number: u64) -> HarvestResult<Option<CheckpointFile>> {
+        let path =
+            object_store::path::Path::from(format!("{sequence_number}.{CHECKPOINT_EXTENSION}"));
+        // Only the transient errors are retried by the fetcher
+        let failed = |source: object_store::Error| match is_transient(&source) {
+            true => HarvestError::FetchFailed {
+                sequence_number,
+                retries: 0,
+                source: source.into(),
+            },
+            false => HarvestError::StoreUnavailable(source.into()),
+        };
+        let bytes = match self.store.get(&path).await {
+            Ok(response) => response.bytes().await.map_err(failed)?,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(failed(e)),
+        };
+        Ok(Some(CheckpointFile::Memory(bytes.to_vec())))
+    }
+
+    async fn latest(&self) -> HarvestResult<Option<u64>> {
+        // Listing a bucket to find its latest checkpoint is too slow
+        Ok(None)
+    }
+}
+
+/// The checkpoint files served over HTTP at `{url}/{sequence_number}.chk`, like the
+/// checkpoint stores of Sui, with the headers, proxy and certificate authorities of
+/// `options`.
+#[derive(Debug)]
+pub struct HttpCheckpointStore(ObjectCheckpointStore);
+
+impl HttpCheckpointStore {
+    pub fn new(url: &str, timeout: Duration, options: &FetchOptions) -> HarvestResult<Self> {
+        let store = HttpBuilder::new()
+            .with_url(url)
+            .with_client_options(client_options(timeout, options))
+            .with_retry(RetryConfig {
+                max_retries: 0,
+                ..RetryConfig::default()
+            })
+            .build()
+            .map_err(|e| HarvestError::StoreUnavailable(e.into()))?;
+        Ok(Self(ObjectCheckpointStore::new(Box::new(store))))
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for HttpCheckpointStore {
+    async fn get_contents(&self, sequence_number: u64) -> HarvestResult<Option<CheckpointFile>> {
+        self.0.get_contents(sequence_number).await
+    }
+
+    async fn latest(&self) -> HarvestResult<Option<u64>> {
+        self.0.latest().await
+    }
+}
+
+/// The checkpoint files of a local directory, compressed or not, e.g. a copy of a cache
+/// folder.
+#[derive(Debug)]
+pub struct LocalCheckpointStore {
+    folder: PathBuf,
+}
+
+impl LocalCheckpointStore {
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        Self {
+            folder: folder.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for LocalCheckpointStore {
+    async fn get_contents(&self, sequence_number: u64) -> HarvestResult<Option<CheckpointFile>> {
+        let path = self
+            .folder
+            .join(format!("{sequence_number}.{CHECKPOINT_EXTENSION}"));
+        match tokio::fs::try_exists(&path).await {
+            Ok(true) => Ok(Some(CheckpointFile::Disk(path))),
+            Ok(false) => Ok(None),
+            Err(e) => Err(HarvestError::StoreUnavailable(e.into())),
+        }
+    }
+
+    async fn latest(&self) -> HarvestResult<Option<u64>> {
+        let folder = self.folder.clone();
+        tokio::task::spawn_blocking(move || latest_file(&folder))
+            .await
+            .map_err(|e| HarvestError::StoreUnavailable(e.into()))?
+    }
+}
+
+/// The highest sequence number of the checkpoint files of `folder`.
+fn latest_file(folder: &Path) -> HarvestResult<Option<u64>> {
+    let entries =
+        std::fs::read_dir(folder).map_err(|e| HarvestError::StoreUnavailable(e.into()))?;
+    Ok(entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != CHECKPOINT_EXTENSION {
+                return None;
+            }
+            path.file_stem()?.to_str()?.parse::<u64>().ok()
+        })
+        .max())
+}
+
+/// The remote stores of the fetcher, with its cache and retries. Missing checkpoints are
+/// reported as errors, or waited for when the fetcher follows the chain.
+#[async_trait]
+impl CheckpointStore for CheckpointFetcher {
+    async fn get_contents(&self, sequence_number: u64) -> HarvestResult<Option<CheckpointFile>> {
+        self.fetch_file(sequence_number).await.map(Some)
+    }
+
+    async fn latest(&self) -> HarvestResult<Option<u64>> {
+        self.latest_checkpoint().await
+    }
+}
@@ -0,0 +1,67 @@
+use std::{collections::VecDeque, marker::PhantomData};
+
+use anyhow::Result;
+use move_core_types::language_storage::StructTag;
+use serde::de::DeserializeOwned;
+use sui_types::{
+    base_types::SuiAddress, event::EventID, messages_checkpoint::CertifiedCheckpointSummary,
+};
+
+use crate::{channel::Receiver, EventIndex, EventRecord};
+
+/// An event deserialized into the Rust type mirroring its Move struct.
+#[derive(Debug)]
+pub struct TypedEvent<T> {
+    pub index: EventIndex,
+    pub id: EventID,
+    pub sender: SuiAddress,
+    pub data: T,
+}
+
+/// A stream of the events of a single Move type, already deserialized into `T`, see
+/// `EventExtractWorkerBuilder::subscribe`.
+pub struct EventStream<T> {
+    tag: StructTag,
+    receiver: Receiver<(CertifiedCheckpointSummary, Vec<EventRecord>)>,
+    pending: VecDeque<EventRecord>,
+    _type: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> EventStream<T> {
+    /// Wrap a receiver of event records, keeping only the events of type `tag`.
+    pub fn new(
+        tag: StructTag,
+        receiver: Receiver<(CertifiedCheckpointSummary, Vec<EventRecord>)>,
+    ) -> Self {
+        Self {
+            tag,
+            receiver,
+            pending: VecDeque::new(),
+            _type: PhantomData,
+        }
+    }
+
+    /// The next event of the stream, or `None` once the worker is done.
+    pub async fn next(&mut self) -> Option<Result<TypedEvent<T>>> {
+        loop {
+            while let Some((index, id, event)) = self.pending.pop_front() {
+                if event.type_ != self.tag {
+                    continue;
+                }
+                return Some(
+                    bcs::from_bytes(&event.contents)
+                        .map(|data| TypedEvent {
+                            index,
+                            id,
+                            sender: event.sender,
+                            data,
+                        })
+                        .map_err(Into::into),
+                );
+            }
+
+            let (_summary, records) = self.receiver.recv().await?;
+            self.pending.extend(records);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use sui_types::gas::GasCostSummary;
+
+#[cfg(feature = "full-node")]
+pub use worker::{CheckpointSummaryWorker, CheckpointSummaryWorkerBuilder};
+
+/// The chain-level statistics of a checkpoint, read from its summary alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointSummaryRecord {
+    pub sequence_number: u64,
+    pub epoch: u64,
+    pub timestamp: u64,
+    /// Number of transactions executed in the checkpoint
+    pub transactions: u64,
+    /// Gas used by these transactions, the difference between the rolling gas of the epoch
+    /// at this checkpoint and at the previous one
+    pub gas: GasCostSummary,
+}
+
+#[cfg(feature = "full-node")]
+mod worker {
+    use std::collections::HashMap;
+
+    use anyhow::anyhow;
+    use futures::Future;
+    use sui_sdk::{rpc_types::CheckpointId, SuiClient};
+    use sui_types::{
+        gas::GasCostSummary, messages_checkpoint::CheckpointSequenceNumber, sui_serde::BigInt,
+    };
+
+    use super::CheckpointSummaryRecord;
+    use crate::{
+        channel::{self, ChannelConfig, Receiver, Sender},
+        error::{HarvestError, HarvestResult},
+        fetcher::RetryPolicy,
+        metrics::HarvestMetrics,
+    };
+
+    /// Maximum number of checkpoints the full node returns per page
+    const PAGE_SIZE: usize = 100;
+
+    /// Reads the summaries of a range of checkpoints from the full node, a page at a time,
+    /// without downloading their contents from the checkpoint store. Unlike the other workers, it
+    /// is not fed the full checkpoints, so it is much faster when only the number of
+    /// transactions, the gas used and the timestamps of the checkpoints are needed.
+    pub struct CheckpointSummaryWorker {
+        client: SuiClient,
+        sender: Sender<CheckpointSummaryRecord>,
+        retry_policy: RetryPolicy,
+        #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+        metrics: Option<HarvestMetrics>,
+    }
+
+    impl CheckpointSummaryWorker {
+        /// Configure a worker reading the summaries from the full node of `client`.
+        pub fn builder(client: SuiClient) -> CheckpointSummaryWorkerBuilder {
+            CheckpointSummaryWorkerBuilder {
+                client,
+                start: 0,
+                limit: None,
+                channel: None,
+                retry_policy: RetryPolicy::default(),
+                metrics: None,
+            }
+        }
+
+        async fn run(
+            self,
+            initial: u64,
+            limit: Option<u64>,
+        ) -> HarvestResult<HashMap<String, CheckpointSequenceNumber>> {
+            // The rolling gas of the checkpoint before the range, to compute that of the first one
+            let mut previous = match initial.checked_sub(1) {
+                Some(sequence_number) => {
+                    let checkpoint = self
+                        .retry_policy
+                        .retry_with_backoff("get the previous checkpoint", || {
+                            self.client
+                                .read_api()
+                                .get_checkpoint(CheckpointId::SequenceNumber(sequence_number))
+                        })
+                        .await
+                        .map_err(|e| HarvestError::Rpc(e.into()))?;
+                    Some((checkpoint.epoch, checkpoint.epoch_rolling_gas_cost_summary))
+                }
+                None => None,
+            };
+
+            // Without a limit, the range ends at the latest checkpoint
+            let end = initial.saturating_add(limit.unwrap_or(u64::MAX - initial));
+            let mut next = initial;
+            while next < end {
+                let page_size = PAGE_SIZE.min((end - next).try_into().unwrap_or(PAGE_SIZE));
+                let page = self
+                    .retry_policy
+                    .retry_with_backoff("get the checkpoint summaries", || {
+                        self.client.read_api().get_checkpoints(
+                            next.checked_sub(1).map(BigInt::from),
+                            Some(page_size),
+                            false,
+                        )
+                    })
+                    .await
+                    .map_err(|e| HarvestError::Rpc(e.into()))?;
+                if page.data.is_empty() {
+                    if limit.is_none() {
+                        break;
+                    }
+                    return Err(HarvestError::Rpc(
+                        anyhow!("Checkpoint {next} of the range is past the latest checkpoint")
+                            .into(),
+                    ));
+                }
+
+                for checkpoint in page.data {
+                    let rolling = checkpoint.epoch_rolling_gas_cost_summary;
+                    let gas = match &previous {
+                        Some((epoch, before)) if *epoch == checkpoint.epoch => GasCostSummary {
+                            computation_cost: rolling
+                                .computation_cost
+                                .saturating_sub(before.computation_cost),
+                            storage_cost: rolling.storage_cost.saturating_sub(before.storage_cost),
+                            storage_rebate: rolling
+                                .storage_rebate
+                                .saturating_sub(before.storage_rebate),
+                            non_refundable_storage_fee: rolling
+                                .non_refundable_storage_fee
+                                .saturating_sub(before.non_refundable_storage_fee),
+                        },
+                        // The first checkpoint of an epoch starts its rolling gas
+                        _ => rolling.clone(),
+                    };
+                    let record = CheckpointSummaryRecord {
+                        sequence_number: checkpoint.sequence_number,
+                        epoch: checkpoint.epoch,
+                        timestamp: checkpoint.timestamp_ms,
+                        transactions: checkpoint.transactions.len() as u64,
+                        gas,
+                    };
+                    previous = Some((checkpoint.epoch, rolling));
+                    next = checkpoint.sequence_number + 1;
+
+                    self.sender.send(record).await?;
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = &self.metrics {
+                        metrics.checkpoints_processed.inc();
+                        metrics
+                            .latest_checkpoint
+                            .set(checkpoint.sequence_number as i64);
+                    }
+                }
+            }
+
+            // Every checkpoint before this one was processed
+            Ok(HashMap::from([("workflow".to_string(), next)]))
+        }
+    }
+
+    /// Configures and starts a `CheckpointSummaryWorker`, from checkpoint 0 to the latest
+    /// checkpoint by default.
+    pub struct CheckpointSummaryWorkerBuilder {
+        client: SuiClient,
+        start: u64,
+        limit: Option<u64>,
+        channel: Option<ChannelConfig>,
+        retry_policy: RetryPolicy,
+        metrics: Option<HarvestMetrics>,
+    }
+
+    impl CheckpointSummaryWorkerBuilder {
+        /// First checkpoint to process.
+        pub fn start(mut self, start: u64) -> Self {
+            self.start = start;
+            self
+        }
+
+        /// Number of checkpoints to process, up to the latest one by default.
+        pub fn limit(mut self, limit: u64) -> Self {
+            self.limit = Some(limit);
+            self
+        }
+
+        pub fn channel(mut self, config: ChannelConfig) -> Self {
+            self.channel = Some(config);
+            self
+        }
+
+        /// How the failed requests to the full node are retried.
+        pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+            self.retry_policy = retry_policy;
+            self
+        }
+
+        pub fn metrics(mut self, metrics: HarvestMetrics) -> Self {
+            self.metrics = Some(metrics);
+            self
+        }
+
+        /// Start the worker, returning the future driving it and the receiver of the
+        /// summaries, in checkpoint order.
+        pub async fn build(
+            self,
+        ) -> HarvestResult<(
+            impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+            Receiver<CheckpointSummaryRecord>,
+        )> {
+            let (sender, receiver) = channel::channel(self.channel.unwrap_or_default());
+            let worker = CheckpointSummaryWorker {
+                client: self.client,
+                sender,
+                retry_policy: self.retry_policy,
+                metrics: self.metrics,
+            };
+            Ok((worker.run(self.start, self.limit), receiver))
+        }
+    }
+}
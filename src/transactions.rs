@@ -0,0 +1,252 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::Future;
+use serde::{Deserialize, Serialize};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    effects::TransactionEffectsAPI,
+    execution_status::ExecutionStatus,
+    full_checkpoint_content::CheckpointData,
+    gas::GasCostSummary,
+    messages_checkpoint::{CertifiedCheckpointSummary, CheckpointSequenceNumber},
+    transaction::{Command, TransactionDataAPI, TransactionKind},
+};
+use url::Url;
+
+use crate::{
+    channel::{ChannelConfig, Receiver, Sender},
+    default_remote_store_urls,
+    error::HarvestResult,
+    fetcher::{FetchOptions, ReaderOptions},
+    metrics::HarvestMetrics,
+    run_ordered, Replay, Worker,
+};
+
+/// A Move function called by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MoveCallTarget {
+    pub package: ObjectID,
+    pub module: String,
+    pub function: String,
+}
+
+/// A summary of a transaction executed in a checkpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub checkpoint_sequence_number: u64,
+    pub transaction_sequence_number: u64,
+    pub timestamp: u64,
+    pub digest: TransactionDigest,
+    pub sender: SuiAddress,
+    pub gas: GasCostSummary,
+    /// The commands of programmable transactions, or the kind of system transactions
+    pub commands: Vec<String>,
+    /// The Move functions called by programmable transactions
+    pub calls: Vec<MoveCallTarget>,
+    pub status: ExecutionStatus,
+    /// Number of events emitted; they share the transaction digest
+    pub event_count: usize,
+}
+
+pub struct TransactionExtractWorker<F>
+where
+    F: Fn(&TransactionRecord) -> bool,
+{
+    filter: F,
+    sender: Sender<(CertifiedCheckpointSummary, Vec<TransactionRecord>)>,
+}
+
+impl<F> TransactionExtractWorker<F>
+where
+    F: Fn(&TransactionRecord) -> bool + Send + Sync + 'static,
+{
+    /// Configure a worker keeping the transactions accepted by `filter`.
+    pub fn builder(filter: F) -> TransactionExtractWorkerBuilder<F> {
+        TransactionExtractWorkerBuilder {
+            filter,
+            start: 0,
+            limit: None,
+            remote_store_urls: default_remote_store_urls(),
+            concurrency: 5,
+            reader_options: None,
+            fetch_options: None,
+            cache_folder: None,
+            channel: None,
+            metrics: None,
+        }
+    }
+}
+
+/// Configures and starts a `TransactionExtractWorker`, with the defaults of
+/// `EventExtractWorkerBuilder`.
+pub struct TransactionExtractWorkerBuilder<F> {
+    filter: F,
+    start: u64,
+    limit: Option<u64>,
+    remote_store_urls: Vec<Url>,
+    concurrency: usize,
+    reader_options: Option<ReaderOptions>,
+    fetch_options: Option<FetchOptions>,
+    cache_folder: Option<PathBuf>,
+    channel: Option<ChannelConfig>,
+    metrics: Option<HarvestMetrics>,
+}
+
+impl<F> TransactionExtractWorkerBuilder<F>
+where
+    F: Fn(&TransactionRecord) -> bool + Send + Sync + 'static,
+{
+    /// First checkpoint to process.
+    pub fn start(mut self, start: u64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Number of checkpoints to process, unlimited by default.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// See `fetcher::CheckpointFetcher::new` for the supported URLs.
+    pub fn remote_store_url(mut self, url: Url) -> Self {
+        self.remote_store_urls = vec![url];
+        self
+    }
+
+    /// Spread the downloads over these stores, failing over between them.
+    pub fn remote_store_urls(mut self, urls: impl IntoIterator<Item = Url>) -> Self {
+        self.remote_store_urls = urls.into_iter().collect();
+        self
+    }
+
+    /// Number of checkpoints downloaded concurrently.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn reader_options(mut self, options: ReaderOptions) -> Self {
+        self.reader_options = Some(options);
+        self
+    }
+
+    pub fn fetch_options(mut self, options: FetchOptions) -> Self {
+        self.fetch_options = Some(options);
+        self
+    }
+
+    /// Cache the downloaded checkpoints in this folder.
+    pub fn cache_folder(mut self, folder: impl Into<PathBuf>) -> Self {
+        self.cache_folder = Some(folder.into());
+        self
+    }
+
+    pub fn channel(mut self, config: ChannelConfig) -> Self {
+        self.channel = Some(config);
+        self
+    }
+
+    pub fn metrics(mut self, metrics: HarvestMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Start the worker, returning the future driving it and the receiver of the transactions
+    /// of every checkpoint, in checkpoint order.
+    pub async fn build(
+        self,
+    ) -> HarvestResult<(
+        impl Future<Output = HarvestResult<HashMap<String, CheckpointSequenceNumber>>>,
+        Receiver<(CertifiedCheckpointSummary, Vec<TransactionRecord>)>,
+    )> {
+        let filter = self.filter;
+        let length = self.limit.unwrap_or(u64::MAX - self.start);
+        run_ordered(
+            |sender| TransactionExtractWorker { filter, sender },
+            vec![self.start..self.start.saturating_add(length)],
+            self.remote_store_urls.into(),
+            self.concurrency,
+            self.reader_options,
+            self.fetch_options,
+            self.cache_folder,
+            self.channel,
+            self.metrics,
+        )
+        .await
+    }
+}
+
+impl<F> Replay for TransactionExtractWorker<F> {}
+
+#[async_trait]
+impl<F> Worker for TransactionExtractWorker<F>
+where
+    F: Fn(&TransactionRecord) -> bool + Send + Sync,
+{
+    async fn process_checkpoint(&self, checkpoint: CheckpointData) -> Result<()> {
+        let mut records = extract_transactions(&checkpoint);
+        records.retain(|record| (self.filter)(record));
+
+        // Send them to the aggregator
+        self.sender
+            .send((checkpoint.checkpoint_summary, records))
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// The transactions of `checkpoint`, in order.
+pub(crate) fn extract_transactions(checkpoint: &CheckpointData) -> Vec<TransactionRecord> {
+    let CheckpointData {
+        checkpoint_summary,
+        checkpoint_contents: _, // We don't need this
+        transactions,
+    } = checkpoint;
+
+    transactions
+        .iter()
+        .enumerate()
+        .map(|(tx_seq, tx)| {
+            let (commands, calls) = match tx.transaction.data().transaction_data().kind() {
+                TransactionKind::ProgrammableTransaction(programmable) => (
+                    programmable
+                        .commands
+                        .iter()
+                        .map(|command| command.to_string())
+                        .collect(),
+                    programmable
+                        .commands
+                        .iter()
+                        .filter_map(|command| match command {
+                            Command::MoveCall(call) => Some(MoveCallTarget {
+                                package: call.package,
+                                module: call.module.to_string(),
+                                function: call.function.to_string(),
+                            }),
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+                kind => (vec![kind.name().to_string()], vec![]),
+            };
+
+            TransactionRecord {
+                checkpoint_sequence_number: checkpoint_summary.sequence_number,
+                transaction_sequence_number: tx_seq as u64,
+                timestamp: checkpoint_summary.timestamp_ms,
+                digest: *tx.transaction.digest(),
+                sender: tx.transaction.sender_address(),
+                gas: tx.effects.gas_cost_summary().clone(),
+                commands,
+                calls,
+                status: tx.effects.status().clone(),
+                event_count: tx.events.as_ref().map_or(0, |events| events.data.len()),
+            }
+        })
+        .collect()
+}
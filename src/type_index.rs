@@ -0,0 +1,24 @@
+use serde::Serialize;
+
+#[cfg(feature = "cache-index")]
+mod index;
+
+#[cfg(feature = "cache-index")]
+pub use index::{clear, TypeIndex};
+
+/// File of the index of the event types, inside the checkpoint cache folder.
+pub const TYPE_INDEX_FILE: &str = "events.sqlite";
+
+/// Where an event was emitted: its checkpoint, the index of its transaction in the
+/// checkpoint and its index in the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct EventLocation {
+    pub checkpoint: u64,
+    pub transaction: u64,
+    pub event: u64,
+}
+
+/// Without the `cache-index` feature there is no index: this type has no values, so the
+/// index of the workers is always `None`.
+#[cfg(not(feature = "cache-index"))]
+pub enum TypeIndex {}
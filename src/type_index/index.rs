@@ -0,0 +1,179 @@
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::{EventLocation, TYPE_INDEX_FILE};
+use crate::{filter::EventTypePattern, EventRecord};
+
+const TYPE_INDEX_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS types (
+        id INTEGER PRIMARY KEY,
+        address TEXT NOT NULL,
+        module TEXT NOT NULL,
+        name TEXT NOT NULL,
+        type TEXT NOT NULL UNIQUE
+    );
+    CREATE INDEX IF NOT EXISTS types_address ON types (address, module, name);
+    CREATE TABLE IF NOT EXISTS locations (
+        type INTEGER NOT NULL,
+        checkpoint INTEGER NOT NULL,
+        transaction_index INTEGER NOT NULL,
+        event_index INTEGER NOT NULL,
+        PRIMARY KEY (type, checkpoint, transaction_index, event_index)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS checkpoints (
+        sequence_number INTEGER PRIMARY KEY
+    ) WITHOUT ROWID;
+";
+
+/// The locations of the events of every type in the indexed checkpoints, so that a query for
+/// some types only reads the checkpoints holding their events, e.g. from the cache. All the
+/// events of a checkpoint are indexed, whatever the filter of the run, and the indexed
+/// checkpoints are recorded to tell those without events of a type from those not indexed.
+pub struct TypeIndex {
+    connection: Mutex<Connection>,
+}
+
+impl TypeIndex {
+    /// Open the index in the `TYPE_INDEX_FILE` of `cache_folder`, creating it if needed.
+    pub fn open(cache_folder: &Path) -> Result<Self> {
+        std::fs::create_dir_all(cache_folder)?;
+        let connection = Connection::open(cache_folder.join(TYPE_INDEX_FILE))?;
+        // Other processes sharing the folder may be writing
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        connection.execute_batch(TYPE_INDEX_SCHEMA)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Index all the events of checkpoint `sequence_number`, replacing those already indexed.
+    pub fn insert(&self, sequence_number: u64, events: &[EventRecord]) -> Result<()> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        {
+            transaction.execute(
+                "DELETE FROM locations WHERE checkpoint = ?1",
+                params![sequence_number as i64],
+            )?;
+            let mut insert_type = transaction.prepare_cached(
+                "INSERT INTO types (address, module, name, type) VALUES (?1, ?2, ?3, ?4)
+                    ON CONFLICT (type) DO UPDATE SET type = type
+                    RETURNING id",
+            )?;
+            let mut insert_location = transaction.prepare_cached(
+                "INSERT OR IGNORE INTO locations
+                    (type, checkpoint, transaction_index, event_index) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (index, id, event) in events {
+                let type_ = &event.type_;
+                let type_id: i64 = insert_type.query_row(
+                    params![
+                        type_.address.to_canonical_string(true),
+                        type_.module.as_str(),
+                        type_.name.as_str(),
+                        type_.to_canonical_string(true),
+                    ],
+                    |row| row.get(0),
+                )?;
+                insert_location.execute(params![
+                    type_id,
+                    sequence_number as i64,
+                    index.transaction_sequence_number as i64,
+                    id.event_seq as i64,
+                ])?;
+            }
+            transaction.execute(
+                "INSERT OR IGNORE INTO checkpoints (sequence_number) VALUES (?1)",
+                params![sequence_number as i64],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// The locations of the events of the types matching `pattern` in checkpoints
+    /// `start..=end`, in the order they were emitted.
+    pub fn locations(
+        &self,
+        pattern: &EventTypePattern,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<EventLocation>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT checkpoint, transaction_index, event_index
+                FROM locations JOIN types ON types.id = locations.type
+                WHERE address = ?1 AND (?2 IS NULL OR module = ?2) AND (?3 IS NULL OR name = ?3)
+                    AND checkpoint BETWEEN ?4 AND ?5
+                ORDER BY checkpoint, transaction_index, event_index",
+        )?;
+        let locations = statement
+            .query_map(
+                params![
+                    pattern.address.to_canonical_string(true),
+                    pattern.module,
+                    pattern.name,
+                    start as i64,
+                    end as i64,
+                ],
+                |row| {
+                    Ok(EventLocation {
+                        checkpoint: row.get::<_, i64>(0)? as u64,
+                        transaction: row.get::<_, i64>(1)? as u64,
+                        event: row.get::<_, i64>(2)? as u64,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(locations)
+    }
+
+    /// The inclusive ranges of checkpoints of `start..=end` that were not indexed.
+    pub fn missing(&self, start: u64, end: u64) -> Result<Vec<(u64, u64)>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT sequence_number FROM checkpoints
+                WHERE sequence_number BETWEEN ?1 AND ?2 ORDER BY sequence_number",
+        )?;
+        let indexed = statement
+            .query_map(params![start as i64, end as i64], |row| {
+                row.get::<_, i64>(0)
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut missing = Vec::new();
+        let mut next = start;
+        for sequence_number in indexed.into_iter().map(|n| n as u64) {
+            if sequence_number > next {
+                missing.push((next, sequence_number - 1));
+            }
+            next = sequence_number + 1;
+        }
+        if next <= end {
+            missing.push((next, end));
+        }
+        Ok(missing)
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Remove the index of the event types.
+pub fn clear(cache_folder: &Path) -> Result<()> {
+    for suffix in ["", "-wal", "-shm"] {
+        match std::fs::remove_file(cache_folder.join(format!("{TYPE_INDEX_FILE}{suffix}"))) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => (),
+        }
+    }
+    Ok(())
+}